@@ -0,0 +1,288 @@
+use crate::constants::{
+    ADMIN_LOCKOUT_SEC, ADMIN_MAX_FAILED_ATTEMPTS, ADMIN_MAX_LOCKOUT_SEC, ADMIN_PIN_FILENAME,
+    ADMIN_PIN_MIN_LENGTH, ADMIN_UNLOCK_DURATION_SEC,
+};
+use crate::error::ApplicationError;
+use crate::storage;
+
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use chrono::{DateTime, Duration, Local};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+/*管理操作(テストの追加・編集・削除，結果の集計など)を保護する管理者のPIN=====================
+PINはargon2でハッシュ化してtest_list.jsonと同じ場所(admin_pin.json)に保存する
+PINが設定されていれば，unlock()してからADMIN_UNLOCK_DURATION_SEC秒の間だけ管理操作ができる
+PINが設定されていなければ常に管理操作ができる
+PINの入力にADMIN_MAX_FAILED_ATTEMPTS回続けて失敗すると，しばらくの間PINの入力を受け付けない
+*/
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct AdminPinFile {
+    hash: String, // PHC形式のハッシュ(ソルトとパラメータを含む)
+}
+
+#[derive(Debug, Default)]
+pub struct AdminLock {
+    pin_hash: Option<String>,
+    unlocked_until: Option<DateTime<Local>>,
+    failed_attempts: usize,                    // PINの入力に続けて失敗した回数
+    locked_out_until: Option<DateTime<Local>>, // PINの入力を受け付けない期限
+}
+
+// フロントエンドに返すロックの状態
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AdminStatus {
+    pub enabled: bool,                             // PINが設定されているか
+    pub unlocked: bool,                            // 管理操作ができるか
+    pub unlocked_until: Option<DateTime<Local>>,   // ロックを解除している場合は再びロックする時刻
+    pub locked_out_until: Option<DateTime<Local>>, // PINの入力を受け付けない場合はその期限
+}
+
+impl AdminLock {
+    // app_data_rootからPINを読み込む(ファイルがなければPINなし)------------------------------
+    pub fn load(app_data_root: &PathBuf) -> Result<AdminLock> {
+        let pin_path = app_data_root.join(ADMIN_PIN_FILENAME);
+        if storage::exists(&pin_path) == false {
+            return Ok(AdminLock::default());
+        }
+        let pin_file: AdminPinFile = storage::read_json(&pin_path)?;
+        info!("Admin PIN is enabled");
+        Ok(AdminLock {
+            pin_hash: Some(pin_file.hash),
+            ..AdminLock::default()
+        })
+    }
+
+    pub fn status(&self) -> AdminStatus {
+        let unlocked_until = match self.pin_hash {
+            Some(_) => self.unlocked_until.filter(|until| *until > Local::now()),
+            None => None,
+        };
+        AdminStatus {
+            enabled: self.pin_hash.is_some(),
+            unlocked: self.pin_hash.is_none() || unlocked_until.is_some(),
+            unlocked_until: unlocked_until,
+            locked_out_until: self.locked_out_until.filter(|until| *until > Local::now()),
+        }
+    }
+
+    // 管理操作の前に呼ぶ(ロックされていればエラー)-----------------------------------------
+    pub fn check(&self) -> Result<()> {
+        if self.status().unlocked == false {
+            return Err(anyhow!(ApplicationError::AdminLockedError));
+        }
+        Ok(())
+    }
+
+    /*PINを確かめてロックを解除する(解除中に呼ぶと解除の期限を延ばす)----------------------------
+    入力を受け付けない期間中はPINを確かめずにエラーを返す
+    */
+    pub fn unlock(&mut self, pin: &str) -> Result<()> {
+        let pin_hash = match &self.pin_hash {
+            Some(pin_hash) => pin_hash,
+            None => return Ok(()),
+        };
+        let now = Local::now();
+        if let Some(until) = self.locked_out_until.filter(|until| *until > now) {
+            let remaining = (until - now).num_seconds().max(1) as usize;
+            return Err(anyhow!(ApplicationError::AdminLockedOutError(remaining)));
+        }
+        let parsed_hash = PasswordHash::new(pin_hash)
+            .map_err(|e| anyhow!(ApplicationError::InternalError(e.to_string())))?;
+        if Argon2::default()
+            .verify_password(pin.as_bytes(), &parsed_hash)
+            .is_err()
+        {
+            self.record_failure(now);
+            return Err(anyhow!(ApplicationError::InvalidAdminPinError));
+        }
+        self.failed_attempts = 0;
+        self.locked_out_until = None;
+        self.unlocked_until = Some(Local::now() + Duration::seconds(ADMIN_UNLOCK_DURATION_SEC));
+        info!("Admin unlocked until {:?}", self.unlocked_until);
+        Ok(())
+    }
+
+    /*PINの入力の失敗を記録する-----------------------------------------------------------
+    ADMIN_MAX_FAILED_ATTEMPTS回目からは失敗するたびにADMIN_LOCKOUT_SEC秒，その2倍，4倍...の間
+    (ADMIN_MAX_LOCKOUT_SEC秒まで)入力を受け付けない
+    */
+    fn record_failure(&mut self, now: DateTime<Local>) {
+        self.failed_attempts += 1;
+        warn!("Admin unlock failed ({} attempts)", self.failed_attempts);
+        if self.failed_attempts < ADMIN_MAX_FAILED_ATTEMPTS {
+            return;
+        }
+        let exponent = (self.failed_attempts - ADMIN_MAX_FAILED_ATTEMPTS).min(16) as u32;
+        let lockout_sec = (ADMIN_LOCKOUT_SEC * 2_i64.pow(exponent)).min(ADMIN_MAX_LOCKOUT_SEC);
+        self.locked_out_until = Some(now + Duration::seconds(lockout_sec));
+        warn!(
+            "Admin unlock is locked out until {:?}",
+            self.locked_out_until
+        );
+    }
+
+    pub fn lock(&mut self) {
+        self.unlocked_until = None;
+        info!("Admin locked");
+    }
+
+    /*PINを設定・変更する(Noneの場合はPINを削除する)---------------------------------------
+    PINが設定されている場合はロックを解除してから呼ぶ
+    設定したPINでロックを解除した状態になる
+    */
+    pub fn set_pin(&mut self, app_data_root: &PathBuf, pin: Option<String>) -> Result<()> {
+        self.check()?;
+        let pin_path = app_data_root.join(ADMIN_PIN_FILENAME);
+        let pin = match pin {
+            Some(pin) => pin,
+            None => {
+                storage::remove(&pin_path)?;
+                self.pin_hash = None;
+                self.unlocked_until = None;
+                info!("Admin PIN has been removed");
+                return Ok(());
+            }
+        };
+        if pin.chars().count() < ADMIN_PIN_MIN_LENGTH {
+            return Err(anyhow!(ApplicationError::AdminPinFormatError(
+                ADMIN_PIN_MIN_LENGTH
+            )));
+        }
+
+        let salt = SaltString::generate(&mut OsRng);
+        let hash = Argon2::default()
+            .hash_password(pin.as_bytes(), &salt)
+            .map_err(|e| anyhow!(ApplicationError::InternalError(e.to_string())))?
+            .to_string();
+        let pin_file = AdminPinFile { hash: hash.clone() };
+        storage::write_json(&pin_path, &pin_file)?;
+
+        self.pin_hash = Some(hash);
+        self.unlocked_until = Some(Local::now() + Duration::seconds(ADMIN_UNLOCK_DURATION_SEC));
+        info!("Admin PIN has been set");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lock_with_pin(pin: &str) -> AdminLock {
+        let salt = SaltString::generate(&mut OsRng);
+        let hash = Argon2::default()
+            .hash_password(pin.as_bytes(), &salt)
+            .unwrap()
+            .to_string();
+        AdminLock {
+            pin_hash: Some(hash),
+            ..AdminLock::default()
+        }
+    }
+
+    fn is_error(result: Result<()>, expected: fn(&ApplicationError) -> bool) -> bool {
+        match result {
+            Ok(_) => false,
+            Err(e) => e.downcast_ref::<ApplicationError>().map_or(false, expected),
+        }
+    }
+
+    #[test]
+    fn unlocked_without_pin() {
+        let mut admin = AdminLock::default();
+        assert!(admin.status().unlocked);
+        assert!(admin.check().is_ok());
+        assert!(admin.unlock("wrong").is_ok());
+    }
+
+    #[test]
+    fn unlocks_only_with_correct_pin() {
+        let mut admin = lock_with_pin("1234");
+        assert!(is_error(admin.check(), |e| matches!(
+            e,
+            ApplicationError::AdminLockedError
+        )));
+        assert!(is_error(admin.unlock("4321"), |e| matches!(
+            e,
+            ApplicationError::InvalidAdminPinError
+        )));
+        assert!(admin.status().unlocked == false);
+
+        admin.unlock("1234").unwrap();
+        assert!(admin.check().is_ok());
+        let until = admin.status().unlocked_until.unwrap();
+        assert!(until <= Local::now() + Duration::seconds(ADMIN_UNLOCK_DURATION_SEC));
+        assert!(until > Local::now() + Duration::seconds(ADMIN_UNLOCK_DURATION_SEC - 10));
+        assert_eq!(admin.failed_attempts, 0);
+
+        admin.lock();
+        assert!(admin.check().is_err());
+    }
+
+    // 解除の期限を過ぎると再びロックされる
+    #[test]
+    fn relocks_after_expiry() {
+        let mut admin = lock_with_pin("1234");
+        admin.unlocked_until = Some(Local::now() - Duration::seconds(1));
+        let status = admin.status();
+        assert!(status.enabled);
+        assert!(status.unlocked == false);
+        assert_eq!(status.unlocked_until, None);
+        assert!(admin.check().is_err());
+    }
+
+    // 続けて失敗すると正しいPINでも受け付けず，期限を過ぎれば解除できる
+    #[test]
+    fn locks_out_after_repeated_failures() {
+        let mut admin = lock_with_pin("1234");
+        for _ in 0..ADMIN_MAX_FAILED_ATTEMPTS {
+            assert!(admin.unlock("0000").is_err());
+        }
+        assert!(admin.status().locked_out_until.is_some());
+        assert!(is_error(admin.unlock("1234"), |e| matches!(
+            e,
+            ApplicationError::AdminLockedOutError(_)
+        )));
+        assert!(admin.check().is_err());
+
+        admin.locked_out_until = Some(Local::now() - Duration::seconds(1));
+        admin.unlock("1234").unwrap();
+        assert!(admin.check().is_ok());
+        assert_eq!(admin.failed_attempts, 0);
+        assert_eq!(admin.status().locked_out_until, None);
+    }
+
+    // 受け付けない時間は失敗するたびに倍になり，上限で止まる
+    #[test]
+    fn backs_off_exponentially() {
+        let mut admin = lock_with_pin("1234");
+        let now = Local::now();
+        for _ in 0..ADMIN_MAX_FAILED_ATTEMPTS - 1 {
+            admin.record_failure(now);
+        }
+        assert_eq!(admin.locked_out_until, None);
+        admin.record_failure(now);
+        assert_eq!(
+            admin.locked_out_until,
+            Some(now + Duration::seconds(ADMIN_LOCKOUT_SEC))
+        );
+        admin.record_failure(now);
+        assert_eq!(
+            admin.locked_out_until,
+            Some(now + Duration::seconds(ADMIN_LOCKOUT_SEC * 2))
+        );
+        for _ in 0..100 {
+            admin.record_failure(now);
+        }
+        assert_eq!(
+            admin.locked_out_until,
+            Some(now + Duration::seconds(ADMIN_MAX_LOCKOUT_SEC))
+        );
+    }
+}
@@ -1,23 +1,55 @@
-use crate::constants::{TEST_LIST_FILENAME, TEST_MANAGER_DIRNAME, TEST_MANAGER_SETTING_FILENAME};
-use crate::error::ApplicationError;
-use crate::test_manager::TestManager;
-use crate::test_manager::{mos::MosManager, thurstone::ThurstoneManager};
-use crate::test_trial::TrialStatus;
+use crate::admin::{AdminLock, AdminStatus};
+use crate::constants::{
+    TEST_LIST_FILENAME, TEST_MANAGER_DIRNAME, TEST_MANAGER_SETTING_FILENAME,
+    TRASHED_RESULT_FILENAME,
+};
+use crate::error::{invalid_setup, ApplicationError, WithPath};
+use crate::events::{EventSink, JobKind, TestEvent, TrialEvent};
+use crate::identifier::{IdentifierKind, Slug};
+use crate::participant_pool::{ParticipantOverview, ParticipantPool, PoolTestStatus};
+use crate::questionnaire::Answers;
+use crate::recycle_bin::{RecycleBin, TrashEntry, TrashedItem};
+use crate::schema::{self, DocumentKind};
+use crate::storage;
+use crate::test_manager::registry::TestTypeRegistry;
+use crate::test_manager::{
+    relocate_results, ParticipantStatus, TestManager, TestTypeDescription, TrialResult, TrialView,
+};
+use crate::test_trial::{ScoreInput, SessionRecord, TrialProgress, TrialStatus};
 use log::{error, info, warn};
 
 use std::collections::HashMap;
-use std::io::Write;
+use std::fmt;
+use std::fs;
 use std::path::PathBuf;
-use std::{fs, fs::File};
 
 use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
 
-//テストの種類
+//テストの種類のid("Mos"，"Thurstone"など．TestTypeRegistryに登録された種類)
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(transparent)]
+pub struct TestType(String);
+
+impl TestType {
+    pub fn new(id: &str) -> TestType {
+        TestType(id.to_string())
+    }
+}
+
+impl fmt::Display for TestType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+// 読み込みに失敗したテスト(test_listには残し，修復操作を受け付ける)
 #[derive(Serialize, Deserialize, Debug, Clone)]
-pub enum TestType {
-    Mos,
-    Thurstone,
+pub struct BrokenTest {
+    pub name: String,
+    pub test_type: TestType,
+    pub reason: String,   // 読み込みに失敗した理由
+    pub has_backup: bool, // status.jsonのバックアップがあるか
 }
 
 /*アプリケーションをコントロールするマネージャ===================================
@@ -25,13 +57,26 @@ pub enum TestType {
 設定の読み込み：setup()
 新しいテストの追加：add_new_test()
 テストの開始：start_test()
+参加者プールの操作：add_pool_member(), set_pool_group() など
+読み込めなかったテストの修復：reload_broken_test(), remove_broken_test(), restore_test_backup()
+管理者のPIN：set_admin_pin(), unlock_admin(), lock_admin()
+ごみ箱(削除したテストと結果)：get_trash(), restore_trash(), purge_trash(), empty_trash()
+  (テストの追加・編集・削除，参加者プールの操作，修復，結果の集計はロックを解除している間だけ行える)
 
 */
 pub struct ApplicationManager {
     managers: HashMap<String, Box<dyn TestManager>>, //TestManagerのインスタンスを保持
+    registry: TestTypeRegistry,                      //テストの種類ごとのマネージャの生成方法
     app_data_root: PathBuf,
     test_list: HashMap<String, TestType>,
+    broken_tests: HashMap<String, BrokenTest>, //読み込みに失敗したテスト
+    participant_pool: ParticipantPool,
     active_test_name: Option<String>,
+    active_examinee: Option<String>,
+    paused: bool,            //実験者がトライアルを一時停止しているか
+    events: EventSink,       //フロントエンドへのイベントの送り先
+    admin: AdminLock,        //管理操作を保護する管理者のPIN
+    recycle_bin: RecycleBin, //削除したテストとトライアルの結果
 }
 #[allow(dead_code)]
 impl ApplicationManager {
@@ -43,63 +88,115 @@ impl ApplicationManager {
     pub fn setup(app_data_root: PathBuf) -> Result<ApplicationManager> {
         let managers: HashMap<String, Box<dyn TestManager>>;
         let test_list: HashMap<String, TestType>;
+        let broken_tests: HashMap<String, BrokenTest>;
+        let registry = TestTypeRegistry::with_builtin_types();
 
         let test_list_path = app_data_root.join(TEST_LIST_FILENAME);
-        if test_list_path.exists() {
+        if storage::exists(&test_list_path) {
             info!("Load test managers from test list");
-            let json_string = fs::read_to_string(test_list_path)?;
-            test_list = serde_json::from_str(&json_string)?;
-            managers = ApplicationManager::load(&test_list, app_data_root.clone())?;
+            test_list = schema::read_document(&test_list_path, DocumentKind::TestList)?;
+            (managers, broken_tests) =
+                ApplicationManager::load(&registry, &test_list, &app_data_root);
         } else {
             info!("Create new test list");
             ApplicationManager::init(app_data_root.clone())?;
             test_list = HashMap::new();
             managers = HashMap::new();
+            broken_tests = HashMap::new();
         }
 
+        let participant_pool = ParticipantPool::load(&app_data_root)?;
+        let admin = AdminLock::load(&app_data_root)?;
+        let recycle_bin = RecycleBin::load(&app_data_root)?;
+
         info!("Application setup complete");
 
         Ok(ApplicationManager {
             managers: managers,
+            registry: registry,
             app_data_root: app_data_root,
             test_list: test_list,
+            broken_tests: broken_tests,
+            participant_pool: participant_pool,
             active_test_name: None,
+            active_examinee: None,
+            paused: false,
+            events: EventSink::default(),
+            admin: admin,
+            recycle_bin: recycle_bin,
         })
     }
 
     /*作成済みのテストのテストマネージャをインスタンス化------------------------------------
-     */
+    読み込めなかったテストは理由と一緒にbroken_testsに入れて，残りのテストの読み込みを続ける
+    */
     fn load(
+        registry: &TestTypeRegistry,
         test_list: &HashMap<String, TestType>,
-        app_data_root: PathBuf,
-    ) -> Result<HashMap<String, Box<dyn TestManager>>> {
+        app_data_root: &PathBuf,
+    ) -> (
+        HashMap<String, Box<dyn TestManager>>,
+        HashMap<String, BrokenTest>,
+    ) {
         let mut managers: HashMap<String, Box<dyn TestManager>> = HashMap::new();
+        let mut broken_tests: HashMap<String, BrokenTest> = HashMap::new();
 
         for (t_name, t_type) in test_list.iter() {
-            let manager_json_path = app_data_root
-                .join(TEST_MANAGER_DIRNAME)
-                .join(&t_name)
-                .join(TEST_MANAGER_SETTING_FILENAME);
-            let manager =
-                ApplicationManager::load_test_from_json(t_type.clone(), manager_json_path)?;
-            info!("Test loaded: {}", t_name);
-            managers.insert(t_name.clone(), manager);
+            match ApplicationManager::load_test(registry, app_data_root, t_name, t_type) {
+                Ok(manager) => {
+                    info!("Test loaded: {}", t_name);
+                    managers.insert(t_name.clone(), manager);
+                }
+                Err(broken_test) => {
+                    broken_tests.insert(t_name.clone(), broken_test);
+                }
+            }
         }
-        info!("All tests have been loaded");
-        return Ok(managers);
+        info!(
+            "{} tests have been loaded ({} broken)",
+            managers.len(),
+            broken_tests.len()
+        );
+        return (managers, broken_tests);
     }
 
-    //-------------------------------------------------
-    fn load_test_from_json(
-        test_type: TestType,
-        setting_json_path: PathBuf,
-    ) -> Result<Box<dyn TestManager>> {
-        match test_type {
-            TestType::Mos => Ok(Box::new(MosManager::from_json(setting_json_path)?)),
-            TestType::Thurstone => Ok(Box::new(ThurstoneManager::from_json(setting_json_path)?)),
+    // 1つのテストを読み込む．失敗した場合は修復用の情報を返す-------------------------------
+    fn load_test(
+        registry: &TestTypeRegistry,
+        app_data_root: &PathBuf,
+        test_name: &String,
+        test_type: &TestType,
+    ) -> Result<Box<dyn TestManager>, BrokenTest> {
+        let load = || -> Result<Box<dyn TestManager>> {
+            let manager_json_path = ApplicationManager::setting_path(app_data_root, test_name)?;
+            registry.load(test_type, manager_json_path)
+        };
+        match load() {
+            Ok(manager) => Ok(manager),
+            Err(e) => {
+                error!("Failed to load test {}: {:#}", test_name, e);
+                Err(BrokenTest {
+                    name: test_name.clone(),
+                    test_type: test_type.clone(),
+                    reason: format!("{:#}", e),
+                    has_backup: ApplicationManager::setting_path(app_data_root, test_name)
+                        .map_or(false, |path| storage::has_backup(&path)),
+                })
+            }
         }
     }
 
+    fn setting_path(app_data_root: &PathBuf, test_name: &String) -> Result<PathBuf> {
+        Ok(ApplicationManager::test_data_dir(app_data_root, test_name)?
+            .join(TEST_MANAGER_SETTING_FILENAME))
+    }
+
+    // テストのディレクトリ．test_listが書き換えられていてもデータディレクトリの外は指さない
+    fn test_data_dir(app_data_root: &PathBuf, test_name: &String) -> Result<PathBuf> {
+        let slug = Slug::new(test_name, IdentifierKind::Test)?;
+        Ok(app_data_root.join(TEST_MANAGER_DIRNAME).join(slug.as_str()))
+    }
+
     //-------------------------------------------------
     fn init(app_data_root: PathBuf) -> Result<()> {
         info!("initialize this app");
@@ -107,90 +204,444 @@ impl ApplicationManager {
         let test_list_path = app_data_root.join(TEST_LIST_FILENAME);
 
         let test_list: HashMap<String, TestType> = HashMap::new();
-        let json_string = serde_json::to_string_pretty(&test_list)?;
-        let mut file = File::create(&test_list_path)?;
-        file.write_all(json_string.as_bytes())?;
+        schema::write_document(&test_list_path, DocumentKind::TestList, &test_list)?;
         info!("Created new test list file: {:?}", test_list_path);
 
         Ok(())
     }
 
     //-------------------------------------------------
+    /*テストを追加する-----------------------------------------------------------
+    テスト名が一覧にある場合や，一覧から外したテストのディレクトリが残っている場合は
+    ディレクトリを作る前にエラーにする(残っているデータを上書きしない)
+    コピーや保存に失敗した場合は作ったディレクトリを消す
+    */
     pub fn add_test(&mut self, test_type: TestType, json_string: String) -> Result<()> {
-        let new_manager: Box<dyn TestManager> = match test_type {
-            TestType::Mos => Box::new(MosManager::setup(self.app_data_root.clone(), json_string)?),
-            TestType::Thurstone => Box::new(ThurstoneManager::setup(
-                self.app_data_root.clone(),
-                json_string,
-            )?),
-        };
-
-        let new_test_name = new_manager.get_name();
-        if self.managers.contains_key(&new_test_name) {
+        self.admin.check()?;
+        let (json_string, participants) = self.resolve_participants(json_string)?;
+        let new_test_name = ApplicationManager::setup_test_name(&json_string)?;
+        let new_dir = ApplicationManager::test_data_dir(&self.app_data_root, &new_test_name)?;
+        if self.test_list.contains_key(&new_test_name) || new_dir.exists() {
             warn!("Test name has been used: {}", &new_test_name);
             return Err(anyhow!(ApplicationError::AlreadyUsedTestNameError(
                 new_test_name
             )));
         }
 
-        new_manager.copy_categories()?;
-        new_manager.save_setting()?;
+        let mut new_manager = self.events.run_job(JobKind::Validation, None, || {
+            self.registry
+                .setup(&test_type, self.app_data_root.clone(), json_string)
+        })?;
+
+        let result = self
+            .events
+            .run_job(JobKind::Copying, Some(new_test_name.clone()), || {
+                new_manager.copy_categories()
+            })
+            .and_then(|_| new_manager.save_setting());
+        if let Err(e) = result {
+            error!("failed to add test: {}", e);
+            if new_dir.exists() {
+                fs::remove_dir_all(&new_dir).with_path(&new_dir)?;
+            }
+            return Err(e);
+        }
 
         self.managers.insert(new_test_name.clone(), new_manager);
         self.test_list.insert(new_test_name.clone(), test_type);
         self.save_test_list()?;
+        self.participant_pool.absorb(&participants)?;
+        self.participant_pool.save(&self.app_data_root)?;
 
         info!("test added: {}", new_test_name);
+        self.events.test(TestEvent::Added {
+            test_name: new_test_name,
+        });
 
         Ok(())
     }
 
+    // 結果のあるテストを新しいバージョンとして複製する(json_stringには新しいテスト名を指定)------------
+    pub fn fork_test(&mut self, test_name: String, json_string: String) -> Result<()> {
+        self.admin.check()?;
+        let test_type = match self.test_list.get(&test_name) {
+            Some(test_type) => test_type.clone(),
+            None => {
+                error!("Test does not exist: {}", &test_name);
+                return Err(anyhow!(ApplicationError::UnavailableTestError(test_name)));
+            }
+        };
+        let mut info: serde_json::Value =
+            serde_json::from_str(&json_string).map_err(invalid_setup)?;
+        info["forked_from"] = serde_json::Value::String(test_name.clone());
+        self.add_test(test_type, info.to_string())?;
+        info!("test forked: {}", test_name);
+        Ok(())
+    }
+
     pub fn delete_test(&mut self, test_name: String) -> Result<()> {
+        self.admin.check()?;
         if self.managers.contains_key(&test_name) == false {
             warn!("Test does not exist: {}", &test_name);
             return Err(anyhow!(ApplicationError::UnavailableTestError(test_name)));
         }
-        let test_data_dir = self
-            .app_data_root
-            .join(TEST_MANAGER_DIRNAME)
-            .join(&test_name);
+        let test_data_dir = ApplicationManager::test_data_dir(&self.app_data_root, &test_name)?;
         if test_data_dir.exists() == false {
             error!("Test directory does not exist: {:?}", &test_data_dir);
             return Err(anyhow!(ApplicationError::UnavailableTestError(test_name)));
         }
-        fs::remove_dir_all(test_data_dir)?;
+        if self.active_test_name.as_ref() == Some(&test_name) {
+            return Err(anyhow!(ApplicationError::TestInProgressError(test_name)));
+        }
+
+        // テストのディレクトリごとごみ箱に移動
+        let entry = self.recycle_bin.new_entry(
+            &self.app_data_root,
+            TrashedItem::Test {
+                test_name: test_name.clone(),
+                test_type: self.test_list[&test_name].clone(),
+            },
+        )?;
+        let trash_path = RecycleBin::entry_path(&self.app_data_root, &entry.id);
+        fs::rename(&test_data_dir, &trash_path).with_path(&test_data_dir)?;
+        self.recycle_bin.push(entry);
+        self.recycle_bin.save(&self.app_data_root)?;
 
         self.test_list.remove(&test_name);
         self.managers.remove(&test_name);
         self.save_test_list()?;
         info!("test deleted: {}", test_name);
+        self.events.test(TestEvent::Deleted {
+            test_name: test_name,
+        });
+        Ok(())
+    }
+
+    /*テスト名を変更する---------------------------------------------------------
+    ディレクトリを移動してからマネージャの設定と結果ファイルを書き換え，失敗した場合はディレクトリを元に戻す
+    */
+    pub fn rename_test(&mut self, test_name: String, new_name: String) -> Result<()> {
+        self.admin.check()?;
+        if self.managers.contains_key(&test_name) == false {
+            error!("Test does not exist: {}", &test_name);
+            return Err(anyhow!(ApplicationError::UnavailableTestError(test_name)));
+        }
+        let new_slug = Slug::from_display_name(&new_name, IdentifierKind::Test)?;
+        let new_name = new_slug.as_str().to_string();
+        if self.active_test_name.as_ref() == Some(&test_name) {
+            return Err(anyhow!(ApplicationError::TestInProgressError(test_name)));
+        }
+        let old_dir = ApplicationManager::test_data_dir(&self.app_data_root, &test_name)?;
+        let new_dir = ApplicationManager::test_data_dir(&self.app_data_root, &new_name)?;
+        if self.test_list.contains_key(&new_name) || new_dir.exists() {
+            warn!("Test name has been used: {}", &new_name);
+            return Err(anyhow!(ApplicationError::AlreadyUsedTestNameError(
+                new_name
+            )));
+        }
+
+        fs::rename(&old_dir, &new_dir).with_path(&old_dir)?;
+        let result = self
+            .managers
+            .get_mut(&test_name)
+            .unwrap()
+            .rename(new_slug, new_dir.clone());
+        if let Err(e) = result {
+            fs::rename(&new_dir, &old_dir).with_path(&new_dir)?;
+            return Err(e);
+        }
+
+        let manager = self.managers.remove(&test_name).unwrap();
+        self.managers.insert(new_name.clone(), manager);
+        let test_type = self.test_list.remove(&test_name).unwrap();
+        self.test_list.insert(new_name.clone(), test_type);
+        self.save_test_list()?;
+
+        // ごみ箱にあるこのテストの結果もテスト名と音声ファイルのパスを書き換える
+        for id in self.recycle_bin.rename_test(&test_name, &new_name) {
+            let trash_path = RecycleBin::entry_path(&self.app_data_root, &id);
+            relocate_results(&trash_path, &old_dir, &new_dir)?;
+        }
+        self.recycle_bin.save(&self.app_data_root)?;
+        info!("test renamed: {} -> {}", test_name, new_name);
+        self.events.test(TestEvent::Renamed {
+            test_name: test_name,
+            new_name: new_name,
+        });
         Ok(())
     }
 
     pub fn delete_trial(&mut self, test_name: String, examinee: String) -> Result<()> {
+        self.admin.check()?;
         if self.managers.contains_key(&test_name) == false {
             return Err(anyhow!(ApplicationError::UnavailableTestError(test_name)));
         }
-        self.managers
+        // 最後のセッションの結果ファイルをごみ箱に移動
+        let mut entry = self.recycle_bin.new_entry(
+            &self.app_data_root,
+            TrashedItem::Trial {
+                test_name: test_name.clone(),
+                examinee: examinee.clone(),
+                session: None,
+            },
+        )?;
+        let trash_path = RecycleBin::entry_path(&self.app_data_root, &entry.id);
+        fs::create_dir_all(&trash_path).with_path(&trash_path)?;
+        let result = self
+            .managers
             .get_mut(&test_name)
             .unwrap()
-            .delete_trial(examinee.clone())?;
+            .delete_trial(examinee.clone(), &trash_path.join(TRASHED_RESULT_FILENAME));
+        let session = match result {
+            Ok(session) => session,
+            Err(e) => {
+                let _ = fs::remove_dir(&trash_path);
+                return Err(e);
+            }
+        };
+        entry.item = TrashedItem::Trial {
+            test_name: test_name.clone(),
+            examinee: examinee.clone(),
+            session: Some(session),
+        };
+        self.recycle_bin.push(entry);
+        self.recycle_bin.save(&self.app_data_root)?;
+
+        self.events.test(TestEvent::Edited {
+            test_name: test_name,
+        });
+        Ok(())
+    }
+
+    /*ごみ箱=================================================================*/
+    pub fn get_trash(&self) -> Vec<TrashEntry> {
+        self.recycle_bin.list()
+    }
+
+    /*ごみ箱から元に戻す------------------------------------------------------------------
+    テスト：同じ名前のテストがなければディレクトリを戻してテストの一覧に加える
+            (読み込めなければディレクトリをごみ箱に戻してエラー)
+    トライアル：テストが残っていて，参加者がまだ同じ回を受験し直していなければ結果を戻す
+    */
+    pub fn restore_trash(&mut self, id: String) -> Result<()> {
+        self.admin.check()?;
+        let entry = self.recycle_bin.get(&id)?;
+        let trash_path = RecycleBin::entry_path(&self.app_data_root, &id);
+        match entry.item {
+            TrashedItem::Test {
+                test_name,
+                test_type,
+            } => {
+                let test_data_dir =
+                    ApplicationManager::test_data_dir(&self.app_data_root, &test_name)?;
+                if self.test_list.contains_key(&test_name) || test_data_dir.exists() {
+                    warn!("Test name has been used: {}", &test_name);
+                    return Err(anyhow!(ApplicationError::AlreadyUsedTestNameError(
+                        test_name
+                    )));
+                }
+                fs::rename(&trash_path, &test_data_dir).with_path(&trash_path)?;
+                let manager = match ApplicationManager::load_test(
+                    &self.registry,
+                    &self.app_data_root,
+                    &test_name,
+                    &test_type,
+                ) {
+                    Ok(manager) => manager,
+                    Err(broken_test) => {
+                        fs::rename(&test_data_dir, &trash_path).with_path(&test_data_dir)?;
+                        return Err(anyhow!(ApplicationError::TestLoadError(
+                            test_name,
+                            broken_test.reason
+                        )));
+                    }
+                };
+                self.managers.insert(test_name.clone(), manager);
+                self.test_list.insert(test_name.clone(), test_type);
+                self.save_test_list()?;
+                info!("test restored: {}", test_name);
+                self.events.test(TestEvent::Added {
+                    test_name: test_name,
+                });
+            }
+            TrashedItem::Trial {
+                test_name,
+                examinee,
+                session,
+            } => {
+                if self.managers.contains_key(&test_name) == false {
+                    return Err(anyhow!(ApplicationError::UnavailableTestError(test_name)));
+                }
+                if self.active_test_name.as_ref() == Some(&test_name)
+                    && self.active_examinee.as_ref() == Some(&examinee)
+                {
+                    return Err(anyhow!(ApplicationError::TestInProgressError(examinee)));
+                }
+                self.managers.get_mut(&test_name).unwrap().restore_trial(
+                    examinee.clone(),
+                    session.unwrap_or(SessionRecord::unknown(1)),
+                    &trash_path.join(TRASHED_RESULT_FILENAME),
+                )?;
+                fs::remove_dir_all(&trash_path).with_path(&trash_path)?;
+                info!("trial restored: {} / {}", test_name, examinee);
+                self.events.test(TestEvent::Edited {
+                    test_name: test_name,
+                });
+            }
+        }
+        self.recycle_bin.remove(&id);
+        self.recycle_bin.save(&self.app_data_root)?;
+        Ok(())
+    }
+
+    // ごみ箱から完全に削除する----------------------------------------------------------
+    pub fn purge_trash(&mut self, id: String) -> Result<()> {
+        self.admin.check()?;
+        self.recycle_bin.purge(&self.app_data_root, &id)?;
+        self.recycle_bin.save(&self.app_data_root)?;
+        Ok(())
+    }
+
+    pub fn empty_trash(&mut self) -> Result<()> {
+        self.admin.check()?;
+        // 途中で失敗しても削除できた分は一覧から外して保存する
+        let mut result: Result<()> = Ok(());
+        for id in self.recycle_bin.ids() {
+            result = self.recycle_bin.purge(&self.app_data_root, &id);
+            if result.is_err() {
+                break;
+            }
+        }
+        self.recycle_bin.save(&self.app_data_root)?;
+        info!("trash emptied");
+        result
+    }
+
+    // 登録されているテストの種類の一覧------------------------------------------------
+    pub fn get_test_types(&self) -> Vec<TestTypeDescription> {
+        self.registry.describe_all()
+    }
+
+    /*読み込めなかったテストの修復=================================================*/
+    pub fn get_broken_tests(&self) -> Vec<BrokenTest> {
+        let mut broken_tests: Vec<BrokenTest> = self.broken_tests.values().cloned().collect();
+        broken_tests.sort_by(|a, b| a.name.cmp(&b.name));
+        broken_tests
+    }
+
+    // もう一度読み込む(手動でファイルを直した場合など)----------------------------------------
+    pub fn reload_broken_test(&mut self, test_name: String) -> Result<()> {
+        self.admin.check()?;
+        let test_type = match self.broken_tests.get(&test_name) {
+            Some(broken_test) => broken_test.test_type.clone(),
+            None => {
+                return Err(anyhow!(ApplicationError::BrokenTestNotFoundError(
+                    test_name
+                )))
+            }
+        };
+        match ApplicationManager::load_test(
+            &self.registry,
+            &self.app_data_root,
+            &test_name,
+            &test_type,
+        ) {
+            Ok(manager) => {
+                self.broken_tests.remove(&test_name);
+                self.managers.insert(test_name.clone(), manager);
+                info!("broken test reloaded: {}", test_name);
+                Ok(())
+            }
+            Err(broken_test) => {
+                let reason = broken_test.reason.clone();
+                self.broken_tests.insert(test_name.clone(), broken_test);
+                Err(anyhow!(ApplicationError::TestLoadError(test_name, reason)))
+            }
+        }
+    }
+
+    // テストの一覧から外す(データのディレクトリはそのまま残す)---------------------------------
+    pub fn remove_broken_test(&mut self, test_name: String) -> Result<()> {
+        self.admin.check()?;
+        if self.broken_tests.remove(&test_name).is_none() {
+            return Err(anyhow!(ApplicationError::BrokenTestNotFoundError(
+                test_name
+            )));
+        }
+        self.test_list.remove(&test_name);
+        self.save_test_list()?;
+        info!("broken test removed from test list: {}", test_name);
         Ok(())
     }
 
+    // status.jsonをバックアップで置き換えてから読み込み直す-----------------------------------
+    pub fn restore_test_backup(&mut self, test_name: String) -> Result<()> {
+        self.admin.check()?;
+        if self.broken_tests.contains_key(&test_name) == false {
+            return Err(anyhow!(ApplicationError::BrokenTestNotFoundError(
+                test_name
+            )));
+        }
+        let manager_json_path = ApplicationManager::setting_path(&self.app_data_root, &test_name)?;
+        storage::restore_backup(&manager_json_path)?;
+        info!("restore backup: {:?}", manager_json_path);
+        self.reload_broken_test(test_name)
+    }
+
     //-------------------------------------------------
     fn save_test_list(&self) -> Result<()> {
         let test_list_path = self.app_data_root.join(TEST_LIST_FILENAME);
-        let json_string = serde_json::to_string_pretty(&self.test_list)?;
-        let mut file = File::create(test_list_path)?;
-        file.write_all(json_string.as_bytes())?;
+        schema::write_document(&test_list_path, DocumentKind::TestList, &self.test_list)?;
 
         info!("Save test list");
         Ok(())
     }
 
+    //受験前アンケートの回答と同意を記録-------------------------------------------------
+    pub fn register_participant(
+        &mut self,
+        test_name: String,
+        examinee: String,
+        answers: Answers,
+        consent: bool,
+    ) -> Result<()> {
+        if self.managers.contains_key(&test_name) == false {
+            error!("Test does not exist: {}", &test_name);
+            return Err(anyhow!(ApplicationError::UnavailableTestError(test_name)));
+        }
+        self.managers
+            .get_mut(&test_name)
+            .unwrap()
+            .register_participant(examinee, answers, consent)?;
+        Ok(())
+    }
+
+    //受験後アンケートの回答を保存-------------------------------------------------
+    pub fn submit_post_test(
+        &mut self,
+        test_name: String,
+        examinee: String,
+        answers: Answers,
+    ) -> Result<()> {
+        if self.managers.contains_key(&test_name) == false {
+            error!("Test does not exist: {}", &test_name);
+            return Err(anyhow!(ApplicationError::UnavailableTestError(test_name)));
+        }
+        self.managers
+            .get_mut(&test_name)
+            .unwrap()
+            .submit_post_test(examinee, answers)?;
+        Ok(())
+    }
+
     //-------------------------------------------------
     pub fn start_test(&mut self, test_name: String, examinee: String) -> Result<()> {
+        // 他のトライアルやプレビューを実施中なら開始しない
+        if let Some(active_test_name) = &self.active_test_name {
+            return Err(anyhow!(ApplicationError::TestInProgressError(
+                active_test_name.clone()
+            )));
+        }
         if self.managers.contains_key(&test_name) == false {
             error!("Test does not exist: {}", &test_name);
             return Err(anyhow!(ApplicationError::UnavailableTestError(test_name)));
@@ -200,12 +651,25 @@ impl ApplicationManager {
             .unwrap()
             .launch_trial(examinee.clone())?;
         self.active_test_name = Some(test_name.clone());
+        self.active_examinee = Some(examinee.clone());
+        self.paused = false;
+        self.events.trial(TrialEvent::Started {
+            test_name: test_name,
+            examinee: examinee,
+        });
         Ok(())
     }
 
     //-------------------------------------------------
     pub fn close_test(&mut self, examinee: String) -> Result<()> {
-        let test_name = self.active_test_name.as_mut().unwrap().clone();
+        let test_name = self
+            .active_test_name
+            .clone()
+            .ok_or(anyhow!(ApplicationError::NoActiveTrialError))?;
+        // 実施中のトライアルの受験者でなければエラー
+        if self.active_examinee.as_ref() != Some(&examinee) {
+            return Err(anyhow!(ApplicationError::InactiveExamineeError(examinee)));
+        }
         if self.managers.contains_key(&test_name) == false {
             return Err(anyhow!(ApplicationError::UnavailableTestError(test_name)));
         }
@@ -214,10 +678,21 @@ impl ApplicationManager {
             .unwrap()
             .close_trial(examinee.clone())?;
         self.active_test_name = None;
+        self.active_examinee = None;
+        self.paused = false;
+        self.events.trial(TrialEvent::Finished {
+            test_name: test_name,
+            examinee: examinee,
+        });
         Ok(())
     }
 
     pub fn start_preview(&mut self, test_name: String) -> Result<()> {
+        if let Some(active_test_name) = &self.active_test_name {
+            return Err(anyhow!(ApplicationError::TestInProgressError(
+                active_test_name.clone()
+            )));
+        }
         if self.managers.contains_key(&test_name) == false {
             return Err(anyhow!(ApplicationError::UnavailableTestError(test_name)));
         }
@@ -230,7 +705,10 @@ impl ApplicationManager {
     }
 
     pub fn close_preview(&mut self) -> Result<()> {
-        let test_name = self.active_test_name.as_mut().unwrap().clone();
+        let test_name = self
+            .active_test_name
+            .clone()
+            .ok_or(anyhow!(ApplicationError::NoActiveTrialError))?;
         if self.managers.contains_key(&test_name) == false {
             return Err(anyhow!(ApplicationError::UnavailableTestError(test_name)));
         }
@@ -240,31 +718,110 @@ impl ApplicationManager {
     }
 
     pub fn edit_test(&mut self, test_name: String, json_string: String) -> Result<()> {
+        self.admin.check()?;
         if self.managers.contains_key(&test_name) == false {
             error!("Test does not exist: {}", &test_name);
             return Err(anyhow!(ApplicationError::UnavailableTestError(test_name)));
         }
-        self.managers
-            .get_mut(&test_name)
-            .unwrap()
-            .edit(json_string)?;
+        let (json_string, participants) = self.resolve_participants(json_string)?;
+        let manager = self.managers.get_mut(&test_name).unwrap();
+        self.events
+            .run_job(JobKind::Copying, Some(test_name.clone()), || {
+                manager.edit(json_string)
+            })?;
+        self.participant_pool.absorb(&participants)?;
+        self.participant_pool.save(&self.app_data_root)?;
+        self.events.test(TestEvent::Edited {
+            test_name: test_name,
+        });
         Ok(())
     }
 
+    // セットアップ情報のテスト名(ディレクトリ名に使う名前)-------------------------------------
+    fn setup_test_name(json_string: &String) -> Result<String> {
+        let info: serde_json::Value = serde_json::from_str(json_string).map_err(invalid_setup)?;
+        let name: String = serde_json::from_value(info["name"].clone()).map_err(invalid_setup)?;
+        Ok(Slug::from_display_name(&name, IdentifierKind::Test)?.into_string())
+    }
+
+    /*セットアップ情報の参加者グループを展開して参加者のリストに加える---------------------------
+    展開後のセットアップ情報と参加者のリストを返す
+    */
+    fn resolve_participants(&self, json_string: String) -> Result<(String, Vec<String>)> {
+        let mut info: serde_json::Value =
+            serde_json::from_str(&json_string).map_err(invalid_setup)?;
+        let participants: Vec<String> =
+            serde_json::from_value(info["participants"].clone()).map_err(invalid_setup)?;
+        let groups: Vec<String> = match info.get("participant_groups") {
+            Some(groups) => serde_json::from_value(groups.clone()).map_err(invalid_setup)?,
+            None => Vec::new(),
+        };
+        let expanded = self.participant_pool.expand(&participants, &groups)?;
+        info["participants"] = serde_json::to_value(&expanded)?;
+        Ok((info.to_string(), expanded))
+    }
+
     //-------------------------------------------------
-    pub fn get_audio(&mut self) -> Result<Vec<PathBuf>> {
-        let test_name = self.active_test_name.as_mut().unwrap().clone();
+    pub fn get_audio(&mut self) -> Result<Vec<String>> {
+        if self.paused {
+            return Err(anyhow!(ApplicationError::TrialPausedError));
+        }
+        // 受験者が取得しようとした設問は時間切れで進んでいる
+        if self.expire_trial()?.is_some() {
+            return Err(anyhow!(ApplicationError::ItemTimedOutError));
+        }
+        let test_name = self
+            .active_test_name
+            .clone()
+            .ok_or(anyhow!(ApplicationError::NoActiveTrialError))?;
         if self.managers.contains_key(&test_name) == false {
             error!("Test does not exist: {}", test_name);
             return Err(anyhow!(ApplicationError::UnavailableTestError(test_name)));
         }
-        let audio_path = self.managers.get_mut(&test_name).unwrap().get_audio()?;
-        Ok(audio_path)
+        let tokens = self.managers.get_mut(&test_name).unwrap().get_audio()?;
+        Ok(tokens)
+    }
+
+    // 音声配信用のプロトコルから呼ばれる．トークンに対応する音声ファイルのパスを返す----------------
+    pub fn resolve_audio(&mut self, token: &str) -> Result<PathBuf> {
+        let test_name = match &self.active_test_name {
+            Some(name) => name.clone(),
+            None => return Err(anyhow!(ApplicationError::NoActiveTrialError)),
+        };
+        match self.managers.get_mut(&test_name) {
+            Some(manager) => manager.resolve_audio(token),
+            None => Err(anyhow!(ApplicationError::UnavailableTestError(test_name))),
+        }
+    }
+
+    // 受験者側で音声が最後まで再生されたときに呼ばれる(再生回数はバックエンドで数える)------------
+    pub fn report_playback_end(&mut self, token: String) -> Result<()> {
+        if self.paused {
+            return Err(anyhow!(ApplicationError::TrialPausedError));
+        }
+        let test_name = self
+            .active_test_name
+            .clone()
+            .ok_or(anyhow!(ApplicationError::NoActiveTrialError))?;
+        match self.managers.get_mut(&test_name) {
+            Some(manager) => manager.report_playback_end(&token),
+            None => Err(anyhow!(ApplicationError::UnavailableTestError(test_name))),
+        }
     }
 
     //-------------------------------------------------
-    pub fn set_score(&mut self, score: Vec<String>) -> Result<TrialStatus> {
-        let test_name = self.active_test_name.as_mut().unwrap().clone();
+    pub fn set_score(&mut self, input: ScoreInput) -> Result<TrialStatus> {
+        if self.paused {
+            return Err(anyhow!(ApplicationError::TrialPausedError));
+        }
+        // 回答した設問は時間切れで進んでいる(時間切れとして記録済み)
+        if self.expire_trial()?.is_some() {
+            return Err(anyhow!(ApplicationError::ItemTimedOutError));
+        }
+        let test_name = self
+            .active_test_name
+            .clone()
+            .ok_or(anyhow!(ApplicationError::NoActiveTrialError))?;
         if self.managers.contains_key(&test_name) == false {
             error!("Test does not exist: {}", &test_name);
             return Err(anyhow!(ApplicationError::UnavailableTestError(test_name)));
@@ -273,16 +830,262 @@ impl ApplicationManager {
             .managers
             .get_mut(&test_name)
             .unwrap()
-            .set_score(score)?;
+            .set_score(input)?;
+
+        // プレビューでなければ進み具合を通知
+        if let Some(examinee) = &self.active_examinee {
+            let progress = self
+                .managers
+                .get(&test_name)
+                .ok_or(anyhow!(ApplicationError::UnavailableTestError(
+                    test_name.clone()
+                )))?
+                .get_progress()?;
+            self.events.trial(TrialEvent::Progressed {
+                test_name: test_name,
+                examinee: examinee.clone(),
+                progress: progress,
+            });
+        }
+        Ok(status)
+    }
+
+    // Tauriのアプリに接続したときにイベントの送り先を設定する------------------------------
+    pub fn set_event_sink(&mut self, events: EventSink) {
+        self.events = events;
+    }
+
+    // 実施中のトライアルの進み具合を返す(制限時間を過ぎた設問があれば先に処理する)-------------------
+    pub fn get_progress(&mut self) -> Result<TrialProgress> {
+        self.expire_trial()?;
+        let test_name = match &self.active_test_name {
+            Some(name) => name.clone(),
+            None => return Err(anyhow!(ApplicationError::NoActiveTrialError)),
+        };
+        match self.managers.get(&test_name) {
+            Some(manager) => manager.get_progress(),
+            None => Err(anyhow!(ApplicationError::UnavailableTestError(test_name))),
+        }
+    }
+
+    /*制限時間を過ぎた設問とセッションを処理する(プレビューと一時停止中は何もしない)-----------------
+    設問の制限時間を過ぎた場合は時間切れとして記録して次の設問に進み，TimedOutイベントを送る
+    最後の設問やセッションの制限時間を過ぎた場合は，通常どおりトライアルを終了して結果を保存する
+    処理した場合は進んだ後の状態を返す
+    */
+    fn expire_trial(&mut self) -> Result<Option<TrialStatus>> {
+        if self.paused {
+            return Ok(None);
+        }
+        let (test_name, examinee) = match self.active_trial_names() {
+            Ok(names) => names,
+            Err(_) => return Ok(None),
+        };
+        let manager = match self.managers.get_mut(&test_name) {
+            Some(manager) => manager,
+            None => return Err(anyhow!(ApplicationError::UnavailableTestError(test_name))),
+        };
+        let status = match manager.check_expiry()? {
+            Some(status) => status,
+            None => return Ok(None),
+        };
+        match status {
+            TrialStatus::Doing => {
+                let progress = manager.get_progress()?;
+                self.events.trial(TrialEvent::TimedOut {
+                    test_name: test_name,
+                    examinee: examinee,
+                    progress: progress,
+                });
+            }
+            TrialStatus::Done => self.close_test(examinee)?,
+        }
+        Ok(Some(status))
+    }
+
+    /*実験者のコンソールからのトライアルの操作=================================================
+    一時停止中は受験者側から音声の取得と回答ができない
+    中止したトライアルの結果は保存せず，受験者は未受験のままになる
+    */
+    pub fn pause_trial(&mut self) -> Result<()> {
+        let (test_name, examinee) = self.active_trial_names()?;
+        self.paused = true;
+        info!("trial paused: {}", test_name);
+        self.events.trial(TrialEvent::Paused {
+            test_name: test_name,
+            examinee: examinee,
+        });
+        Ok(())
+    }
+
+    pub fn resume_trial(&mut self) -> Result<()> {
+        let (test_name, examinee) = self.active_trial_names()?;
+        self.paused = false;
+        info!("trial resumed: {}", test_name);
+        self.events.trial(TrialEvent::Resumed {
+            test_name: test_name,
+            examinee: examinee,
+        });
+        Ok(())
+    }
+
+    pub fn skip_item(&mut self) -> Result<TrialStatus> {
+        let (test_name, examinee) = self.active_trial_names()?;
+        let manager = match self.managers.get_mut(&test_name) {
+            Some(manager) => manager,
+            None => return Err(anyhow!(ApplicationError::UnavailableTestError(test_name))),
+        };
+        let status = manager.skip_item()?;
+        let progress = manager.get_progress()?;
+        self.events.trial(TrialEvent::Skipped {
+            test_name: test_name,
+            examinee: examinee,
+            progress: progress,
+        });
         Ok(status)
     }
 
+    pub fn abort_trial(&mut self) -> Result<()> {
+        let (test_name, examinee) = self.active_trial_names()?;
+        match self.managers.get_mut(&test_name) {
+            Some(manager) => manager.abort_trial()?,
+            None => return Err(anyhow!(ApplicationError::UnavailableTestError(test_name))),
+        }
+        self.active_test_name = None;
+        self.active_examinee = None;
+        self.paused = false;
+        self.events.trial(TrialEvent::Aborted {
+            test_name: test_name,
+            examinee: examinee,
+        });
+        Ok(())
+    }
+
+    // 受験者用のキオスクに表示する実施中のテストの情報-----------------------------------------
+    pub fn get_trial_view(&self) -> Result<TrialView> {
+        let (test_name, _) = self.active_trial_names()?;
+        match self.managers.get(&test_name) {
+            Some(manager) => Ok(manager.get_trial_view()),
+            None => Err(anyhow!(ApplicationError::UnavailableTestError(test_name))),
+        }
+    }
+
+    // 実施中のトライアルのテスト名と受験者(プレビューは含まない)
+    fn active_trial_names(&self) -> Result<(String, String)> {
+        match (&self.active_test_name, &self.active_examinee) {
+            (Some(test_name), Some(examinee)) => Ok((test_name.clone(), examinee.clone())),
+            _ => Err(anyhow!(ApplicationError::NoActiveTrialError)),
+        }
+    }
+
+    // 読み込めたテストの設定(読み込めなかったテストはget_broken_tests()で返す)
     pub fn get_settings(&self) -> Result<Vec<String>> {
         let mut settings: Vec<String> = Vec::new();
-        for (_name, _) in self.test_list.iter() {
-            let setting = self.managers.get(_name).unwrap().get_setting()?;
-            settings.push(setting);
+        for manager in self.managers.values() {
+            settings.push(manager.get_setting()?);
         }
         Ok(settings)
     }
+
+    //管理者のPIN-------------------------------------------------
+    pub fn get_admin_status(&self) -> AdminStatus {
+        self.admin.status()
+    }
+
+    pub fn unlock_admin(&mut self, pin: String) -> Result<()> {
+        self.admin.unlock(&pin)
+    }
+
+    pub fn lock_admin(&mut self) {
+        self.admin.lock();
+    }
+
+    // pinがNoneの場合はPINを削除する(PINが設定されている場合はロックを解除してから)
+    pub fn set_admin_pin(&mut self, pin: Option<String>) -> Result<()> {
+        self.admin.set_pin(&self.app_data_root, pin)
+    }
+
+    // 集計のためにテストの結果を返す(管理者のPINでロックされていればエラー)-----------------------
+    pub fn get_results(&self, test_name: String) -> Result<Vec<TrialResult>> {
+        self.admin.check()?;
+        match self.managers.get(&test_name) {
+            Some(manager) => manager.get_results(),
+            None => Err(anyhow!(ApplicationError::UnavailableTestError(test_name))),
+        }
+    }
+
+    //参加者プール-------------------------------------------------
+    pub fn get_participant_pool(&self) -> ParticipantPool {
+        self.participant_pool.clone()
+    }
+
+    pub fn add_pool_member(&mut self, name: String) -> Result<()> {
+        self.admin.check()?;
+        self.participant_pool.add_member(name)?;
+        self.participant_pool.save(&self.app_data_root)?;
+        Ok(())
+    }
+
+    pub fn remove_pool_member(&mut self, name: String) -> Result<()> {
+        self.admin.check()?;
+        self.participant_pool.remove_member(&name)?;
+        self.participant_pool.save(&self.app_data_root)?;
+        Ok(())
+    }
+
+    // グループを更新し，そのグループを参照するテストに新しいメンバーを追加する------------------
+    // グループから外れたメンバーは結果が残っている可能性があるためテストからは削除しない
+    pub fn set_pool_group(&mut self, group: String, members: Vec<String>) -> Result<()> {
+        self.admin.check()?;
+        self.participant_pool.set_group(group.clone(), members)?;
+        self.participant_pool.save(&self.app_data_root)?;
+
+        let members = self.participant_pool.get_group_members(&group)?;
+        for (test_name, manager) in self.managers.iter_mut() {
+            if manager.get_participant_groups().contains(&group) {
+                manager.add_participants(members.clone())?;
+                info!("participant group {} synced to {}", &group, test_name);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn delete_pool_group(&mut self, group: String) -> Result<()> {
+        self.admin.check()?;
+        for (test_name, manager) in self.managers.iter() {
+            if manager.get_participant_groups().contains(&group) {
+                return Err(anyhow!(ApplicationError::PoolGroupInUseError(
+                    group,
+                    test_name.clone()
+                )));
+            }
+        }
+        self.participant_pool.delete_group(&group)?;
+        self.participant_pool.save(&self.app_data_root)?;
+        Ok(())
+    }
+
+    // 参加者ごとに，参加者として登録されているテストの状態を返す-----------------------------
+    pub fn get_participant_overview(&self, name: String) -> Result<ParticipantOverview> {
+        if self.participant_pool.contains(&name) == false {
+            return Err(anyhow!(ApplicationError::PoolMemberNotFoundError(name)));
+        }
+        let mut tests: HashMap<String, PoolTestStatus> = HashMap::new();
+        for (test_name, manager) in self.managers.iter() {
+            let in_progress = self.active_test_name.as_ref() == Some(test_name)
+                && self.active_examinee.as_ref() == Some(&name);
+            let status = match manager.get_participant_status(&name) {
+                Some(ParticipantStatus::Done) => PoolTestStatus::Taken,
+                Some(ParticipantStatus::Yet) if in_progress => PoolTestStatus::InProgress,
+                Some(ParticipantStatus::Yet) => PoolTestStatus::Pending,
+                None => continue,
+            };
+            tests.insert(test_name.clone(), status);
+        }
+        Ok(ParticipantOverview {
+            groups: self.participant_pool.get_groups_of(&name),
+            name: name,
+            tests: tests,
+        })
+    }
 }
@@ -0,0 +1,249 @@
+pub mod loudness;
+
+use crate::error::{ApplicationError, WithPath};
+
+use std::fs::File;
+use std::io::ErrorKind;
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use symphonia::core::audio::{AudioBufferRef, SampleBuffer};
+use symphonia::core::codecs::{CodecParameters, DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::{FormatOptions, FormatReader};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+// 音声ファイルのメタデータ=====================================================
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AudioMetadata {
+    pub codec: String,
+    pub sample_rate: u32,
+    pub channels: usize,
+    pub duration_sec: f64,
+    #[serde(default = "default_verified")]
+    pub verified: bool, // 最後までデコードして確認したかどうか
+}
+fn default_verified() -> bool {
+    true
+}
+
+// デコード済みの音声(チャンネルごとのサンプル列, -1.0 ~ 1.0)=================================
+#[derive(Debug, Clone)]
+pub struct DecodedAudio {
+    pub sample_rate: u32,
+    pub channels: Vec<Vec<f32>>,
+}
+
+// 音声ファイルを最後までデコードしてメタデータを取得する-------------------------------------
+// デコードできないファイルや長さが0のファイルはエラー
+// デコーダがないコーデック(Opusなど)はコンテナの情報のみからメタデータを取得する
+pub fn probe_audio(path: &Path) -> Result<AudioMetadata> {
+    let mut sample_rate: u32 = 0;
+    let mut channels: usize = 0;
+    let mut num_frames: u64 = 0;
+    let codec = match decode_each(path, |buffer| {
+        sample_rate = buffer.spec().rate;
+        channels = buffer.spec().channels.count();
+        num_frames += buffer.frames() as u64;
+    }) {
+        Ok(codec) => codec,
+        Err(e) => match e.downcast_ref::<ApplicationError>() {
+            Some(ApplicationError::UndecodableAudioError(_)) => return probe_container(path),
+            _ => return Err(e),
+        },
+    };
+
+    if num_frames == 0 || sample_rate == 0 {
+        return Err(anyhow!(ApplicationError::UndecodableAudioError(
+            path.to_path_buf()
+        )));
+    }
+
+    Ok(AudioMetadata {
+        codec: codec,
+        sample_rate: sample_rate,
+        channels: channels,
+        duration_sec: num_frames as f64 / sample_rate as f64,
+        verified: true,
+    })
+}
+
+// コンテナの情報のみからメタデータを取得する-----------------------------------------------
+fn probe_container(path: &Path) -> Result<AudioMetadata> {
+    let (_, _, params) = open_track(path)?;
+    match (params.sample_rate, params.channels, params.n_frames) {
+        (Some(sample_rate), Some(channels), Some(num_frames)) if num_frames > 0 => {
+            Ok(AudioMetadata {
+                codec: codec_name(&params),
+                sample_rate: sample_rate,
+                channels: channels.count(),
+                duration_sec: num_frames as f64 / sample_rate as f64,
+                verified: false,
+            })
+        }
+        _ => Err(anyhow!(ApplicationError::UndecodableAudioError(
+            path.to_path_buf()
+        ))),
+    }
+}
+
+// 音声ファイル全体をデコードしてサンプル列を返す------------------------------------------
+pub fn decode_audio(path: &Path) -> Result<DecodedAudio> {
+    let mut sample_rate: u32 = 0;
+    let mut channels: Vec<Vec<f32>> = Vec::new();
+    decode_each(path, |buffer| {
+        let spec = *buffer.spec();
+        let num_channels = spec.channels.count();
+        sample_rate = spec.rate;
+        if channels.is_empty() {
+            channels = vec![Vec::new(); num_channels];
+        }
+
+        let mut samples = SampleBuffer::<f32>::new(buffer.capacity() as u64, spec);
+        samples.copy_interleaved_ref(buffer);
+        for frame in samples.samples().chunks(num_channels) {
+            for (c, sample) in frame.iter().enumerate() {
+                channels[c].push(*sample);
+            }
+        }
+    })?;
+
+    if channels.is_empty() || channels[0].is_empty() {
+        return Err(anyhow!(ApplicationError::UndecodableAudioError(
+            path.to_path_buf()
+        )));
+    }
+    Ok(DecodedAudio {
+        sample_rate: sample_rate,
+        channels: channels,
+    })
+}
+
+// 16bit PCMのwavファイルとして書き出す---------------------------------------------
+pub fn write_wav(path: &Path, audio: &DecodedAudio) -> Result<()> {
+    let spec = hound::WavSpec {
+        channels: audio.channels.len() as u16,
+        sample_rate: audio.sample_rate,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(path, spec).with_path(path)?;
+    let num_frames = audio.channels[0].len();
+    for i in 0..num_frames {
+        for channel in &audio.channels {
+            let sample = (channel[i].clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16;
+            writer.write_sample(sample).with_path(path)?;
+        }
+    }
+    writer.finalize().with_path(path)?;
+    Ok(())
+}
+
+// ファイルを先頭からデコードし，デコードしたバッファごとにコールバックを呼ぶ------------------------
+// 戻り値はコーデック名
+fn decode_each<F>(path: &Path, mut on_buffer: F) -> Result<String>
+where
+    F: FnMut(AudioBufferRef),
+{
+    let (mut format, track_id, params) = open_track(path)?;
+    let codec = codec_name(&params);
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&params, &DecoderOptions::default())
+        .with_path(path)?;
+
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            // ストリームの終端
+            Err(SymphoniaError::IoError(e)) if e.kind() == ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e).with_path(path),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        let decoded = decoder.decode(&packet).with_path(path)?;
+        on_buffer(decoded);
+    }
+    Ok(codec)
+}
+
+// ファイルを開いて最初の音声トラックを返す---------------------------------------------
+fn open_track(path: &Path) -> Result<(Box<dyn FormatReader>, u32, CodecParameters)> {
+    let file = File::open(path).with_path(path)?;
+    let source = MediaSourceStream::new(Box::new(file), Default::default());
+
+    // 拡張子は大文字小文字を区別せずにヒントとして渡す
+    let mut hint = Hint::new();
+    if let Some(extension) = path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(&extension.to_lowercase());
+    }
+    let probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            source,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .with_path(path)?;
+    let format = probed.format;
+
+    let track = format
+        .tracks()
+        .iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+        .ok_or(anyhow!(ApplicationError::UndecodableAudioError(
+            path.to_path_buf()
+        )))?;
+    let track_id = track.id;
+    let params = track.codec_params.clone();
+    Ok((format, track_id, params))
+}
+
+fn codec_name(params: &CodecParameters) -> String {
+    symphonia::default::get_codecs()
+        .get_codec(params.codec)
+        .map(|d| d.short_name.to_string())
+        .unwrap_or(format!("{}", params.codec))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn error_path(error: anyhow::Error) -> Option<PathBuf> {
+        match error.downcast_ref::<ApplicationError>() {
+            Some(ApplicationError::UndecodableAudioError(path))
+            | Some(ApplicationError::FileFormatError(path, _))
+            | Some(ApplicationError::FileAccessError(path, _)) => Some(path.clone()),
+            _ => None,
+        }
+    }
+
+    // デコードできないファイルのエラーにはそのファイルのパスが付く
+    #[test]
+    fn reports_path_of_undecodable_audio() {
+        let dir = std::env::temp_dir().join(format!("tloyt_audio_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("broken.wav");
+        std::fs::write(&path, b"not an audio file").unwrap();
+
+        assert_eq!(
+            error_path(probe_audio(&path).unwrap_err()),
+            Some(path.clone())
+        );
+        assert_eq!(
+            error_path(decode_audio(&path).unwrap_err()),
+            Some(path.clone())
+        );
+        let missing = dir.join("missing.wav");
+        assert_eq!(
+            error_path(probe_audio(&missing).unwrap_err()),
+            Some(missing)
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,210 @@
+use crate::audio::DecodedAudio;
+
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+
+// ラウドネス正規化の設定=======================================================
+// target_lufs: 目標の統合ラウドネス(LUFS)，true_peak_ceiling_dbtp: トゥルーピークの上限(dBTP)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LoudnessNormalization {
+    pub target_lufs: f64,
+    pub true_peak_ceiling_dbtp: f64,
+}
+
+// ファイルごとの正規化の記録(無音のファイルは測定値がNone)=====================================
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LoudnessRecord {
+    pub measured_lufs: Option<f64>,
+    pub true_peak_dbtp: Option<f64>,
+    pub gain_db: f64,
+}
+
+// 音声を目標のラウドネスに正規化し，適用したゲインの記録を返す-------------------------------
+// ゲインを適用した後のトゥルーピークが上限を超える場合は，上限に収まるようにゲインを下げる
+pub fn normalize(audio: &mut DecodedAudio, option: &LoudnessNormalization) -> LoudnessRecord {
+    let measured_lufs = integrated_loudness(audio);
+    let true_peak_dbtp = true_peak(audio);
+
+    let mut gain_db = match measured_lufs {
+        Some(lufs) => option.target_lufs - lufs,
+        None => 0.0,
+    };
+    if let Some(peak) = true_peak_dbtp {
+        if peak + gain_db > option.true_peak_ceiling_dbtp {
+            gain_db = option.true_peak_ceiling_dbtp - peak;
+        }
+    }
+
+    let gain = 10f64.powf(gain_db / 20.0) as f32;
+    for channel in audio.channels.iter_mut() {
+        for sample in channel.iter_mut() {
+            *sample *= gain;
+        }
+    }
+
+    LoudnessRecord {
+        measured_lufs: measured_lufs,
+        true_peak_dbtp: true_peak_dbtp,
+        gain_db: gain_db,
+    }
+}
+
+// ITU-R BS.1770に基づく統合ラウドネス(LUFS)-------------------------------------------
+// 全チャンネルの重みは1.0とする(モノラル・ステレオを想定)．無音の場合はNone
+pub fn integrated_loudness(audio: &DecodedAudio) -> Option<f64> {
+    let fs = audio.sample_rate as f64;
+    let filtered: Vec<Vec<f64>> = audio
+        .channels
+        .iter()
+        .map(|channel| k_weighting(channel, fs))
+        .collect();
+
+    // 400msのブロックを75%ずつ重ねて平均二乗を計算
+    let num_samples = filtered[0].len();
+    let block = ((0.4 * fs) as usize).min(num_samples);
+    let step = ((0.1 * fs) as usize).max(1);
+    let mut block_powers: Vec<f64> = Vec::new();
+    let mut start = 0;
+    while start + block <= num_samples {
+        let power: f64 = filtered
+            .iter()
+            .map(|channel| {
+                channel[start..start + block].iter().map(|x| x * x).sum::<f64>() / block as f64
+            })
+            .sum();
+        block_powers.push(power);
+        start += step;
+    }
+
+    // 絶対ゲート(-70 LUFS)
+    let absolute_gated: Vec<f64> = block_powers
+        .into_iter()
+        .filter(|p| to_lufs(*p) > -70.0)
+        .collect();
+    if absolute_gated.is_empty() {
+        return None;
+    }
+
+    // 相対ゲート(絶対ゲート後の平均から-10 LU)
+    let relative_threshold = to_lufs(mean(&absolute_gated)) - 10.0;
+    let relative_gated: Vec<f64> = absolute_gated
+        .into_iter()
+        .filter(|p| to_lufs(*p) > relative_threshold)
+        .collect();
+    if relative_gated.is_empty() {
+        return None;
+    }
+    Some(to_lufs(mean(&relative_gated)))
+}
+
+// 4倍オーバーサンプリングによるトゥルーピーク(dBTP)．無音の場合はNone------------------------------
+pub fn true_peak(audio: &DecodedAudio) -> Option<f64> {
+    const OVERSAMPLING: usize = 4;
+    const HALF_TAPS: isize = 12;
+
+    let mut peak: f64 = 0.0;
+    for channel in &audio.channels {
+        let n = channel.len() as isize;
+        for i in 0..n {
+            peak = peak.max(channel[i as usize].abs() as f64);
+            // サンプル間の値を窓付きsinc関数で補間
+            for phase in 1..OVERSAMPLING {
+                let offset = phase as f64 / OVERSAMPLING as f64;
+                let mut value = 0.0;
+                for m in -HALF_TAPS + 1..=HALF_TAPS {
+                    let j = i + m;
+                    if j < 0 || j >= n {
+                        continue;
+                    }
+                    let t = m as f64 - offset;
+                    let window = 0.5 + 0.5 * (PI * t / HALF_TAPS as f64).cos();
+                    value += channel[j as usize] as f64 * sinc(t) * window;
+                }
+                peak = peak.max(value.abs());
+            }
+        }
+    }
+
+    if peak == 0.0 {
+        return None;
+    }
+    Some(20.0 * peak.log10())
+}
+
+// K特性フィルタ(高域シェルフ + ハイパス)をかける------------------------------------------
+fn k_weighting(channel: &[f32], fs: f64) -> Vec<f64> {
+    let stage1 = Biquad::high_shelf(fs).apply(channel.iter().map(|x| *x as f64));
+    Biquad::high_pass(fs).apply(stage1.into_iter())
+}
+
+fn to_lufs(power: f64) -> f64 {
+    -0.691 + 10.0 * power.log10()
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+fn sinc(x: f64) -> f64 {
+    if x == 0.0 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+// 双二次フィルタ(係数はa0で正規化済み)=================================================
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+}
+impl Biquad {
+    // 頭部の音響的な影響を模擬する高域シェルフ(任意のサンプリング周波数向けに双一次変換で設計)
+    fn high_shelf(fs: f64) -> Biquad {
+        let f0 = 1681.974_450_955_533;
+        let gain_db = 3.999_843_853_973_347;
+        let q = 0.707_175_236_955_419_6;
+
+        let k = (PI * f0 / fs).tan();
+        let vh = 10f64.powf(gain_db / 20.0);
+        let vb = vh.powf(0.499_666_774_154_541_6);
+        let a0 = 1.0 + k / q + k * k;
+        Biquad {
+            b: [
+                (vh + vb * k / q + k * k) / a0,
+                2.0 * (k * k - vh) / a0,
+                (vh - vb * k / q + k * k) / a0,
+            ],
+            a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        }
+    }
+
+    // RLBハイパスフィルタ
+    fn high_pass(fs: f64) -> Biquad {
+        let f0 = 38.135_470_876_024_44;
+        let q = 0.500_327_037_323_877_3;
+
+        let k = (PI * f0 / fs).tan();
+        let a0 = 1.0 + k / q + k * k;
+        Biquad {
+            b: [1.0, -2.0, 1.0],
+            a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        }
+    }
+
+    fn apply<I: Iterator<Item = f64>>(&self, input: I) -> Vec<f64> {
+        let (mut x1, mut x2, mut y1, mut y2) = (0.0, 0.0, 0.0, 0.0);
+        input
+            .map(|x| {
+                let y = self.b[0] * x + self.b[1] * x1 + self.b[2] * x2
+                    - self.a[0] * y1
+                    - self.a[1] * y2;
+                x2 = x1;
+                x1 = x;
+                y2 = y1;
+                y1 = y;
+                y
+            })
+            .collect()
+    }
+}
@@ -1,7 +1,8 @@
 use crate::app::{ApplicationManager, TestType};
-use crate::test_trial::TrialStatus;
+use crate::test_trial::{Answer, PlaybackRecord, ScoreInput, TrialStatus};
 
 use anyhow::Result;
+use chrono::Local;
 use dialoguer::Select;
 use std::path::PathBuf;
 use std::sync::Mutex;
@@ -19,17 +20,35 @@ pub fn cli_test() -> Result<()> {
         .lock()
         .unwrap()
         .start_test("AB".to_string(), "n_ichi".to_string())?;
-    let scores = vec!["0".to_string(), "1".to_string()];
+    let scores = vec!["A".to_string(), "B".to_string()];
     loop {
+        let presented_at = Local::now();
+        let mut playback: Vec<PlaybackRecord> = Vec::new();
+        let mut tokens: Vec<String> = Vec::new();
         for _ in 0..=1 {
             let file = app_manager.lock().unwrap().get_audio()?;
             println!("{:?}", file);
+            tokens = file.clone();
+            playback = file
+                .iter()
+                .map(|_| PlaybackRecord {
+                    play_count: 1,
+                    full_play_count: 1,
+                    played_to_end: true,
+                })
+                .collect();
         }
         let selection = Select::new().items(&scores).interact()?;
-        let status = app_manager
-            .lock()
-            .unwrap()
-            .set_score(vec![scores[selection].clone()])?;
+        let status = app_manager.lock().unwrap().set_score(ScoreInput {
+            answer: Answer::Choice {
+                option: scores[selection].clone(),
+            },
+            tokens: tokens,
+            presented_at: presented_at,
+            answered_at: Local::now(),
+            playback: playback,
+            comment: None,
+        })?;
         match status {
             TrialStatus::Done => break,
             _ => {}
@@ -1,9 +1,56 @@
 pub const TEST_MANAGER_DIRNAME: &str = "test_manager";
 
 pub const CATEGORIES_DIRNAME: &str = "categories";
+pub const CATEGORIES_STAGING_DIRNAME: &str = "categories_staging"; // 編集で追加したカテゴリを一時的に置く
 pub const TRIAL_DIRNAME: &str = "trials";
+pub const PARTICIPANT_DIRNAME: &str = "participants";
+pub const QUESTIONNAIRE_DIRNAME: &str = "questionnaires";
+pub const TRASH_DIRNAME: &str = "trash";
 
 pub const TEST_LIST_FILENAME: &str = "test_list.json";
+pub const PARTICIPANT_POOL_FILENAME: &str = "participant_pool.json";
 pub const TEST_MANAGER_SETTING_FILENAME: &str = "status.json";
+pub const ADMIN_PIN_FILENAME: &str = "admin_pin.json";
+pub const RECYCLE_BIN_FILENAME: &str = "recycle_bin.json";
+pub const TRASHED_RESULT_FILENAME: &str = "result.json";
 
-pub const AVAILABLE_AUDIO_FILE_EXTENTION: [&str; 2] = ["wav", "mp3"];
+pub const AVAILABLE_AUDIO_FILE_EXTENTION: [&str; 10] = [
+    "wav", "mp3", "flac", "ogg", "opus", "aif", "aiff", "aifc", "m4a", "aac",
+];
+
+// Webviewがそのまま再生できる音声ファイルの拡張子(プラットフォームごと)
+#[cfg(target_os = "windows")]
+pub const WEBVIEW_PLAYABLE_AUDIO_FILE_EXTENTION: &[&str] =
+    &["wav", "mp3", "flac", "ogg", "opus", "m4a", "aac"];
+#[cfg(target_os = "macos")]
+pub const WEBVIEW_PLAYABLE_AUDIO_FILE_EXTENTION: &[&str] =
+    &["wav", "mp3", "flac", "aif", "aiff", "aifc", "m4a", "aac"];
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+pub const WEBVIEW_PLAYABLE_AUDIO_FILE_EXTENTION: &[&str] = &["wav", "mp3", "flac", "ogg", "opus"];
+
+// テスト名・カテゴリ名・参加者名の最大文字数(ディレクトリ名・ファイル名に使うため)
+pub const MAX_IDENTIFIER_LENGTH: usize = 100;
+
+// MOSの評価値の段階数(1から5)
+pub const MOS_SCORE_POINTS: usize = 5;
+
+// カテゴリ間で同名の音声ファイルの長さの差がこの値(秒)を超えると警告
+pub const DURATION_MISMATCH_TOLERANCE_SEC: f64 = 0.5;
+// 再生終了の報告を受け付ける，音声の長さに対する時間の誤差(秒)
+pub const PLAYBACK_END_TOLERANCE_SEC: f64 = 0.5;
+
+// テスト音声を配信するカスタムプロトコルのスキーム名
+pub const STIMULUS_PROTOCOL: &str = "stimulus";
+
+// ウィンドウのラベル(実験者のコンソールと受験者用のキオスク)
+pub const EXPERIMENTER_WINDOW_LABEL: &str = "main";
+pub const KIOSK_WINDOW_LABEL: &str = "kiosk";
+
+// 管理者のPINの最小文字数と，PINでロックを解除してから再びロックするまでの時間(秒)
+pub const ADMIN_PIN_MIN_LENGTH: usize = 4;
+pub const ADMIN_UNLOCK_DURATION_SEC: i64 = 600;
+// PINの入力に続けて失敗できる回数と，それを超えた場合に入力を受け付けない時間(秒)
+// 以降は失敗するたびに受け付けない時間を倍にする(ADMIN_MAX_LOCKOUT_SECまで)
+pub const ADMIN_MAX_FAILED_ATTEMPTS: usize = 5;
+pub const ADMIN_LOCKOUT_SEC: i64 = 30;
+pub const ADMIN_MAX_LOCKOUT_SEC: i64 = 3600;
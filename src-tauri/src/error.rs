@@ -1,10 +1,21 @@
-use std::path::PathBuf;
+use crate::test_manager::ValidationReport;
+
+use anyhow::anyhow;
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use std::path::{Path, PathBuf};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum ApplicationError {
     #[error("\'{0}\' has already used")]
     AlreadyUsedTestNameError(String),
+    #[error("Invalid {0} \'{1}\': {2}")]
+    InvalidIdentifierError(String, String, String),
+    #[error("{1} and {2} map to the same {0}")]
+    DuplicateIdentifierError(String, String, String),
+    #[error("{0} is in progress")]
+    TestInProgressError(String),
     #[error("There is no available test: {0}")]
     UnavailableTestError(String),
     #[error("There is no trial data: {0}")]
@@ -17,6 +28,401 @@ pub enum ApplicationError {
     TestDataNotFoundError(PathBuf),
     #[error("Unable to setup category: {0}")]
     InvalidCategoriesError(String),
-    #[error("Different type for set score")]
-    InvalidScoreInputTypeError,
+    #[error("Expected {0} answer, got {1}")]
+    AnswerTypeMismatchError(String, String),
+    #[error("Answer is out of range: {0}")]
+    AnswerOutOfRangeError(String),
+    #[error("Invalid response record: {0}")]
+    InvalidResponseRecordError(String),
+    #[error("Each stimulus must be played to the end at least {0} times")]
+    InsufficientListeningError(usize),
+    #[error("The answered item has timed out")]
+    ItemTimedOutError,
+    #[error("Unable to decode audio file: {0}")]
+    UndecodableAudioError(PathBuf),
+    #[error("Invalid audio files: {0}")]
+    AudioValidationError(ValidationReport),
+    #[error("Unknown audio token: {0}")]
+    InvalidAudioTokenError(String),
+    #[error("Invalid playback report: {0}")]
+    InvalidPlaybackReportError(String),
+    #[error("There is no active trial")]
+    NoActiveTrialError,
+    #[error("{0} is not taking the active trial")]
+    InactiveExamineeError(String),
+    #[error("Invalid answer for question: {0}")]
+    InvalidAnswerError(String),
+    #[error("Answer is required for question: {0}")]
+    MissingAnswerError(String),
+    #[error("Invalid questionnaire definition: '{0}'")]
+    InvalidQuestionnaireError(String),
+    #[error("{0} has not finished test")]
+    UnfinishedTrialError(String),
+    #[error("{0} already has results. Structural changes require a new version of the test")]
+    StructuralEditError(String),
+    #[error("Invalid display text: {0}")]
+    InvalidDisplayTextError(String),
+    #[error("{0} has not given consent")]
+    ConsentRequiredError(String),
+    #[error("\'{0}\' is already in participant pool")]
+    AlreadyUsedPoolMemberError(String),
+    #[error("{0} is not in participant pool")]
+    PoolMemberNotFoundError(String),
+    #[error("There is no participant group: {0}")]
+    PoolGroupNotFoundError(String),
+    #[error("Participant group {0} is used by {1}")]
+    PoolGroupInUseError(String, String),
+    #[error("Invalid document: {0}")]
+    InvalidDocumentError(String),
+    #[error("Schema version {0} is newer than supported version {1}")]
+    UnsupportedSchemaVersionError(u64, u64),
+    #[error("There is no backup of {0}")]
+    BackupNotFoundError(PathBuf),
+    #[error("{0} is not a broken test")]
+    BrokenTestNotFoundError(String),
+    #[error("Failed to load {0}: {1}")]
+    TestLoadError(String, String),
+    #[error("Unknown test type: {0}")]
+    UnknownTestTypeError(String),
+    #[error("Unable to access {0}: {1}")]
+    FileAccessError(PathBuf, String),
+    #[error("Unable to parse {0}: {1}")]
+    FileFormatError(PathBuf, String),
+    #[error("Invalid setup information: {0}")]
+    InvalidSetupError(String),
+    #[error("Window '{0}' is not allowed to call {1}")]
+    UnauthorizedWindowError(String, String),
+    #[error("The trial is paused")]
+    TrialPausedError,
+    #[error("Management operations are locked by the admin PIN")]
+    AdminLockedError,
+    #[error("Admin PIN is incorrect")]
+    InvalidAdminPinError,
+    #[error("Too many failed attempts. Try again in {0} seconds")]
+    AdminLockedOutError(usize),
+    #[error("Admin PIN must be at least {0} characters")]
+    AdminPinFormatError(usize),
+    #[error("There is no such item in the recycle bin: {0}")]
+    TrashEntryNotFoundError(String),
+    #[error("{0}")]
+    InternalError(String),
+}
+
+/*フロントエンドに返すエラー==========================================================
+{"code": "UNAVAILABLE_TEST", "params": ["MOS test"], "message": "...", "message_ja": "..."}
+codeはエラーの種類ごとに固定の文字列なので，フロントエンドはcodeで処理を分ける
+*/
+impl ApplicationError {
+    // エラーのコードと日本語のメッセージ({n}はparamsのn番目で置き換える)------------------
+    fn catalog(&self) -> (&'static str, &'static str) {
+        match self {
+            ApplicationError::AlreadyUsedTestNameError(_) => {
+                ("ALREADY_USED_TEST_NAME", "'{0}'は既に使われています")
+            }
+            ApplicationError::InvalidIdentifierError(..) => {
+                ("INVALID_IDENTIFIER", "'{1}'は名前に使えません: {2}")
+            }
+            ApplicationError::DuplicateIdentifierError(..) => (
+                "DUPLICATE_IDENTIFIER",
+                "'{1}'と'{2}'は同じ名前として保存されるため，一緒に使えません",
+            ),
+            ApplicationError::TestInProgressError(_) => ("TEST_IN_PROGRESS", "{0}は実施中です"),
+            ApplicationError::UnavailableTestError(_) => {
+                ("UNAVAILABLE_TEST", "テストが見つかりません: {0}")
+            }
+            ApplicationError::TrialDataNotFoundError(_) => {
+                ("TRIAL_DATA_NOT_FOUND", "受験データがありません: {0}")
+            }
+            ApplicationError::AlreadyTakenTrialError(_) => {
+                ("ALREADY_TAKEN_TRIAL", "{0}は既に受験済みです")
+            }
+            ApplicationError::UnregisteredParticipantError(..) => (
+                "UNREGISTERED_PARTICIPANT",
+                "{0}は{1}の受験者に登録されていません",
+            ),
+            ApplicationError::TestDataNotFoundError(_) => {
+                ("TEST_DATA_NOT_FOUND", "テストのデータがありません: {0}")
+            }
+            ApplicationError::InvalidCategoriesError(_) => {
+                ("INVALID_CATEGORIES", "カテゴリを設定できません: {0}")
+            }
+            ApplicationError::AnswerTypeMismatchError(..) => (
+                "ANSWER_TYPE_MISMATCH",
+                "{0}形式の回答が必要ですが，{1}形式の回答が送られました",
+            ),
+            ApplicationError::AnswerOutOfRangeError(_) => {
+                ("ANSWER_OUT_OF_RANGE", "回答が範囲外です: {0}")
+            }
+            ApplicationError::InvalidResponseRecordError(_) => {
+                ("INVALID_RESPONSE_RECORD", "回答の記録が不正です: {0}")
+            }
+            ApplicationError::InsufficientListeningError(_) => (
+                "INSUFFICIENT_LISTENING",
+                "各音声を最後まで{0}回以上再生してください",
+            ),
+            ApplicationError::ItemTimedOutError => (
+                "ITEM_TIMED_OUT",
+                "制限時間を過ぎた設問への回答は記録されません",
+            ),
+            ApplicationError::UndecodableAudioError(_) => {
+                ("UNDECODABLE_AUDIO", "音声ファイルを読み込めません: {0}")
+            }
+            ApplicationError::AudioValidationError(_) => {
+                ("AUDIO_VALIDATION", "音声ファイルに問題があります: {0}")
+            }
+            ApplicationError::InvalidAudioTokenError(_) => {
+                ("INVALID_AUDIO_TOKEN", "不明な音声です: {0}")
+            }
+            ApplicationError::InvalidPlaybackReportError(_) => {
+                ("INVALID_PLAYBACK_REPORT", "再生の記録が正しくありません: {0}")
+            }
+            ApplicationError::NoActiveTrialError => {
+                ("NO_ACTIVE_TRIAL", "実施中のテストがありません")
+            }
+            ApplicationError::InactiveExamineeError(_) => {
+                ("INACTIVE_EXAMINEE", "{0}は実施中のテストの受験者ではありません")
+            }
+            ApplicationError::InvalidAnswerError(_) => ("INVALID_ANSWER", "回答が不正です: {0}"),
+            ApplicationError::MissingAnswerError(_) => ("MISSING_ANSWER", "回答が必要です: {0}"),
+            ApplicationError::InvalidQuestionnaireError(_) => {
+                ("INVALID_QUESTIONNAIRE", "アンケートの設定が不正です: '{0}'")
+            }
+            ApplicationError::UnfinishedTrialError(_) => {
+                ("UNFINISHED_TRIAL", "{0}はテストを終えていません")
+            }
+            ApplicationError::StructuralEditError(_) => (
+                "STRUCTURAL_EDIT",
+                "{0}には結果があるため，構成を変えるには新しいバージョンとして複製する必要があります",
+            ),
+            ApplicationError::InvalidDisplayTextError(_) => {
+                ("INVALID_DISPLAY_TEXT", "表示する文言が不正です: {0}")
+            }
+            ApplicationError::ConsentRequiredError(_) => {
+                ("CONSENT_REQUIRED", "{0}の同意が得られていません")
+            }
+            ApplicationError::AlreadyUsedPoolMemberError(_) => {
+                ("ALREADY_USED_POOL_MEMBER", "'{0}'は既に参加者プールにいます")
+            }
+            ApplicationError::PoolMemberNotFoundError(_) => {
+                ("POOL_MEMBER_NOT_FOUND", "{0}は参加者プールにいません")
+            }
+            ApplicationError::PoolGroupNotFoundError(_) => {
+                ("POOL_GROUP_NOT_FOUND", "参加者グループがありません: {0}")
+            }
+            ApplicationError::PoolGroupInUseError(..) => {
+                ("POOL_GROUP_IN_USE", "参加者グループ{0}は{1}で使われています")
+            }
+            ApplicationError::InvalidDocumentError(_) => {
+                ("INVALID_DOCUMENT", "ファイルの形式が不正です: {0}")
+            }
+            ApplicationError::UnsupportedSchemaVersionError(..) => (
+                "UNSUPPORTED_SCHEMA_VERSION",
+                "ファイルのバージョン{0}は対応しているバージョン{1}より新しいです",
+            ),
+            ApplicationError::BackupNotFoundError(_) => {
+                ("BACKUP_NOT_FOUND", "{0}のバックアップがありません")
+            }
+            ApplicationError::BrokenTestNotFoundError(_) => (
+                "BROKEN_TEST_NOT_FOUND",
+                "{0}は読み込みに失敗したテストではありません",
+            ),
+            ApplicationError::TestLoadError(..) => {
+                ("TEST_LOAD", "{0}の読み込みに失敗しました: {1}")
+            }
+            ApplicationError::UnknownTestTypeError(_) => {
+                ("UNKNOWN_TEST_TYPE", "不明なテストの種類です: {0}")
+            }
+            ApplicationError::FileAccessError(..) => {
+                ("FILE_ACCESS", "ファイルにアクセスできません: {0} ({1})")
+            }
+            ApplicationError::FileFormatError(..) => {
+                ("FILE_FORMAT", "ファイルを読み込めません: {0} ({1})")
+            }
+            ApplicationError::InvalidSetupError(_) => {
+                ("INVALID_SETUP", "セットアップ情報が不正です: {0}")
+            }
+            ApplicationError::UnauthorizedWindowError(..) => (
+                "UNAUTHORIZED_WINDOW",
+                "このウィンドウからは{1}を実行できません",
+            ),
+            ApplicationError::TrialPausedError => ("TRIAL_PAUSED", "テストは一時停止中です"),
+            ApplicationError::AdminLockedError => (
+                "ADMIN_LOCKED",
+                "管理者のPINでロックを解除してください",
+            ),
+            ApplicationError::InvalidAdminPinError => ("INVALID_ADMIN_PIN", "PINが違います"),
+            ApplicationError::AdminLockedOutError(_) => (
+                "ADMIN_LOCKED_OUT",
+                "PINの入力に続けて失敗しました．{0}秒後に再度入力してください",
+            ),
+            ApplicationError::AdminPinFormatError(_) => {
+                ("ADMIN_PIN_FORMAT", "PINは{0}文字以上で指定してください")
+            }
+            ApplicationError::TrashEntryNotFoundError(_) => {
+                ("TRASH_ENTRY_NOT_FOUND", "ごみ箱に見つかりません: {0}")
+            }
+            ApplicationError::InternalError(_) => ("INTERNAL", "内部エラーが発生しました: {0}"),
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        self.catalog().0
+    }
+
+    // メッセージに埋め込む値-----------------------------------------------------------
+    pub fn params(&self) -> Vec<String> {
+        match self {
+            ApplicationError::AlreadyUsedTestNameError(s)
+            | ApplicationError::TestInProgressError(s)
+            | ApplicationError::UnavailableTestError(s)
+            | ApplicationError::AlreadyTakenTrialError(s)
+            | ApplicationError::InvalidCategoriesError(s)
+            | ApplicationError::AnswerOutOfRangeError(s)
+            | ApplicationError::InvalidResponseRecordError(s)
+            | ApplicationError::InvalidAudioTokenError(s)
+            | ApplicationError::InvalidPlaybackReportError(s)
+            | ApplicationError::InactiveExamineeError(s)
+            | ApplicationError::InvalidAnswerError(s)
+            | ApplicationError::MissingAnswerError(s)
+            | ApplicationError::InvalidQuestionnaireError(s)
+            | ApplicationError::UnfinishedTrialError(s)
+            | ApplicationError::StructuralEditError(s)
+            | ApplicationError::InvalidDisplayTextError(s)
+            | ApplicationError::ConsentRequiredError(s)
+            | ApplicationError::AlreadyUsedPoolMemberError(s)
+            | ApplicationError::PoolMemberNotFoundError(s)
+            | ApplicationError::PoolGroupNotFoundError(s)
+            | ApplicationError::InvalidDocumentError(s)
+            | ApplicationError::BrokenTestNotFoundError(s)
+            | ApplicationError::UnknownTestTypeError(s)
+            | ApplicationError::InvalidSetupError(s)
+            | ApplicationError::TrashEntryNotFoundError(s)
+            | ApplicationError::InternalError(s) => vec![s.clone()],
+            ApplicationError::UnregisteredParticipantError(a, b)
+            | ApplicationError::AnswerTypeMismatchError(a, b)
+            | ApplicationError::PoolGroupInUseError(a, b)
+            | ApplicationError::TestLoadError(a, b)
+            | ApplicationError::UnauthorizedWindowError(a, b) => vec![a.clone(), b.clone()],
+            ApplicationError::InvalidIdentifierError(kind, name, reason) => {
+                vec![kind.clone(), name.clone(), reason.clone()]
+            }
+            ApplicationError::DuplicateIdentifierError(kind, name, other) => {
+                vec![kind.clone(), name.clone(), other.clone()]
+            }
+            ApplicationError::TrialDataNotFoundError(path)
+            | ApplicationError::TestDataNotFoundError(path)
+            | ApplicationError::UndecodableAudioError(path)
+            | ApplicationError::BackupNotFoundError(path) => vec![path.display().to_string()],
+            ApplicationError::FileAccessError(path, reason)
+            | ApplicationError::FileFormatError(path, reason) => {
+                vec![path.display().to_string(), reason.clone()]
+            }
+            ApplicationError::InsufficientListeningError(n)
+            | ApplicationError::AdminPinFormatError(n)
+            | ApplicationError::AdminLockedOutError(n) => vec![n.to_string()],
+            ApplicationError::UnsupportedSchemaVersionError(version, current) => {
+                vec![version.to_string(), current.to_string()]
+            }
+            ApplicationError::AudioValidationError(report) => vec![report.to_string()],
+            ApplicationError::NoActiveTrialError
+            | ApplicationError::ItemTimedOutError
+            | ApplicationError::TrialPausedError
+            | ApplicationError::AdminLockedError
+            | ApplicationError::InvalidAdminPinError => Vec::new(),
+        }
+    }
+
+    pub fn message_ja(&self) -> String {
+        let mut message = self.catalog().1.to_string();
+        for (i, param) in self.params().iter().enumerate() {
+            message = message.replace(&format!("{{{}}}", i), param);
+        }
+        message
+    }
+}
+
+impl Serialize for ApplicationError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("ApplicationError", 4)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("params", &self.params())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.serialize_field("message_ja", &self.message_ja())?;
+        state.end()
+    }
+}
+
+// 内部のエラーをフロントエンドに返すエラーに変換(ApplicationError以外は内部エラーとする)------
+impl From<anyhow::Error> for ApplicationError {
+    fn from(error: anyhow::Error) -> ApplicationError {
+        match error.downcast::<ApplicationError>() {
+            Ok(e) => e,
+            Err(error) => ApplicationError::InternalError(format!("{:#}", error)),
+        }
+    }
+}
+
+/*io・serde・音声のエラーに対象のファイルのパスを付ける=====================================
+fs::read_to_string(&path).with_path(&path)? のように使う
+*/
+pub trait WithPath<T> {
+    fn with_path(self, path: &Path) -> anyhow::Result<T>;
+}
+
+impl<T> WithPath<T> for Result<T, std::io::Error> {
+    fn with_path(self, path: &Path) -> anyhow::Result<T> {
+        self.map_err(|e| {
+            anyhow!(ApplicationError::FileAccessError(
+                path.to_path_buf(),
+                e.to_string()
+            ))
+        })
+    }
+}
+
+impl<T> WithPath<T> for Result<T, serde_json::Error> {
+    fn with_path(self, path: &Path) -> anyhow::Result<T> {
+        self.map_err(|e| {
+            anyhow!(ApplicationError::FileFormatError(
+                path.to_path_buf(),
+                e.to_string()
+            ))
+        })
+    }
+}
+
+impl<T> WithPath<T> for Result<T, hound::Error> {
+    fn with_path(self, path: &Path) -> anyhow::Result<T> {
+        self.map_err(|e| {
+            anyhow!(ApplicationError::FileAccessError(
+                path.to_path_buf(),
+                e.to_string()
+            ))
+        })
+    }
+}
+
+// デコーダがないコーデックや形式はデコードできない音声，それ以外は壊れたファイルとして扱う
+impl<T> WithPath<T> for Result<T, symphonia::core::errors::Error> {
+    fn with_path(self, path: &Path) -> anyhow::Result<T> {
+        use symphonia::core::errors::Error as SymphoniaError;
+        self.map_err(|e| match e {
+            SymphoniaError::IoError(e) => anyhow!(ApplicationError::FileAccessError(
+                path.to_path_buf(),
+                e.to_string()
+            )),
+            SymphoniaError::Unsupported(_) => {
+                anyhow!(ApplicationError::UndecodableAudioError(path.to_path_buf()))
+            }
+            e => anyhow!(ApplicationError::FileFormatError(
+                path.to_path_buf(),
+                e.to_string()
+            )),
+        })
+    }
+}
+
+// フロントエンドから送られたセットアップ情報(JSON)のエラー
+pub fn invalid_setup(error: serde_json::Error) -> anyhow::Error {
+    anyhow!(ApplicationError::InvalidSetupError(error.to_string()))
 }
@@ -0,0 +1,203 @@
+use crate::constants::{EXPERIMENTER_WINDOW_LABEL, KIOSK_WINDOW_LABEL};
+use crate::test_trial::TrialProgress;
+
+use anyhow::Result;
+use log::warn;
+use serde::Serialize;
+use serde_json::Value;
+
+/*フロントエンドに通知するイベント=====================================================
+複数のウィンドウ(実験者のモニタと受験者の画面など)が同じ状態を表示できるように，
+テスト・トライアル・時間のかかる処理の変化をイベントとして実験者のウィンドウに送る
+受験者用のキオスクには，受験者名やテスト名を除いたトライアルのイベントだけを送る
+*/
+pub const TEST_EVENT: &str = "test-changed";
+pub const TRIAL_EVENT: &str = "trial-changed";
+pub const JOB_EVENT: &str = "job-status";
+
+// テストの追加・編集・削除・名前の変更--------------------------------------------------
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TestEvent {
+    Added { test_name: String },
+    Edited { test_name: String },
+    Deleted { test_name: String },
+    Renamed { test_name: String, new_name: String },
+}
+
+// トライアルの開始・回答ごとの進み具合・終了(プレビューは通知しない)------------------------
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TrialEvent {
+    Started {
+        test_name: String,
+        examinee: String,
+    },
+    Progressed {
+        test_name: String,
+        examinee: String,
+        progress: TrialProgress,
+    },
+    Finished {
+        test_name: String,
+        examinee: String,
+    },
+    // 実験者のコンソールからの操作
+    Paused {
+        test_name: String,
+        examinee: String,
+    },
+    Resumed {
+        test_name: String,
+        examinee: String,
+    },
+    Skipped {
+        test_name: String,
+        examinee: String,
+        progress: TrialProgress,
+    },
+    // 設問の制限時間を過ぎて次の設問に進んだ(トライアルが終わる場合はFinished)
+    TimedOut {
+        test_name: String,
+        examinee: String,
+        progress: TrialProgress,
+    },
+    Aborted {
+        test_name: String,
+        examinee: String,
+    },
+}
+
+// キオスクに送るトライアルのイベント(受験者名とテスト名を含めない)----------------------------
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum KioskTrialEvent {
+    Started,
+    Progressed { progress: TrialProgress },
+    Finished,
+    Paused,
+    Resumed,
+    Skipped { progress: TrialProgress },
+    TimedOut { progress: TrialProgress },
+    Aborted,
+}
+
+impl From<&TrialEvent> for KioskTrialEvent {
+    fn from(event: &TrialEvent) -> KioskTrialEvent {
+        match event {
+            TrialEvent::Started { .. } => KioskTrialEvent::Started,
+            TrialEvent::Progressed { progress, .. } => KioskTrialEvent::Progressed {
+                progress: progress.clone(),
+            },
+            TrialEvent::Finished { .. } => KioskTrialEvent::Finished,
+            TrialEvent::Paused { .. } => KioskTrialEvent::Paused,
+            TrialEvent::Resumed { .. } => KioskTrialEvent::Resumed,
+            TrialEvent::Skipped { progress, .. } => KioskTrialEvent::Skipped {
+                progress: progress.clone(),
+            },
+            TrialEvent::TimedOut { progress, .. } => KioskTrialEvent::TimedOut {
+                progress: progress.clone(),
+            },
+            TrialEvent::Aborted { .. } => KioskTrialEvent::Aborted,
+        }
+    }
+}
+
+// 時間のかかる処理の状態---------------------------------------------------------------
+// copying: 音声ファイルのテストへのコピー・変換，validation: 音声ファイルの検証
+// 結果のエクスポートはまだコマンドがない(結果はget_resultsで集計画面に渡すのみ)ので種類に含めていない．
+// エクスポートを追加するときはExportを加えてrun_jobで実行する
+#[derive(Serialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum JobKind {
+    Copying,
+    Validation,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum JobStatus {
+    Running,
+    Succeeded,
+    Failed { error: String },
+}
+
+// test_nameは対象のテスト(テストの作成前の検証ではNone)
+#[derive(Serialize, Debug, Clone)]
+pub struct JobEvent {
+    pub job: JobKind,
+    pub test_name: Option<String>,
+    #[serde(flatten)]
+    pub status: JobStatus,
+}
+
+/*イベントの送り先===================================================================
+emitには送り先のウィンドウのラベル，イベント名，内容を渡す
+Tauriのアプリに接続するまで(CLIから使う場合など)は何も送らない
+*/
+type EmitFn = Box<dyn Fn(&str, &str, Value) + Send + Sync>;
+
+#[derive(Default)]
+pub struct EventSink {
+    emit: Option<EmitFn>,
+}
+
+impl EventSink {
+    pub fn new(emit: impl Fn(&str, &str, Value) + Send + Sync + 'static) -> EventSink {
+        EventSink {
+            emit: Some(Box::new(emit)),
+        }
+    }
+
+    pub fn test(&self, event: TestEvent) {
+        self.send(EXPERIMENTER_WINDOW_LABEL, TEST_EVENT, &event);
+    }
+
+    pub fn trial(&self, event: TrialEvent) {
+        self.send(
+            KIOSK_WINDOW_LABEL,
+            TRIAL_EVENT,
+            &KioskTrialEvent::from(&event),
+        );
+        self.send(EXPERIMENTER_WINDOW_LABEL, TRIAL_EVENT, &event);
+    }
+
+    // 処理の開始と終了(成功・失敗)を通知しながら実行する-------------------------------------
+    pub fn run_job<T>(
+        &self,
+        job: JobKind,
+        test_name: Option<String>,
+        f: impl FnOnce() -> Result<T>,
+    ) -> Result<T> {
+        self.job(job, test_name.clone(), JobStatus::Running);
+        let result = f();
+        let status = match &result {
+            Ok(_) => JobStatus::Succeeded,
+            Err(e) => JobStatus::Failed {
+                error: e.to_string(),
+            },
+        };
+        self.job(job, test_name, status);
+        result
+    }
+
+    fn job(&self, job: JobKind, test_name: Option<String>, status: JobStatus) {
+        let event = JobEvent {
+            job: job,
+            test_name: test_name,
+            status: status,
+        };
+        self.send(EXPERIMENTER_WINDOW_LABEL, JOB_EVENT, &event);
+    }
+
+    fn send<T: Serialize>(&self, window_label: &str, name: &str, event: &T) {
+        let emit = match &self.emit {
+            Some(emit) => emit,
+            None => return,
+        };
+        match serde_json::to_value(event) {
+            Ok(payload) => emit(window_label, name, payload),
+            Err(e) => warn!("failed to serialize {} event: {}", name, e),
+        }
+    }
+}
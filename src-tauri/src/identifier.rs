@@ -0,0 +1,229 @@
+use crate::constants::MAX_IDENTIFIER_LENGTH;
+use crate::error::ApplicationError;
+
+use std::collections::HashMap;
+use std::fmt;
+
+use anyhow::{anyhow, Result};
+
+// ファイル・ディレクトリ名に使う名前の種類(エラーメッセージ用)
+#[derive(Debug, Clone, Copy)]
+pub enum IdentifierKind {
+    Test,
+    Category,
+    Participant,
+}
+
+impl fmt::Display for IdentifierKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IdentifierKind::Test => write!(f, "test name"),
+            IdentifierKind::Category => write!(f, "category name"),
+            IdentifierKind::Participant => write!(f, "participant name"),
+        }
+    }
+}
+
+// Windowsで予約されているデバイス名(拡張子が付いていても使えない)
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+const FORBIDDEN_CHARS: [char; 9] = ['/', '\\', ':', '*', '?', '"', '<', '>', '|'];
+
+/*ファイルシステム上で安全に使える名前==================================================
+テスト名・カテゴリ名・参加者名はそのままディレクトリ名やファイル名になるので，
+入力された表示名から空白をアンダーバーに置き換えて作り，パスとして危険な名前は拒否する
+(パス区切りや..でデータディレクトリの外を指す名前，Windowsで使えない文字・予約名など)
+入力された表示名(前後の空白を除いたもの)も保持し，設定に保存して画面に表示できるようにする
+*/
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Slug {
+    slug: String,
+    display_name: String,
+}
+
+impl Slug {
+    // 保存済みの名前をそのまま検証する(変換はしない)
+    pub fn new(name: &str, kind: IdentifierKind) -> Result<Slug> {
+        Slug::check(name).map_err(|reason| {
+            anyhow!(ApplicationError::InvalidIdentifierError(
+                kind.to_string(),
+                name.to_string(),
+                reason
+            ))
+        })?;
+        Ok(Slug {
+            slug: name.to_string(),
+            display_name: name.to_string(),
+        })
+    }
+
+    // 入力された表示名から作る(前後の空白を除き，空白をアンダーバーに置き換える)
+    pub fn from_display_name(display_name: &str, kind: IdentifierKind) -> Result<Slug> {
+        let display_name = display_name.trim();
+        let slug = display_name.replace(" ", "_");
+        Slug::check(&slug).map_err(|reason| {
+            anyhow!(ApplicationError::InvalidIdentifierError(
+                kind.to_string(),
+                display_name.to_string(),
+                reason
+            ))
+        })?;
+        Ok(Slug {
+            slug: slug,
+            display_name: display_name.to_string(),
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.slug
+    }
+
+    pub fn display_name(&self) -> &str {
+        &self.display_name
+    }
+
+    pub fn into_string(self) -> String {
+        self.slug
+    }
+
+    /*同じ名前になる入力がないか確かめる-----------------------------------------------------
+    "A B"と"A_B"のように変換後に同じになる名前や，大文字と小文字だけが違う名前
+    (WindowsとmacOSでは同じディレクトリを指す)はエラー
+    */
+    pub fn check_unique(slugs: &[Slug], kind: IdentifierKind) -> Result<()> {
+        let mut seen: HashMap<String, &Slug> = HashMap::new();
+        for slug in slugs {
+            if let Some(other) = seen.insert(slug.slug.to_lowercase(), slug) {
+                return Err(anyhow!(ApplicationError::DuplicateIdentifierError(
+                    kind.to_string(),
+                    other.display_name.clone(),
+                    slug.display_name.clone()
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    // 安全でない理由を返す
+    fn check(slug: &str) -> Result<(), String> {
+        if slug.is_empty() {
+            return Err("name is empty".to_string());
+        }
+        if slug.chars().count() > MAX_IDENTIFIER_LENGTH {
+            return Err(format!(
+                "name is longer than {} characters",
+                MAX_IDENTIFIER_LENGTH
+            ));
+        }
+        if slug == "." || slug == ".." {
+            return Err("name refers to a directory".to_string());
+        }
+        if let Some(c) = slug
+            .chars()
+            .find(|c| FORBIDDEN_CHARS.contains(c) || c.is_control())
+        {
+            return Err(format!("name contains forbidden character {:?}", c));
+        }
+        if slug.ends_with('.') {
+            return Err("name ends with '.'".to_string());
+        }
+        let stem = slug.split('.').next().unwrap_or(slug).to_uppercase();
+        if RESERVED_NAMES.contains(&stem.as_str()) {
+            return Err(format!("{} is reserved by the system", stem));
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Slug {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.slug)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reason(slug: &str) -> String {
+        Slug::check(slug).unwrap_err()
+    }
+
+    #[test]
+    fn accepts_ordinary_names() {
+        assert!(Slug::check("listening_test").is_ok());
+        assert!(Slug::check("テスト1").is_ok());
+        assert!(Slug::check("v1.2").is_ok());
+        assert!(Slug::check("CONSOLE").is_ok());
+    }
+
+    #[test]
+    fn rejects_reserved_names() {
+        assert!(reason("CON").contains("reserved"));
+        assert!(reason("nul").contains("reserved"));
+        assert!(reason("com1.wav").contains("reserved"));
+        assert!(reason("LPT9").contains("reserved"));
+    }
+
+    #[test]
+    fn rejects_directory_references() {
+        assert!(reason(".").contains("directory"));
+        assert!(reason("..").contains("directory"));
+        assert!(Slug::from_display_name(" .. ", IdentifierKind::Test).is_err());
+    }
+
+    #[test]
+    fn rejects_trailing_dot() {
+        assert!(reason("name.").contains("ends with"));
+    }
+
+    #[test]
+    fn rejects_forbidden_characters() {
+        for c in FORBIDDEN_CHARS {
+            let name = format!("a{}b", c);
+            assert!(reason(&name).contains("forbidden"), "{}", name);
+        }
+        assert!(reason("../outside").contains("forbidden"));
+        assert!(reason("a\tb").contains("forbidden"));
+    }
+
+    #[test]
+    fn rejects_empty_and_long_names() {
+        assert!(reason("").contains("empty"));
+        assert!(Slug::from_display_name("   ", IdentifierKind::Participant).is_err());
+        let longest = "a".repeat(MAX_IDENTIFIER_LENGTH);
+        assert!(Slug::check(&longest).is_ok());
+        let too_long = "a".repeat(MAX_IDENTIFIER_LENGTH + 1);
+        assert!(reason(&too_long).contains("longer"));
+    }
+
+    #[test]
+    fn keeps_display_name() {
+        let slug = Slug::from_display_name(" Taro Yamada ", IdentifierKind::Participant).unwrap();
+        assert_eq!(slug.as_str(), "Taro_Yamada");
+        assert_eq!(slug.display_name(), "Taro Yamada");
+    }
+
+    #[test]
+    fn rejects_names_that_collide_after_slugging() {
+        let slugs: Vec<Slug> = ["A B", "A_B"]
+            .iter()
+            .map(|n| Slug::from_display_name(n, IdentifierKind::Category).unwrap())
+            .collect();
+        assert!(Slug::check_unique(&slugs, IdentifierKind::Category).is_err());
+
+        let slugs: Vec<Slug> = ["Taro", "taro"]
+            .iter()
+            .map(|n| Slug::from_display_name(n, IdentifierKind::Participant).unwrap())
+            .collect();
+        assert!(Slug::check_unique(&slugs, IdentifierKind::Participant).is_err());
+
+        let slugs: Vec<Slug> = ["Taro", "Hanako"]
+            .iter()
+            .map(|n| Slug::from_display_name(n, IdentifierKind::Participant).unwrap())
+            .collect();
+        assert!(Slug::check_unique(&slugs, IdentifierKind::Participant).is_ok());
+    }
+}
@@ -1,16 +1,28 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod admin;
 mod app;
+mod audio;
 mod cli;
 mod constants;
 mod error;
+mod events;
+mod identifier;
+mod participant_pool;
+mod protocol;
+mod questionnaire;
+mod recycle_bin;
+mod schema;
+mod storage;
 mod tauri_commands;
 mod test_manager;
 mod test_trial;
 
 use crate::app::ApplicationManager;
-use log::info;
+use crate::constants::STIMULUS_PROTOCOL;
+use crate::events::EventSink;
+use log::{info, warn};
 use std::sync::Mutex;
 use tauri::Manager;
 use tauri_plugin_log::LogTarget;
@@ -19,35 +31,81 @@ fn main() {
     tauri::Builder::default()
         .plugin(
             tauri_plugin_log::Builder::new()
-                .targets([LogTarget::Stdout, LogTarget::Webview, LogTarget::LogDir])
+                // Webviewにはログを流さない(カテゴリ名などが受験者に見えないようにする)
+                .targets([LogTarget::Stdout, LogTarget::LogDir])
                 .build(),
         )
         .setup(|app| {
             info!("Application start");
             // let app_data_root = data_dir().unwrap().join("TLOYT");
             let app_data_root = app.handle().path_resolver().app_data_dir().unwrap();
-            let app_manager_raw = ApplicationManager::setup(app_data_root)?;
+            let mut app_manager_raw = ApplicationManager::setup(app_data_root)?;
+            // バックエンドの状態の変化をウィンドウごとに通知する(開いていないウィンドウには送らない)
+            let handle = app.handle();
+            app_manager_raw.set_event_sink(EventSink::new(move |label, event, payload| {
+                if handle.get_window(label).is_none() {
+                    return;
+                }
+                if let Err(e) = handle.emit_to(label, event, payload) {
+                    warn!("failed to emit {} to {}: {}", event, label, e);
+                }
+            }));
             let app_manager = Mutex::new(app_manager_raw);
             app.manage(app_manager);
 
-            // 開発時だけdevtoolsを表示する。
+            // 開発時だけ実験者のウィンドウにdevtoolsを表示する(キオスクには表示しない)。
             #[cfg(debug_assertions)]
             app.get_window("main").unwrap().open_devtools();
 
             Ok(())
         })
+        .register_uri_scheme_protocol(STIMULUS_PROTOCOL, protocol::stimulus_protocol)
         .invoke_handler(tauri::generate_handler![
             tauri_commands::add_test,
             tauri_commands::delete_test,
             tauri_commands::edit_test,
+            tauri_commands::fork_test,
+            tauri_commands::rename_test,
+            tauri_commands::register_participant,
+            tauri_commands::submit_post_test,
             tauri_commands::start_test,
             tauri_commands::close_test,
             tauri_commands::get_audio,
+            tauri_commands::report_playback_end,
             tauri_commands::set_score,
+            tauri_commands::get_progress,
             tauri_commands::get_settings,
             tauri_commands::delete_trial,
             tauri_commands::start_preview,
             tauri_commands::close_preview,
+            tauri_commands::get_participant_pool,
+            tauri_commands::add_pool_member,
+            tauri_commands::remove_pool_member,
+            tauri_commands::set_pool_group,
+            tauri_commands::delete_pool_group,
+            tauri_commands::get_participant_overview,
+            tauri_commands::get_broken_tests,
+            tauri_commands::reload_broken_test,
+            tauri_commands::remove_broken_test,
+            tauri_commands::restore_test_backup,
+            tauri_commands::get_test_types,
+            tauri_commands::pause_trial,
+            tauri_commands::resume_trial,
+            tauri_commands::skip_item,
+            tauri_commands::abort_trial,
+            tauri_commands::get_trial_view,
+            tauri_commands::open_kiosk,
+            tauri_commands::pick_directory,
+            tauri_commands::close_kiosk,
+            tauri_commands::get_admin_status,
+            tauri_commands::unlock_admin,
+            tauri_commands::lock_admin,
+            tauri_commands::set_admin_pin,
+            tauri_commands::get_results,
+            tauri_commands::get_trash,
+            tauri_commands::restore_trash,
+            tauri_commands::purge_trash,
+            tauri_commands::empty_trash,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,172 @@
+use crate::constants::PARTICIPANT_POOL_FILENAME;
+use crate::error::ApplicationError;
+use crate::identifier::{IdentifierKind, Slug};
+use crate::storage;
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use log::info;
+use serde::{Deserialize, Serialize};
+
+/*アプリケーション全体で共有する実験参加者のプール=====================================
+members: 登録済みの参加者名，groups: グループ名 -> 所属する参加者名
+テストの参加者はプールのメンバーやグループを参照して設定できる
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ParticipantPool {
+    members: Vec<String>,
+    groups: HashMap<String, Vec<String>>,
+}
+
+// 参加者から見たテストの状態
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum PoolTestStatus {
+    Pending,    //未受験
+    InProgress, //受験中
+    Taken,      //受験済み
+}
+
+// 参加者ごとの一覧(所属グループと，参加者として登録されているテストの状態)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ParticipantOverview {
+    pub name: String,
+    pub groups: Vec<String>,
+    pub tests: HashMap<String, PoolTestStatus>,
+}
+
+impl ParticipantPool {
+    // app_data_rootからプールを読み込む(ファイルがなければ空のプール)-------------------------
+    pub fn load(app_data_root: &PathBuf) -> Result<ParticipantPool> {
+        let pool_path = app_data_root.join(PARTICIPANT_POOL_FILENAME);
+        if storage::exists(&pool_path) == false {
+            info!("Create new participant pool");
+            return Ok(ParticipantPool::default());
+        }
+        let pool: ParticipantPool = storage::read_json(&pool_path)?;
+        Ok(pool)
+    }
+
+    // test_list.jsonと同じ場所に保存---------------------------------------------------
+    pub fn save(&self, app_data_root: &PathBuf) -> Result<()> {
+        let pool_path = app_data_root.join(PARTICIPANT_POOL_FILENAME);
+        storage::write_json(&pool_path, &self)?;
+        info!("Save participant pool");
+        Ok(())
+    }
+
+    pub fn add_member(&mut self, name: String) -> Result<()> {
+        let name = ParticipantPool::normalize(&name)?;
+        if self.members.contains(&name) {
+            return Err(anyhow!(ApplicationError::AlreadyUsedPoolMemberError(name)));
+        }
+        self.members.push(name);
+        Ok(())
+    }
+
+    // メンバーを削除し，所属するグループからも外す(テストの参加者からは削除しない)---------------------
+    pub fn remove_member(&mut self, name: &String) -> Result<()> {
+        if self.members.contains(name) == false {
+            return Err(anyhow!(ApplicationError::PoolMemberNotFoundError(
+                name.clone()
+            )));
+        }
+        self.members.retain(|m| m != name);
+        for members in self.groups.values_mut() {
+            members.retain(|m| m != name);
+        }
+        Ok(())
+    }
+
+    // グループを作成または上書きする．メンバーはプールに登録済みである必要あり-----------------------
+    pub fn set_group(&mut self, group: String, members: Vec<String>) -> Result<()> {
+        let mut new_members: Vec<String> = Vec::new();
+        for member in members.iter().map(ParticipantPool::normalize) {
+            let member = member?;
+            if self.members.contains(&member) == false {
+                return Err(anyhow!(ApplicationError::PoolMemberNotFoundError(member)));
+            }
+            if new_members.contains(&member) == false {
+                new_members.push(member);
+            }
+        }
+        self.groups.insert(group, new_members);
+        Ok(())
+    }
+
+    pub fn delete_group(&mut self, group: &String) -> Result<()> {
+        match self.groups.remove(group) {
+            Some(_) => Ok(()),
+            None => Err(anyhow!(ApplicationError::PoolGroupNotFoundError(
+                group.clone()
+            ))),
+        }
+    }
+
+    pub fn get_group_members(&self, group: &String) -> Result<Vec<String>> {
+        match self.groups.get(group) {
+            Some(members) => Ok(members.clone()),
+            None => Err(anyhow!(ApplicationError::PoolGroupNotFoundError(
+                group.clone()
+            ))),
+        }
+    }
+
+    // 参加者名とグループを展開して重複のない参加者名のリストにする--------------------------------
+    // 直接入力された参加者名は表示名のまま残し，既に含まれている参加者と同じグループのメンバーは除く
+    // (変換後に同じ名前になる異なる入力はテストの設定で拒否する)
+    pub fn expand(&self, participants: &Vec<String>, groups: &Vec<String>) -> Result<Vec<String>> {
+        let mut expanded: Vec<String> = Vec::new();
+        let mut slugs: Vec<String> = Vec::new();
+        for name in participants {
+            let name = name.trim().to_string();
+            if expanded.contains(&name) == false {
+                slugs.push(ParticipantPool::normalize(&name)?);
+                expanded.push(name);
+            }
+        }
+        for group in groups {
+            for member in self.get_group_members(group)? {
+                if slugs.contains(&member) == false {
+                    slugs.push(member.clone());
+                    expanded.push(member);
+                }
+            }
+        }
+        Ok(expanded)
+    }
+
+    // テストの設定で直接入力された参加者をプールに追加する----------------------------------------
+    pub fn absorb(&mut self, names: &Vec<String>) -> Result<()> {
+        for name in names.iter().map(ParticipantPool::normalize) {
+            let name = name?;
+            if self.members.contains(&name) == false {
+                info!("add participant to pool: {}", &name);
+                self.members.push(name);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn contains(&self, name: &String) -> bool {
+        self.members.contains(name)
+    }
+
+    // 参加者が所属するグループ名のリスト------------------------------------------------
+    pub fn get_groups_of(&self, name: &String) -> Vec<String> {
+        let mut groups: Vec<String> = self
+            .groups
+            .iter()
+            .filter(|(_, members)| members.contains(name))
+            .map(|(group, _)| group.clone())
+            .collect();
+        groups.sort();
+        groups
+    }
+
+    // テストマネージャと同じくファイル名に使える名前にする(空白はアンダースコアに置き換える)
+    fn normalize(name: &String) -> Result<String> {
+        Ok(Slug::from_display_name(name, IdentifierKind::Participant)?.into_string())
+    }
+}
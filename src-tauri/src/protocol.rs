@@ -0,0 +1,69 @@
+use crate::app::ApplicationManager;
+use crate::error::WithPath;
+
+use log::{error, info};
+use std::error::Error;
+use std::fs;
+use std::sync::Mutex;
+use tauri::http::{Request, Response, ResponseBuilder};
+use tauri::{AppHandle, Manager, Url};
+
+/*テスト音声を配信するカスタムプロトコルのハンドラ=========================================
+stimulus://localhost/<トークン> (Windowsでは https://stimulus.localhost/<トークン>)
+トークンはアクティブなトライアルの現在の設問にのみ有効．ファイルパスやカテゴリ名はフロントエンドに渡らない
+*/
+pub fn stimulus_protocol(app: &AppHandle, request: &Request) -> Result<Response, Box<dyn Error>> {
+    let url = Url::parse(request.uri())?;
+    let token = url
+        .path_segments()
+        .and_then(|segments| segments.last())
+        .unwrap_or("")
+        .to_string();
+
+    let app_manager = app.state::<Mutex<ApplicationManager>>();
+    let result = app_manager.lock().unwrap().resolve_audio(&token);
+    let path = match result {
+        Ok(path) => path,
+        Err(e) => {
+            error!("stimulus request rejected: {}", e);
+            return ResponseBuilder::new().status(404).body(Vec::new());
+        }
+    };
+
+    let body = fs::read(&path).with_path(&path)?;
+    info!("stimulus served: {}", token);
+    ResponseBuilder::new()
+        .status(200)
+        .mimetype(mimetype(&body))
+        .header("Access-Control-Allow-Origin", "*")
+        .body(body)
+}
+
+// ファイルの先頭のバイト列からMIMEタイプを決める(拡張子はトークンにもレスポンスにも出さない)----------
+fn mimetype(body: &[u8]) -> &'static str {
+    let head = |range: std::ops::Range<usize>| body.get(range).unwrap_or(&[]);
+    if head(0..4) == b"RIFF" && head(8..12) == b"WAVE" {
+        "audio/wav"
+    } else if head(0..4) == b"fLaC" {
+        "audio/flac"
+    } else if head(0..4) == b"OggS" {
+        "audio/ogg"
+    } else if head(0..4) == b"FORM" && (head(8..12) == b"AIFF" || head(8..12) == b"AIFC") {
+        "audio/aiff"
+    } else if head(4..8) == b"ftyp" {
+        "audio/mp4"
+    } else if head(0..3) == b"ID3" {
+        "audio/mpeg"
+    } else if let [0xFF, second, ..] = body {
+        // フレーム同期．layerが0ならAACのADTS，それ以外はMPEGオーディオ
+        if second & 0xF6 == 0xF0 {
+            "audio/aac"
+        } else if second & 0xE0 == 0xE0 {
+            "audio/mpeg"
+        } else {
+            "application/octet-stream"
+        }
+    } else {
+        "application/octet-stream"
+    }
+}
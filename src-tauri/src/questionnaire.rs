@@ -0,0 +1,123 @@
+use crate::error::{ApplicationError, WithPath};
+use crate::storage;
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local};
+use log::info;
+use serde::{Deserialize, Serialize};
+
+// 質問id -> 回答 の対応
+pub type Answers = HashMap<String, String>;
+
+// 質問の形式===============================================================
+// Text: 自由記述，Choice: 選択肢から1つ，YesNo: "yes" か "no"
+// Likert: 1からpointsまでの段階評価(min_label, max_labelは両端の説明)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum QuestionKind {
+    Text,
+    Choice(Vec<String>),
+    YesNo,
+    Likert {
+        points: usize,
+        min_label: String,
+        max_label: String,
+    },
+}
+
+// アンケートの質問===========================================================
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Question {
+    pub id: String,
+    pub label: String,
+    pub kind: QuestionKind,
+    pub required: bool,
+}
+
+// 回答が質問の定義に合っているかを検証する-----------------------------------------------
+pub fn validate_answers(questions: &Vec<Question>, answers: &Answers) -> Result<()> {
+    // 定義されていない質問への回答はエラー
+    for id in answers.keys() {
+        if questions.iter().any(|q| &q.id == id) == false {
+            return Err(anyhow!(ApplicationError::InvalidAnswerError(id.clone())));
+        }
+    }
+
+    for question in questions {
+        let answer = match answers.get(&question.id) {
+            Some(a) if a.is_empty() == false => a,
+            _ => {
+                if question.required {
+                    return Err(anyhow!(ApplicationError::MissingAnswerError(
+                        question.id.clone()
+                    )));
+                }
+                continue;
+            }
+        };
+        let valid = match &question.kind {
+            QuestionKind::Text => true,
+            QuestionKind::Choice(options) => options.contains(answer),
+            QuestionKind::YesNo => answer == "yes" || answer == "no",
+            QuestionKind::Likert { points, .. } => match answer.parse::<usize>() {
+                Ok(point) => 1 <= point && point <= *points,
+                Err(_) => false,
+            },
+        };
+        if valid == false {
+            return Err(anyhow!(ApplicationError::InvalidAnswerError(
+                question.id.clone()
+            )));
+        }
+    }
+    Ok(())
+}
+
+// 質問のidの重複や選択肢の不備がないかを検証する(テストのセットアップ時)-------------------------------
+pub fn validate_questions(questions: &Vec<Question>) -> Result<()> {
+    for (i, question) in questions.iter().enumerate() {
+        let valid_kind = match &question.kind {
+            QuestionKind::Choice(options) => options.is_empty() == false,
+            QuestionKind::Likert { points, .. } => *points >= 2,
+            _ => true,
+        };
+        if valid_kind == false
+            || question.id.is_empty()
+            || questions[..i].iter().any(|q| q.id == question.id)
+        {
+            return Err(anyhow!(ApplicationError::InvalidQuestionnaireError(
+                question.id.clone()
+            )));
+        }
+    }
+    Ok(())
+}
+
+// 受験後アンケートの回答の記録=================================================
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct QuestionnaireResponse {
+    answered_at: DateTime<Local>,
+    answers: Answers,
+}
+impl QuestionnaireResponse {
+    pub fn new(answers: Answers) -> QuestionnaireResponse {
+        QuestionnaireResponse {
+            answered_at: Local::now(),
+            answers: answers,
+        }
+    }
+
+    // 回答をdirectory/<key>.jsonに保存----------------------------------------------
+    pub fn save(&self, directory: &PathBuf, key: &String) -> Result<()> {
+        if directory.exists() == false {
+            fs::create_dir_all(directory).with_path(directory)?;
+        }
+        let path = directory.join(format!("{}.json", key));
+        storage::write_json(&path, &self)?;
+        info!("save questionnaire response: {:?}", &path);
+        Ok(())
+    }
+}
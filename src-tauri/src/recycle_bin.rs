@@ -0,0 +1,149 @@
+use crate::app::TestType;
+use crate::constants::{RECYCLE_BIN_FILENAME, TRASH_DIRNAME};
+use crate::error::{ApplicationError, WithPath};
+use crate::storage;
+use crate::test_trial::SessionRecord;
+
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local};
+use log::info;
+use serde::{Deserialize, Serialize};
+
+/*削除したテストとトライアルの結果を置いておくごみ箱==========================================
+削除したデータはtrash/<id>に移動し，一覧をtest_list.jsonと同じ場所(recycle_bin.json)に保存する
+テスト：テストのディレクトリをtrash/<id>に移動
+トライアル：結果ファイルをtrash/<id>/result.jsonに移動
+*/
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TrashedItem {
+    Test {
+        test_name: String,
+        test_type: TestType,
+    },
+    Trial {
+        test_name: String,
+        examinee: String,
+        #[serde(default)]
+        session: Option<SessionRecord>, // 何回目の受験の結果か(Noneは1回目)
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TrashEntry {
+    pub id: String,
+    pub deleted_at: DateTime<Local>,
+    #[serde(flatten)]
+    pub item: TrashedItem,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct RecycleBin {
+    entries: Vec<TrashEntry>,
+}
+
+impl RecycleBin {
+    // app_data_rootからごみ箱の一覧を読み込む(ファイルがなければ空)-----------------------------
+    pub fn load(app_data_root: &PathBuf) -> Result<RecycleBin> {
+        let bin_path = app_data_root.join(RECYCLE_BIN_FILENAME);
+        if storage::exists(&bin_path) == false {
+            return Ok(RecycleBin::default());
+        }
+        let bin: RecycleBin = storage::read_json(&bin_path)?;
+        Ok(bin)
+    }
+
+    pub fn save(&self, app_data_root: &PathBuf) -> Result<()> {
+        let bin_path = app_data_root.join(RECYCLE_BIN_FILENAME);
+        storage::write_json(&bin_path, &self)?;
+        info!("Save recycle bin");
+        Ok(())
+    }
+
+    // 新しい項目を作る(まだ一覧には加えない)．idは削除した時刻-------------------------------
+    pub fn new_entry(&self, app_data_root: &PathBuf, item: TrashedItem) -> Result<TrashEntry> {
+        let trash_root = app_data_root.join(TRASH_DIRNAME);
+        fs::create_dir_all(&trash_root).with_path(&trash_root)?;
+
+        let deleted_at = Local::now();
+        let base_id = deleted_at.format("%Y%m%d-%H%M%S%3f").to_string();
+        let mut id = base_id.clone();
+        let mut n = 1;
+        while self.entries.iter().any(|e| e.id == id) || trash_root.join(&id).exists() {
+            id = format!("{}-{}", base_id, n);
+            n += 1;
+        }
+        Ok(TrashEntry {
+            id: id,
+            deleted_at: deleted_at,
+            item: item,
+        })
+    }
+
+    // 項目のデータの置き場所
+    pub fn entry_path(app_data_root: &PathBuf, id: &str) -> PathBuf {
+        app_data_root.join(TRASH_DIRNAME).join(id)
+    }
+
+    pub fn push(&mut self, entry: TrashEntry) {
+        info!("moved to trash: {:?}", &entry);
+        self.entries.push(entry);
+    }
+
+    pub fn get(&self, id: &str) -> Result<TrashEntry> {
+        match self.entries.iter().find(|e| e.id == id) {
+            Some(entry) => Ok(entry.clone()),
+            None => Err(anyhow!(ApplicationError::TrashEntryNotFoundError(
+                id.to_string()
+            ))),
+        }
+    }
+
+    pub fn remove(&mut self, id: &str) {
+        self.entries.retain(|e| e.id != id);
+    }
+
+    // 新しく削除したものから順に返す---------------------------------------------------
+    pub fn list(&self) -> Vec<TrashEntry> {
+        let mut entries = self.entries.clone();
+        entries.sort_by(|a, b| b.deleted_at.cmp(&a.deleted_at));
+        entries
+    }
+
+    // 完全に削除する(データも消す)--------------------------------------------------------
+    pub fn purge(&mut self, app_data_root: &PathBuf, id: &str) -> Result<()> {
+        self.get(id)?;
+        let path = RecycleBin::entry_path(app_data_root, id);
+        if path.exists() {
+            fs::remove_dir_all(&path).with_path(&path)?;
+        }
+        self.remove(id);
+        info!("purged from trash: {}", id);
+        Ok(())
+    }
+
+    pub fn ids(&self) -> Vec<String> {
+        self.entries.iter().map(|e| e.id.clone()).collect()
+    }
+
+    // テスト名の変更に合わせて，そのテストのトライアルの項目のテスト名を書き換える-----------------
+    // 書き換えた項目のidを返す
+    pub fn rename_test(&mut self, test_name: &String, new_name: &String) -> Vec<String> {
+        let mut renamed: Vec<String> = Vec::new();
+        for entry in self.entries.iter_mut() {
+            if let TrashedItem::Trial {
+                test_name: name, ..
+            } = &mut entry.item
+            {
+                if name == test_name {
+                    *name = new_name.clone();
+                    renamed.push(entry.id.clone());
+                }
+            }
+        }
+        renamed
+    }
+}
@@ -0,0 +1,281 @@
+use crate::error::ApplicationError;
+use crate::storage;
+
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use log::info;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/*保存するJSONファイルのスキーマのバージョン管理=========================================
+各ファイルはトップレベルにschema_versionを持つ(schema_versionのない古いファイルはバージョン0)
+読み込み時は古いバージョンから順にマイグレーションを適用して現在の形式にしてからデシリアライズする
+形式を変えるときは，DocumentKind::migrationsに1つ前のバージョンから変換する関数を追加する
+*/
+pub const SCHEMA_VERSION_KEY: &str = "schema_version";
+const RESULT_PAYLOAD_KEY: &str = "scores";
+
+// バージョン管理するファイルの種類
+#[derive(Debug, Clone, Copy)]
+pub enum DocumentKind {
+    TestList,         // test_list.json
+    MosSetting,       // MOSのstatus.json
+    ThurstoneSetting, // サーストン法のstatus.json
+    MosResult,        // MOSのtrials/<参加者>.json
+    ThurstoneResult,  // サーストン法のtrials/<参加者>.json
+}
+
+// バージョンnのドキュメントをバージョンn+1に変換する関数
+type Migration = fn(Value) -> Result<Value>;
+
+impl DocumentKind {
+    // migrations()[n]がバージョンnからn+1への変換
+    fn migrations(&self) -> Vec<Migration> {
+        match self {
+            DocumentKind::TestList | DocumentKind::MosSetting | DocumentKind::ThurstoneSetting => {
+                vec![add_version_field]
+            }
+            DocumentKind::MosResult | DocumentKind::ThurstoneResult => vec![wrap_score_list],
+        }
+    }
+
+    pub fn current_version(&self) -> u64 {
+        self.migrations().len() as u64
+    }
+
+    // 配列を保存するファイルはオブジェクトで包んでschema_versionを付ける
+    fn payload_key(&self) -> Option<&'static str> {
+        match self {
+            DocumentKind::MosResult | DocumentKind::ThurstoneResult => Some(RESULT_PAYLOAD_KEY),
+            _ => None,
+        }
+    }
+}
+
+// 現在のバージョンのドキュメントに変換-------------------------------------------------
+pub fn to_document<T: Serialize>(kind: DocumentKind, value: &T) -> Result<Value> {
+    let value = serde_json::to_value(value)?;
+    let mut document = match kind.payload_key() {
+        Some(key) => {
+            let mut map = Map::new();
+            map.insert(key.to_string(), value);
+            map
+        }
+        None => match value {
+            Value::Object(map) => map,
+            _ => {
+                return Err(anyhow!(ApplicationError::InvalidDocumentError(format!(
+                    "{:?} must be a JSON object",
+                    kind
+                ))))
+            }
+        },
+    };
+    document.insert(
+        SCHEMA_VERSION_KEY.to_string(),
+        Value::from(kind.current_version()),
+    );
+    Ok(Value::Object(document))
+}
+
+// 古いバージョンのドキュメントを現在のバージョンに変換-------------------------------------
+pub fn migrate(kind: DocumentKind, mut document: Value) -> Result<Value> {
+    let version = get_version(&document)?;
+    let current = kind.current_version();
+    if version > current {
+        return Err(anyhow!(ApplicationError::UnsupportedSchemaVersionError(
+            version, current
+        )));
+    }
+    for (from, migration) in kind.migrations().iter().enumerate().skip(version as usize) {
+        info!("migrate {:?} from version {} to {}", kind, from, from + 1);
+        document = migration(document)?;
+    }
+    Ok(document)
+}
+
+// ドキュメントをマイグレーションしてから構造体にする---------------------------------------
+pub fn from_document<T: DeserializeOwned>(kind: DocumentKind, document: Value) -> Result<T> {
+    let document = migrate(kind, document)?;
+    let mut map = match document {
+        Value::Object(map) => map,
+        _ => {
+            return Err(anyhow!(ApplicationError::InvalidDocumentError(format!(
+                "{:?} must be a JSON object",
+                kind
+            ))))
+        }
+    };
+    map.remove(SCHEMA_VERSION_KEY);
+    let value = match kind.payload_key() {
+        Some(key) => map
+            .remove(key)
+            .ok_or(anyhow!(ApplicationError::InvalidDocumentError(format!(
+                "{:?} has no '{}'",
+                kind, key
+            ))))?,
+        None => Value::Object(map),
+    };
+    Ok(serde_json::from_value(value)?)
+}
+
+// ファイルの読み書き(storageを通すのでバックアップへのフォールバックも行う)-----------------------
+pub fn read_document<T: DeserializeOwned>(path: &Path, kind: DocumentKind) -> Result<T> {
+    let document: Value = storage::read_json(path)?;
+    // 現在の形式に合わない場合はファイルのパスを付けて返す
+    from_document(kind, document).map_err(|e| match e.downcast::<serde_json::Error>() {
+        Ok(e) => anyhow!(ApplicationError::FileFormatError(
+            path.to_path_buf(),
+            e.to_string()
+        )),
+        Err(e) => e,
+    })
+}
+
+pub fn write_document<T: Serialize>(path: &Path, kind: DocumentKind, value: &T) -> Result<()> {
+    let document = to_document(kind, value)?;
+    storage::write_json(path, &document)
+}
+
+fn get_version(document: &Value) -> Result<u64> {
+    match document.get(SCHEMA_VERSION_KEY) {
+        None => Ok(0),
+        Some(version) => version
+            .as_u64()
+            .ok_or(anyhow!(ApplicationError::InvalidDocumentError(format!(
+                "{} must be a non-negative integer",
+                SCHEMA_VERSION_KEY
+            )))),
+    }
+}
+
+/*マイグレーション=====================================================================*/
+// 0 -> 1: 形式はそのままでschema_versionを付ける
+fn add_version_field(document: Value) -> Result<Value> {
+    match document {
+        Value::Object(mut map) => {
+            map.insert(SCHEMA_VERSION_KEY.to_string(), Value::from(1));
+            Ok(Value::Object(map))
+        }
+        _ => Err(anyhow!(ApplicationError::InvalidDocumentError(
+            "document must be a JSON object".to_string()
+        ))),
+    }
+}
+
+// 0 -> 1: 評価結果の配列を{"schema_version": 1, "scores": [...]}に包む
+fn wrap_score_list(document: Value) -> Result<Value> {
+    match document {
+        Value::Array(scores) => {
+            let mut map = Map::new();
+            map.insert(SCHEMA_VERSION_KEY.to_string(), Value::from(1));
+            map.insert(RESULT_PAYLOAD_KEY.to_string(), Value::Array(scores));
+            Ok(Value::Object(map))
+        }
+        _ => Err(anyhow!(ApplicationError::InvalidDocumentError(
+            "result must be a JSON array".to_string()
+        ))),
+    }
+}
+
+// テストで使う古い形式のファイル(src-tauri/tests/fixtures)のパス
+#[cfg(test)]
+pub fn fixture_path(filename: &str) -> std::path::PathBuf {
+    std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(filename)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::TestType;
+
+    use std::collections::HashMap;
+
+    fn load_fixture(filename: &str) -> Value {
+        storage::read_json(&fixture_path(filename)).unwrap()
+    }
+
+    // 配列のままの評価結果は{"schema_version", "scores": [...]}に包まれ，各行はそのまま残る
+    #[test]
+    fn wraps_legacy_results() {
+        for (filename, kind) in [
+            ("legacy_mos_result.json", DocumentKind::MosResult),
+            (
+                "legacy_thurstone_result.json",
+                DocumentKind::ThurstoneResult,
+            ),
+        ] {
+            let legacy = load_fixture(filename);
+            let migrated = migrate(kind, legacy.clone()).unwrap();
+            assert_eq!(migrated[SCHEMA_VERSION_KEY], kind.current_version());
+            assert_eq!(migrated[RESULT_PAYLOAD_KEY], legacy);
+        }
+    }
+
+    // schema_versionのない設定は内容を変えずにschema_versionだけが付く
+    #[test]
+    fn adds_version_to_legacy_settings() {
+        for (filename, kind) in [
+            ("legacy_test_list.json", DocumentKind::TestList),
+            ("legacy_mos_setting.json", DocumentKind::MosSetting),
+            (
+                "legacy_thurstone_setting.json",
+                DocumentKind::ThurstoneSetting,
+            ),
+        ] {
+            let legacy = load_fixture(filename);
+            let migrated = migrate(kind, legacy.clone()).unwrap();
+            let mut fields = migrated.as_object().unwrap().clone();
+            assert_eq!(
+                fields.remove(SCHEMA_VERSION_KEY),
+                Some(Value::from(kind.current_version()))
+            );
+            assert_eq!(Value::Object(fields), legacy);
+        }
+    }
+
+    #[test]
+    fn reads_legacy_test_list() {
+        let test_list: HashMap<String, TestType> = read_document(
+            &fixture_path("legacy_test_list.json"),
+            DocumentKind::TestList,
+        )
+        .unwrap();
+        assert_eq!(test_list.len(), 2);
+        assert_eq!(test_list["listening_test"], TestType::new("Mos"));
+        assert_eq!(test_list["pair_test"], TestType::new("Thurstone"));
+    }
+
+    // 保存した形式で読み込み直せる
+    #[test]
+    fn round_trips_current_documents() {
+        let scores = vec![1, 2, 3];
+        let document = to_document(DocumentKind::MosResult, &scores).unwrap();
+        assert_eq!(
+            document[SCHEMA_VERSION_KEY],
+            DocumentKind::MosResult.current_version()
+        );
+        let read: Vec<i32> = from_document(DocumentKind::MosResult, document.clone()).unwrap();
+        assert_eq!(read, scores);
+        assert_eq!(
+            migrate(DocumentKind::MosResult, document.clone()).unwrap(),
+            document
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_documents() {
+        // 配列でない古い評価結果
+        let legacy = load_fixture("legacy_test_list.json");
+        assert!(migrate(DocumentKind::MosResult, legacy).is_err());
+        // 新しいバージョンのアプリで保存したドキュメント
+        let mut document = load_fixture("legacy_test_list.json");
+        document[SCHEMA_VERSION_KEY] = Value::from(DocumentKind::TestList.current_version() + 1);
+        assert!(migrate(DocumentKind::TestList, document).is_err());
+    }
+}
@@ -0,0 +1,213 @@
+use crate::error::{ApplicationError, WithPath};
+
+use std::ffi::OsString;
+use std::fs;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use log::warn;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/*設定や結果のファイルを安全に読み書きする=========================================
+書き込み：一時ファイル(<name>.tmp)に書いてfsyncした後にrenameで置き換える．
+          置き換える前のファイルは<name>.bakとして残す(壊れている場合は以前のバックアップを残す)
+読み込み：ファイルが壊れている(読めない・パースできない)場合はバックアップを読む
+*/
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    let tmp_path = with_suffix(path, "tmp");
+    {
+        let mut file = File::create(&tmp_path).with_path(&tmp_path)?;
+        file.write_all(contents).with_path(&tmp_path)?;
+        file.sync_all().with_path(&tmp_path)?;
+    }
+    if path.exists() {
+        if read_file::<serde_json::Value>(path).is_ok() {
+            fs::copy(path, with_suffix(path, "bak")).with_path(path)?;
+        } else {
+            warn!("{:?} is corrupt, keep the previous backup", path);
+        }
+    }
+    fs::rename(&tmp_path, path).with_path(path)?;
+    sync_parent_dir(path)?;
+    Ok(())
+}
+
+pub fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    let json_string = serde_json::to_string_pretty(value).with_path(path)?;
+    write_atomic(path, json_string.as_bytes())
+}
+
+pub fn read_json<T: DeserializeOwned>(path: &Path) -> Result<T> {
+    match read_file(path) {
+        Ok(value) => Ok(value),
+        Err(e) => {
+            let backup_path = with_suffix(path, "bak");
+            if backup_path.exists() == false {
+                return Err(e);
+            }
+            warn!(
+                "{:?} is corrupt ({}), fall back to backup: {:?}",
+                path, e, backup_path
+            );
+            read_file(&backup_path)
+        }
+    }
+}
+
+// バックアップを使わずにファイルを読んでパースする
+fn read_file<T: DeserializeOwned>(path: &Path) -> Result<T> {
+    let json_string = fs::read_to_string(path).with_path(path)?;
+    serde_json::from_str(&json_string).with_path(path)
+}
+
+// バックアップでファイルを置き換える(バックアップ自体は残す)------------------------------
+pub fn restore_backup(path: &Path) -> Result<()> {
+    let backup_path = with_suffix(path, "bak");
+    if backup_path.exists() == false {
+        return Err(anyhow!(ApplicationError::BackupNotFoundError(
+            path.to_path_buf()
+        )));
+    }
+    let tmp_path = with_suffix(path, "tmp");
+    fs::copy(&backup_path, &tmp_path).with_path(&backup_path)?;
+    File::open(&tmp_path)
+        .and_then(|file| file.sync_all())
+        .with_path(&tmp_path)?;
+    fs::rename(&tmp_path, path).with_path(path)?;
+    sync_parent_dir(path)?;
+    Ok(())
+}
+
+pub fn has_backup(path: &Path) -> bool {
+    with_suffix(path, "bak").exists()
+}
+
+// ファイルとそのバックアップを移動する(同じボリューム内)------------------------------------
+pub fn move_file(from: &Path, to: &Path) -> Result<()> {
+    fs::rename(from, to).with_path(from)?;
+    let backup_path = with_suffix(from, "bak");
+    if backup_path.exists() {
+        fs::rename(&backup_path, with_suffix(to, "bak")).with_path(&backup_path)?;
+    }
+    sync_parent_dir(from)?;
+    sync_parent_dir(to)?;
+    Ok(())
+}
+
+// ファイルとそのバックアップを削除する(存在しなければ何もしない)---------------------------
+pub fn remove(path: &Path) -> Result<()> {
+    for target in [path.to_path_buf(), with_suffix(path, "bak")] {
+        if target.exists() {
+            fs::remove_file(&target).with_path(&target)?;
+        }
+    }
+    sync_parent_dir(path)?;
+    Ok(())
+}
+
+// ファイルかそのバックアップが存在するか
+pub fn exists(path: &Path) -> bool {
+    path.exists() || with_suffix(path, "bak").exists()
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name: OsString = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+// renameをディスクに反映するためにディレクトリもfsyncする(Windowsではディレクトリを開けないので省略)
+#[cfg(unix)]
+fn sync_parent_dir(path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        File::open(parent)
+            .and_then(|dir| dir.sync_all())
+            .with_path(parent)?;
+    }
+    Ok(())
+}
+#[cfg(not(unix))]
+fn sync_parent_dir(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    // テストごとに空の一時ディレクトリを用意する
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("tloyt_storage_{}_{}", name, std::process::id()));
+        if dir.exists() {
+            fs::remove_dir_all(&dir).unwrap();
+        }
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn rotates_previous_file_to_backup() {
+        let dir = scratch_dir("rotate");
+        let path = dir.join("setting.json");
+        write_json(&path, &json!({"version": 1})).unwrap();
+        assert!(has_backup(&path) == false);
+        write_json(&path, &json!({"version": 2})).unwrap();
+
+        let backup: Value = read_file(&with_suffix(&path, "bak")).unwrap();
+        assert_eq!(backup, json!({"version": 1}));
+        assert_eq!(read_json::<Value>(&path).unwrap(), json!({"version": 2}));
+        assert!(with_suffix(&path, "tmp").exists() == false);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn falls_back_to_backup_when_corrupt() {
+        let dir = scratch_dir("fallback");
+        let path = dir.join("setting.json");
+        write_json(&path, &json!({"version": 1})).unwrap();
+        write_json(&path, &json!({"version": 2})).unwrap();
+        fs::write(&path, "{\"version\": ").unwrap();
+        assert_eq!(read_json::<Value>(&path).unwrap(), json!({"version": 1}));
+
+        // ファイルがなくてもバックアップがあれば読める
+        fs::remove_file(&path).unwrap();
+        assert!(exists(&path));
+        assert_eq!(read_json::<Value>(&path).unwrap(), json!({"version": 1}));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn fails_when_corrupt_without_backup() {
+        let dir = scratch_dir("no_backup");
+        let path = dir.join("setting.json");
+        fs::write(&path, "not json").unwrap();
+        let err = read_json::<Value>(&path).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<ApplicationError>(),
+            Some(ApplicationError::FileFormatError(..))
+        ));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    // 壊れたファイルでバックアップを上書きしない
+    #[test]
+    fn keeps_backup_when_primary_is_corrupt() {
+        let dir = scratch_dir("keep_backup");
+        let path = dir.join("setting.json");
+        write_json(&path, &json!({"version": 1})).unwrap();
+        write_json(&path, &json!({"version": 2})).unwrap();
+        fs::write(&path, "{\"version\": ").unwrap();
+        write_json(&path, &json!({"version": 3})).unwrap();
+
+        let backup: Value = read_file(&with_suffix(&path, "bak")).unwrap();
+        assert_eq!(backup, json!({"version": 1}));
+        assert_eq!(read_json::<Value>(&path).unwrap(), json!({"version": 3}));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
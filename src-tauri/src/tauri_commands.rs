@@ -1,156 +1,708 @@
-use crate::app::{ApplicationManager, TestType};
-use crate::test_trial::TrialStatus;
+use crate::admin::AdminStatus;
+use crate::app::{ApplicationManager, BrokenTest, TestType};
+use crate::constants::{EXPERIMENTER_WINDOW_LABEL, KIOSK_WINDOW_LABEL};
+use crate::error::ApplicationError;
+use crate::participant_pool::{ParticipantOverview, ParticipantPool};
+use crate::questionnaire::Answers;
+use crate::recycle_bin::TrashEntry;
+use crate::test_manager::{TestTypeDescription, TrialResult, TrialView};
+use crate::test_trial::{ScoreInput, TrialProgress, TrialStatus};
 
-use log::info;
+use log::{info, warn};
 use std::sync::Mutex;
-use tauri::State;
+use tauri::{AppHandle, Manager, State, Window, WindowBuilder, WindowUrl};
+
+/*ウィンドウごとのコマンドの権限==========================================================
+実験者のウィンドウ(main)は全てのコマンドを呼び出せる
+受験者用のキオスク(kiosk)は実施中のトライアルの回答に必要なコマンドだけを呼び出せる
+(設定・結果・参加者の情報は取得できない)
+*/
+enum Access {
+    Experimenter, // 実験者のウィンドウのみ
+    Trial,        // 実験者のウィンドウとキオスク
+}
+
+fn authorize(window: &Window, access: Access, command: &str) -> Result<(), ApplicationError> {
+    let label = window.label();
+    let allowed = match access {
+        Access::Experimenter => label == EXPERIMENTER_WINDOW_LABEL,
+        Access::Trial => label == EXPERIMENTER_WINDOW_LABEL || label == KIOSK_WINDOW_LABEL,
+    };
+    if allowed == false {
+        warn!("{} is refused for window: {}", command, label);
+        return Err(ApplicationError::UnauthorizedWindowError(
+            label.to_string(),
+            command.to_string(),
+        ));
+    }
+    Ok(())
+}
 
 #[tauri::command(rename_all = "snake_case")]
 pub fn add_test(
+    window: Window,
     app_manager: State<Mutex<ApplicationManager>>,
     test_type: TestType,
     json_string: String,
-) -> Result<(), String> {
+) -> Result<(), ApplicationError> {
     info!("add_test command is invoked");
+    authorize(&window, Access::Experimenter, "add_test")?;
     let result = app_manager.lock().unwrap().add_test(test_type, json_string);
     match result {
         Ok(_) => return Ok(()),
-        Err(s) => return Err(s.to_string()),
+        Err(e) => return Err(ApplicationError::from(e)),
     }
 }
 
 #[tauri::command(rename_all = "snake_case")]
 pub fn delete_test(
+    window: Window,
     app_manager: State<Mutex<ApplicationManager>>,
     test_name: String,
-) -> Result<(), String> {
+) -> Result<(), ApplicationError> {
     info!("delete_test command is invoked");
+    authorize(&window, Access::Experimenter, "delete_test")?;
     let result = app_manager.lock().unwrap().delete_test(test_name);
     match result {
         Ok(_) => return Ok(()),
-        Err(s) => return Err(s.to_string()),
+        Err(e) => return Err(ApplicationError::from(e)),
     }
 }
 
 #[tauri::command(rename_all = "snake_case")]
 pub fn edit_test(
+    window: Window,
     app_manager: State<Mutex<ApplicationManager>>,
     test_name: String,
     json_string: String,
-) -> Result<(), String> {
+) -> Result<(), ApplicationError> {
     info!("edit_test command is invoked");
+    authorize(&window, Access::Experimenter, "edit_test")?;
     let result = app_manager
         .lock()
         .unwrap()
         .edit_test(test_name, json_string);
     match result {
         Ok(_) => return Ok(()),
-        Err(s) => return Err(s.to_string()),
+        Err(e) => return Err(ApplicationError::from(e)),
+    }
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub fn rename_test(
+    window: Window,
+    app_manager: State<Mutex<ApplicationManager>>,
+    test_name: String,
+    new_name: String,
+) -> Result<(), ApplicationError> {
+    info!("rename_test command is invoked");
+    authorize(&window, Access::Experimenter, "rename_test")?;
+    let result = app_manager.lock().unwrap().rename_test(test_name, new_name);
+    match result {
+        Ok(_) => return Ok(()),
+        Err(e) => return Err(ApplicationError::from(e)),
+    }
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub fn fork_test(
+    window: Window,
+    app_manager: State<Mutex<ApplicationManager>>,
+    test_name: String,
+    json_string: String,
+) -> Result<(), ApplicationError> {
+    info!("fork_test command is invoked");
+    authorize(&window, Access::Experimenter, "fork_test")?;
+    let result = app_manager
+        .lock()
+        .unwrap()
+        .fork_test(test_name, json_string);
+    match result {
+        Ok(_) => return Ok(()),
+        Err(e) => return Err(ApplicationError::from(e)),
+    }
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub fn register_participant(
+    window: Window,
+    app_manager: State<Mutex<ApplicationManager>>,
+    test_name: String,
+    examinee: String,
+    answers: Answers,
+    consent: bool,
+) -> Result<(), ApplicationError> {
+    info!("register_participant command is invoked");
+    authorize(&window, Access::Experimenter, "register_participant")?;
+    let result = app_manager
+        .lock()
+        .unwrap()
+        .register_participant(test_name, examinee, answers, consent);
+    match result {
+        Ok(_) => return Ok(()),
+        Err(e) => return Err(ApplicationError::from(e)),
+    }
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub fn submit_post_test(
+    window: Window,
+    app_manager: State<Mutex<ApplicationManager>>,
+    test_name: String,
+    examinee: String,
+    answers: Answers,
+) -> Result<(), ApplicationError> {
+    info!("submit_post_test command is invoked");
+    authorize(&window, Access::Experimenter, "submit_post_test")?;
+    let result = app_manager
+        .lock()
+        .unwrap()
+        .submit_post_test(test_name, examinee, answers);
+    match result {
+        Ok(_) => return Ok(()),
+        Err(e) => return Err(ApplicationError::from(e)),
     }
 }
 
 #[tauri::command(rename_all = "snake_case")]
 pub fn start_test(
+    window: Window,
     app_manager: State<Mutex<ApplicationManager>>,
     test_name: String,
     examinee: String,
-) -> Result<(), String> {
+) -> Result<(), ApplicationError> {
     info!("start_test command is invoked");
+    authorize(&window, Access::Experimenter, "start_test")?;
     let result = app_manager.lock().unwrap().start_test(test_name, examinee);
     match result {
         Ok(_) => return Ok(()),
-        Err(s) => return Err(s.to_string()),
+        Err(e) => return Err(ApplicationError::from(e)),
     }
 }
 
 #[tauri::command(rename_all = "snake_case")]
 pub fn close_test(
+    window: Window,
     app_manager: State<Mutex<ApplicationManager>>,
     examinee: String,
-) -> Result<(), String> {
+) -> Result<(), ApplicationError> {
     info!("close_test command is invoked");
+    authorize(&window, Access::Experimenter, "close_test")?;
     let result = app_manager.lock().unwrap().close_test(examinee);
     match result {
         Ok(_) => return Ok(()),
-        Err(s) => return Err(s.to_string()),
+        Err(e) => return Err(ApplicationError::from(e)),
     }
 }
 
 #[tauri::command(rename_all = "snake_case")]
-pub fn get_audio(app_manager: State<Mutex<ApplicationManager>>) -> Result<Vec<String>, String> {
+pub fn get_audio(
+    window: Window,
+    app_manager: State<Mutex<ApplicationManager>>,
+) -> Result<Vec<String>, ApplicationError> {
     info!("get_audio command is invoked");
+    authorize(&window, Access::Trial, "get_audio")?;
     let result = app_manager.lock().unwrap().get_audio();
     match result {
-        Ok(p) => {
-            let paths: Vec<String> = p
-                .into_iter()
-                .map(|_p| _p.to_string_lossy().into_owned())
-                .collect();
-            return Ok(paths);
-        }
-        Err(s) => return Err(s.to_string()),
+        Ok(tokens) => return Ok(tokens),
+        Err(e) => return Err(ApplicationError::from(e)),
+    }
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub fn report_playback_end(
+    window: Window,
+    app_manager: State<Mutex<ApplicationManager>>,
+    token: String,
+) -> Result<(), ApplicationError> {
+    info!("report_playback_end command is invoked");
+    authorize(&window, Access::Trial, "report_playback_end")?;
+    let result = app_manager.lock().unwrap().report_playback_end(token);
+    match result {
+        Ok(_) => return Ok(()),
+        Err(e) => return Err(ApplicationError::from(e)),
     }
 }
 
 #[tauri::command(rename_all = "snake_case")]
 pub fn set_score(
+    window: Window,
     app_manager: State<Mutex<ApplicationManager>>,
-    score: Vec<String>,
-) -> Result<TrialStatus, String> {
+    score: ScoreInput,
+) -> Result<TrialStatus, ApplicationError> {
     info!("set_score command is invoked");
+    authorize(&window, Access::Trial, "set_score")?;
     let result = app_manager.lock().unwrap().set_score(score);
     match result {
         Ok(v) => return Ok(v),
-        Err(s) => return Err(s.to_string()),
+        Err(e) => return Err(ApplicationError::from(e)),
+    }
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub fn get_progress(
+    window: Window,
+    app_manager: State<Mutex<ApplicationManager>>,
+) -> Result<TrialProgress, ApplicationError> {
+    info!("get_progress command is invoked");
+    authorize(&window, Access::Trial, "get_progress")?;
+    let result = app_manager.lock().unwrap().get_progress();
+    match result {
+        Ok(v) => return Ok(v),
+        Err(e) => return Err(ApplicationError::from(e)),
     }
 }
 
 #[tauri::command(rename_all = "snake_case")]
-pub fn get_settings(app_manager: State<Mutex<ApplicationManager>>) -> Result<Vec<String>, String> {
+pub fn get_settings(
+    window: Window,
+    app_manager: State<Mutex<ApplicationManager>>,
+) -> Result<Vec<String>, ApplicationError> {
     info!("get_setting command is invoked");
+    authorize(&window, Access::Experimenter, "get_settings")?;
     let result = app_manager.lock().unwrap().get_settings();
     match result {
         Ok(v) => return Ok(v),
-        Err(s) => return Err(s.to_string()),
+        Err(e) => return Err(ApplicationError::from(e)),
     }
 }
 
 #[tauri::command(rename_all = "snake_case")]
 pub fn delete_trial(
+    window: Window,
     app_manager: State<Mutex<ApplicationManager>>,
     test_name: String,
     examinee: String,
-) -> Result<(), String> {
+) -> Result<(), ApplicationError> {
     info!("delete_trial command is invoked");
+    authorize(&window, Access::Experimenter, "delete_trial")?;
     let result = app_manager
         .lock()
         .unwrap()
         .delete_trial(test_name, examinee);
     match result {
         Ok(_) => return Ok(()),
-        Err(s) => return Err(s.to_string()),
+        Err(e) => return Err(ApplicationError::from(e)),
     }
 }
 
 #[tauri::command(rename_all = "snake_case")]
 pub fn start_preview(
+    window: Window,
     app_manager: State<Mutex<ApplicationManager>>,
     test_name: String,
-) -> Result<(), String> {
+) -> Result<(), ApplicationError> {
     info!("start_preview command is invoked");
+    authorize(&window, Access::Experimenter, "start_preview")?;
     let result = app_manager.lock().unwrap().start_preview(test_name);
     match result {
         Ok(_) => return Ok(()),
-        Err(s) => return Err(s.to_string()),
+        Err(e) => return Err(ApplicationError::from(e)),
     }
 }
 
 #[tauri::command(rename_all = "snake_case")]
-pub fn close_preview(app_manager: State<Mutex<ApplicationManager>>) -> Result<(), String> {
+pub fn close_preview(
+    window: Window,
+    app_manager: State<Mutex<ApplicationManager>>,
+) -> Result<(), ApplicationError> {
     info!("close_preview command is invoked");
+    authorize(&window, Access::Experimenter, "close_preview")?;
     let result = app_manager.lock().unwrap().close_preview();
     match result {
         Ok(_) => return Ok(()),
-        Err(s) => return Err(s.to_string()),
+        Err(e) => return Err(ApplicationError::from(e)),
+    }
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub fn get_participant_pool(
+    window: Window,
+    app_manager: State<Mutex<ApplicationManager>>,
+) -> Result<ParticipantPool, ApplicationError> {
+    info!("get_participant_pool command is invoked");
+    authorize(&window, Access::Experimenter, "get_participant_pool")?;
+    Ok(app_manager.lock().unwrap().get_participant_pool())
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub fn add_pool_member(
+    window: Window,
+    app_manager: State<Mutex<ApplicationManager>>,
+    name: String,
+) -> Result<(), ApplicationError> {
+    info!("add_pool_member command is invoked");
+    authorize(&window, Access::Experimenter, "add_pool_member")?;
+    let result = app_manager.lock().unwrap().add_pool_member(name);
+    match result {
+        Ok(_) => return Ok(()),
+        Err(e) => return Err(ApplicationError::from(e)),
+    }
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub fn remove_pool_member(
+    window: Window,
+    app_manager: State<Mutex<ApplicationManager>>,
+    name: String,
+) -> Result<(), ApplicationError> {
+    info!("remove_pool_member command is invoked");
+    authorize(&window, Access::Experimenter, "remove_pool_member")?;
+    let result = app_manager.lock().unwrap().remove_pool_member(name);
+    match result {
+        Ok(_) => return Ok(()),
+        Err(e) => return Err(ApplicationError::from(e)),
+    }
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub fn set_pool_group(
+    window: Window,
+    app_manager: State<Mutex<ApplicationManager>>,
+    group: String,
+    members: Vec<String>,
+) -> Result<(), ApplicationError> {
+    info!("set_pool_group command is invoked");
+    authorize(&window, Access::Experimenter, "set_pool_group")?;
+    let result = app_manager.lock().unwrap().set_pool_group(group, members);
+    match result {
+        Ok(_) => return Ok(()),
+        Err(e) => return Err(ApplicationError::from(e)),
+    }
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub fn delete_pool_group(
+    window: Window,
+    app_manager: State<Mutex<ApplicationManager>>,
+    group: String,
+) -> Result<(), ApplicationError> {
+    info!("delete_pool_group command is invoked");
+    authorize(&window, Access::Experimenter, "delete_pool_group")?;
+    let result = app_manager.lock().unwrap().delete_pool_group(group);
+    match result {
+        Ok(_) => return Ok(()),
+        Err(e) => return Err(ApplicationError::from(e)),
+    }
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub fn get_participant_overview(
+    window: Window,
+    app_manager: State<Mutex<ApplicationManager>>,
+    name: String,
+) -> Result<ParticipantOverview, ApplicationError> {
+    info!("get_participant_overview command is invoked");
+    authorize(&window, Access::Experimenter, "get_participant_overview")?;
+    let result = app_manager.lock().unwrap().get_participant_overview(name);
+    match result {
+        Ok(overview) => return Ok(overview),
+        Err(e) => return Err(ApplicationError::from(e)),
+    }
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub fn get_broken_tests(
+    window: Window,
+    app_manager: State<Mutex<ApplicationManager>>,
+) -> Result<Vec<BrokenTest>, ApplicationError> {
+    info!("get_broken_tests command is invoked");
+    authorize(&window, Access::Experimenter, "get_broken_tests")?;
+    Ok(app_manager.lock().unwrap().get_broken_tests())
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub fn reload_broken_test(
+    window: Window,
+    app_manager: State<Mutex<ApplicationManager>>,
+    test_name: String,
+) -> Result<(), ApplicationError> {
+    info!("reload_broken_test command is invoked");
+    authorize(&window, Access::Experimenter, "reload_broken_test")?;
+    let result = app_manager.lock().unwrap().reload_broken_test(test_name);
+    match result {
+        Ok(_) => return Ok(()),
+        Err(e) => return Err(ApplicationError::from(e)),
+    }
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub fn remove_broken_test(
+    window: Window,
+    app_manager: State<Mutex<ApplicationManager>>,
+    test_name: String,
+) -> Result<(), ApplicationError> {
+    info!("remove_broken_test command is invoked");
+    authorize(&window, Access::Experimenter, "remove_broken_test")?;
+    let result = app_manager.lock().unwrap().remove_broken_test(test_name);
+    match result {
+        Ok(_) => return Ok(()),
+        Err(e) => return Err(ApplicationError::from(e)),
+    }
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub fn restore_test_backup(
+    window: Window,
+    app_manager: State<Mutex<ApplicationManager>>,
+    test_name: String,
+) -> Result<(), ApplicationError> {
+    info!("restore_test_backup command is invoked");
+    authorize(&window, Access::Experimenter, "restore_test_backup")?;
+    let result = app_manager.lock().unwrap().restore_test_backup(test_name);
+    match result {
+        Ok(_) => return Ok(()),
+        Err(e) => return Err(ApplicationError::from(e)),
+    }
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub fn get_test_types(
+    window: Window,
+    app_manager: State<Mutex<ApplicationManager>>,
+) -> Result<Vec<TestTypeDescription>, ApplicationError> {
+    info!("get_test_types command is invoked");
+    authorize(&window, Access::Experimenter, "get_test_types")?;
+    Ok(app_manager.lock().unwrap().get_test_types())
+}
+
+// ごみ箱====================================================================
+#[tauri::command(rename_all = "snake_case")]
+pub fn get_trash(
+    window: Window,
+    app_manager: State<Mutex<ApplicationManager>>,
+) -> Result<Vec<TrashEntry>, ApplicationError> {
+    info!("get_trash command is invoked");
+    authorize(&window, Access::Experimenter, "get_trash")?;
+    Ok(app_manager.lock().unwrap().get_trash())
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub fn restore_trash(
+    window: Window,
+    app_manager: State<Mutex<ApplicationManager>>,
+    id: String,
+) -> Result<(), ApplicationError> {
+    info!("restore_trash command is invoked");
+    authorize(&window, Access::Experimenter, "restore_trash")?;
+    let result = app_manager.lock().unwrap().restore_trash(id);
+    match result {
+        Ok(_) => return Ok(()),
+        Err(e) => return Err(ApplicationError::from(e)),
+    }
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub fn purge_trash(
+    window: Window,
+    app_manager: State<Mutex<ApplicationManager>>,
+    id: String,
+) -> Result<(), ApplicationError> {
+    info!("purge_trash command is invoked");
+    authorize(&window, Access::Experimenter, "purge_trash")?;
+    let result = app_manager.lock().unwrap().purge_trash(id);
+    match result {
+        Ok(_) => return Ok(()),
+        Err(e) => return Err(ApplicationError::from(e)),
+    }
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub fn empty_trash(
+    window: Window,
+    app_manager: State<Mutex<ApplicationManager>>,
+) -> Result<(), ApplicationError> {
+    info!("empty_trash command is invoked");
+    authorize(&window, Access::Experimenter, "empty_trash")?;
+    let result = app_manager.lock().unwrap().empty_trash();
+    match result {
+        Ok(_) => return Ok(()),
+        Err(e) => return Err(ApplicationError::from(e)),
+    }
+}
+
+// 管理者のPIN==================================================================
+#[tauri::command(rename_all = "snake_case")]
+pub fn get_admin_status(
+    window: Window,
+    app_manager: State<Mutex<ApplicationManager>>,
+) -> Result<AdminStatus, ApplicationError> {
+    info!("get_admin_status command is invoked");
+    authorize(&window, Access::Experimenter, "get_admin_status")?;
+    Ok(app_manager.lock().unwrap().get_admin_status())
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub fn unlock_admin(
+    window: Window,
+    app_manager: State<Mutex<ApplicationManager>>,
+    pin: String,
+) -> Result<(), ApplicationError> {
+    info!("unlock_admin command is invoked");
+    authorize(&window, Access::Experimenter, "unlock_admin")?;
+    let result = app_manager.lock().unwrap().unlock_admin(pin);
+    match result {
+        Ok(_) => return Ok(()),
+        Err(e) => return Err(ApplicationError::from(e)),
+    }
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub fn lock_admin(
+    window: Window,
+    app_manager: State<Mutex<ApplicationManager>>,
+) -> Result<(), ApplicationError> {
+    info!("lock_admin command is invoked");
+    authorize(&window, Access::Experimenter, "lock_admin")?;
+    app_manager.lock().unwrap().lock_admin();
+    Ok(())
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub fn set_admin_pin(
+    window: Window,
+    app_manager: State<Mutex<ApplicationManager>>,
+    pin: Option<String>,
+) -> Result<(), ApplicationError> {
+    info!("set_admin_pin command is invoked");
+    authorize(&window, Access::Experimenter, "set_admin_pin")?;
+    let result = app_manager.lock().unwrap().set_admin_pin(pin);
+    match result {
+        Ok(_) => return Ok(()),
+        Err(e) => return Err(ApplicationError::from(e)),
+    }
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub fn get_results(
+    window: Window,
+    app_manager: State<Mutex<ApplicationManager>>,
+    test_name: String,
+) -> Result<Vec<TrialResult>, ApplicationError> {
+    info!("get_results command is invoked");
+    authorize(&window, Access::Experimenter, "get_results")?;
+    let result = app_manager.lock().unwrap().get_results(test_name);
+    match result {
+        Ok(results) => return Ok(results),
+        Err(e) => return Err(ApplicationError::from(e)),
+    }
+}
+
+// 実験者のコンソールからのトライアルの操作==================================================
+#[tauri::command(rename_all = "snake_case")]
+pub fn pause_trial(
+    window: Window,
+    app_manager: State<Mutex<ApplicationManager>>,
+) -> Result<(), ApplicationError> {
+    info!("pause_trial command is invoked");
+    authorize(&window, Access::Experimenter, "pause_trial")?;
+    let result = app_manager.lock().unwrap().pause_trial();
+    match result {
+        Ok(_) => return Ok(()),
+        Err(e) => return Err(ApplicationError::from(e)),
+    }
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub fn resume_trial(
+    window: Window,
+    app_manager: State<Mutex<ApplicationManager>>,
+) -> Result<(), ApplicationError> {
+    info!("resume_trial command is invoked");
+    authorize(&window, Access::Experimenter, "resume_trial")?;
+    let result = app_manager.lock().unwrap().resume_trial();
+    match result {
+        Ok(_) => return Ok(()),
+        Err(e) => return Err(ApplicationError::from(e)),
+    }
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub fn skip_item(
+    window: Window,
+    app_manager: State<Mutex<ApplicationManager>>,
+) -> Result<TrialStatus, ApplicationError> {
+    info!("skip_item command is invoked");
+    authorize(&window, Access::Experimenter, "skip_item")?;
+    let result = app_manager.lock().unwrap().skip_item();
+    match result {
+        Ok(v) => return Ok(v),
+        Err(e) => return Err(ApplicationError::from(e)),
+    }
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub fn abort_trial(
+    window: Window,
+    app_manager: State<Mutex<ApplicationManager>>,
+) -> Result<(), ApplicationError> {
+    info!("abort_trial command is invoked");
+    authorize(&window, Access::Experimenter, "abort_trial")?;
+    let result = app_manager.lock().unwrap().abort_trial();
+    match result {
+        Ok(_) => return Ok(()),
+        Err(e) => return Err(ApplicationError::from(e)),
+    }
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub fn get_trial_view(
+    window: Window,
+    app_manager: State<Mutex<ApplicationManager>>,
+) -> Result<TrialView, ApplicationError> {
+    info!("get_trial_view command is invoked");
+    authorize(&window, Access::Trial, "get_trial_view")?;
+    let result = app_manager.lock().unwrap().get_trial_view();
+    match result {
+        Ok(v) => return Ok(v),
+        Err(e) => return Err(ApplicationError::from(e)),
+    }
+}
+
+// 受験者用のキオスクを全画面で開く(開いていれば前面に出す)-----------------------------------
+// Windowsではウィンドウの作成を同期コマンドで行うとデッドロックするのでasyncにする
+#[tauri::command(rename_all = "snake_case")]
+pub async fn open_kiosk(window: Window, app: AppHandle) -> Result<(), ApplicationError> {
+    info!("open_kiosk command is invoked");
+    authorize(&window, Access::Experimenter, "open_kiosk")?;
+    if let Some(kiosk) = app.get_window(KIOSK_WINDOW_LABEL) {
+        return kiosk
+            .set_focus()
+            .map_err(|e| ApplicationError::InternalError(e.to_string()));
+    }
+    let result = WindowBuilder::new(&app, KIOSK_WINDOW_LABEL, WindowUrl::default())
+        .title("tloyt")
+        .fullscreen(true)
+        .build();
+    match result {
+        Ok(_) => return Ok(()),
+        Err(e) => return Err(ApplicationError::InternalError(e.to_string())),
+    }
+}
+
+/*カテゴリのフォルダを選ぶダイアログを開き，選ばれたパスを返す(キャンセルした場合はNone)--------------
+Tauri v1の許可リストは全てのウィンドウに適用されるので，キオスクからファイルダイアログを開けないように
+フロントエンドのAPIではなく実験者のウィンドウ専用のコマンドで開く
+*/
+#[tauri::command(rename_all = "snake_case")]
+pub async fn pick_directory(window: Window) -> Result<Option<String>, ApplicationError> {
+    info!("pick_directory command is invoked");
+    authorize(&window, Access::Experimenter, "pick_directory")?;
+    let folder = rfd::AsyncFileDialog::new()
+        .set_parent(&window)
+        .pick_folder()
+        .await;
+    Ok(folder.map(|f| f.path().to_string_lossy().to_string()))
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub fn close_kiosk(window: Window, app: AppHandle) -> Result<(), ApplicationError> {
+    info!("close_kiosk command is invoked");
+    authorize(&window, Access::Experimenter, "close_kiosk")?;
+    match app.get_window(KIOSK_WINDOW_LABEL) {
+        Some(kiosk) => kiosk
+            .close()
+            .map_err(|e| ApplicationError::InternalError(e.to_string())),
+        None => Ok(()),
     }
 }
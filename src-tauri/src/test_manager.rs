@@ -1,12 +1,34 @@
+pub mod generic;
 pub mod mos;
+pub mod registry;
 pub mod thurstone;
 
-use crate::constants::AVAILABLE_AUDIO_FILE_EXTENTION;
-use crate::error::ApplicationError;
-use crate::test_trial::TrialStatus;
+use crate::app::TestType;
+use crate::audio::loudness::{normalize, LoudnessNormalization, LoudnessRecord};
+use crate::audio::{decode_audio, probe_audio, write_wav, AudioMetadata};
+use crate::constants::{
+    AVAILABLE_AUDIO_FILE_EXTENTION, DURATION_MISMATCH_TOLERANCE_SEC,
+    WEBVIEW_PLAYABLE_AUDIO_FILE_EXTENTION,
+};
+use crate::error::{ApplicationError, WithPath};
+use crate::identifier::{IdentifierKind, Slug};
+use crate::questionnaire::{validate_answers, validate_questions, Answers, Question};
+use crate::schema::DocumentKind;
+use crate::storage;
+use crate::test_trial::{
+    ScoreInput, SessionRecord, TestTrial, TrialProgress, TrialRule, TrialStatus,
+};
 
 use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local};
+use log::{info, warn};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::fmt::Debug;
 use std::fs;
 use std::path::PathBuf;
 
@@ -17,26 +39,342 @@ pub trait TestManager: Send + Sync {
     //----------------------------------------------------------------
     fn launch_trial(&mut self, examinee: String) -> Result<()>;
     fn close_trial(&mut self, examinee: String) -> Result<()>;
-    fn delete_trial(&mut self, examinee: String) -> Result<()>;
+    fn delete_trial(&mut self, examinee: String, trash_path: &PathBuf) -> Result<SessionRecord>;
+    fn restore_trial(
+        &mut self,
+        examinee: String,
+        session: SessionRecord,
+        trash_path: &PathBuf,
+    ) -> Result<()>;
     fn launch_preview(&mut self) -> Result<()>;
     fn close_preview(&mut self) -> Result<()>;
+    fn abort_trial(&mut self) -> Result<()>;
     fn edit(&mut self, json_string: String) -> Result<()>;
-    fn get_audio(&mut self) -> Result<Vec<PathBuf>>;
-    fn set_score(&mut self, score: Vec<String>) -> Result<TrialStatus>;
+    fn rename(&mut self, new_name: Slug, new_data_root: PathBuf) -> Result<()>;
+    fn get_audio(&mut self) -> Result<Vec<String>>;
+    fn resolve_audio(&mut self, token: &str) -> Result<PathBuf>;
+    fn report_playback_end(&mut self, token: &str) -> Result<()>;
+    fn set_score(&mut self, input: ScoreInput) -> Result<TrialStatus>;
+    fn skip_item(&mut self) -> Result<TrialStatus>;
+    fn check_expiry(&mut self) -> Result<Option<TrialStatus>>;
+    fn get_progress(&self) -> Result<TrialProgress>;
+    fn register_participant(
+        &mut self,
+        examinee: String,
+        answers: Answers,
+        consent: bool,
+    ) -> Result<()>;
+    fn submit_post_test(&mut self, examinee: String, answers: Answers) -> Result<()>;
+    fn add_participants(&mut self, participants: Vec<String>) -> Result<()>;
+    fn get_participant_status(&self, examinee: &String) -> Option<ParticipantStatus>;
+    fn get_participant_groups(&self) -> Vec<String>;
+    fn get_results(&self) -> Result<Vec<TrialResult>>;
     //----------------------------------------------------------------
-    fn copy_categories(&self) -> Result<()>;
+    fn copy_categories(&mut self) -> Result<()>;
     fn save_setting(&self) -> Result<()>;
     //----------------------------------------------------------------
     fn get_setting(&self) -> Result<String>;
+    fn get_trial_view(&self) -> TrialView;
+}
+
+/*テストの方式(MOS，サーストン法など)ごとに異なる部分を規定するトレイト==========================
+方式固有の設定(MOSの繰り返し回数など)を持つ構造体に実装し，GenericManagerの型引数にする
+方式固有の設定はテストの設定ファイルとセットアップ情報のトップレベルに展開して保存される
+*/
+pub trait TestMethod:
+    Serialize + DeserializeOwned + Debug + Clone + Default + Send + Sync + 'static
+{
+    type Trial: TestTrial + Serialize + DeserializeOwned + Debug + Clone + Send + Sync;
+    const TYPE_ID: &'static str; // test_list.jsonやフロントエンドで使う種類のid
+    const DISPLAY_NAME: &'static str;
+    const SETTING_DOCUMENT: DocumentKind;
+    const RESULT_DOCUMENT: DocumentKind;
+
+    fn num_score_labels() -> usize;
+    fn default_display_text() -> DisplayText;
+    // 受験者ごとの出題を生成する
+    fn generate_trial(
+        &self,
+        manager_data_root: PathBuf,
+        examinee: String,
+        categories: Categories,
+        rule: TrialRule,
+    ) -> Result<Self::Trial>;
+    // 方式固有の設定の変更で結果の互換性が失われるかどうか
+    fn is_structural_change(&self, new: &Self) -> bool;
+}
+
+// フロントエンドに渡すテストの種類の説明----------------------------------------------
+// method_setting: 方式固有の設定項目とその既定値
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TestTypeDescription {
+    pub id: TestType,
+    pub display_name: String,
+    pub num_score_labels: usize,
+    pub default_display_text: DisplayText,
+    pub method_setting: serde_json::Value,
+}
+
+// 受験者用のキオスクに渡すテストの情報(カテゴリや参加者などは含めない)-----------------------------
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TrialView {
+    pub name: String,
+    pub display_name: Option<String>,
+    pub test_type: TestType,
+    pub description: String,
+    pub time_limit: usize,
+    pub display_text: DisplayText,
+}
+
+// 集計のためにフロントエンドに渡す結果ファイルの内容--------------------------------------
+// key: 結果ファイルの名前(匿名化したid)，scores: 現在の形式にマイグレーションした評価結果
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TrialResult {
+    pub key: String,
+    pub scores: serde_json::Value,
+}
+
+// 入力された参加者名をファイル名に使える名前にする(空白はアンダーバーに置換．表示名も保持する)---------
+// 同じ名前になる参加者がいればエラー
+pub fn participant_slugs(participants: &Vec<String>) -> Result<Vec<Slug>> {
+    let slugs = participants
+        .iter()
+        .map(|p| Slug::from_display_name(p, IdentifierKind::Participant))
+        .collect::<Result<Vec<Slug>>>()?;
+    Slug::check_unique(&slugs, IdentifierKind::Participant)?;
+    Ok(slugs)
+}
+
+// テストのディレクトリの移動に合わせて，結果ファイル内のパスを書き換える--------------------------
+// 全てのファイルの書き換え後の内容を作ってから書き込む(途中で読み込みに失敗しても元のファイルは残る)
+pub fn relocate_results(trial_dir: &PathBuf, old_root: &PathBuf, new_root: &PathBuf) -> Result<()> {
+    if trial_dir.exists() == false {
+        return Ok(());
+    }
+    let old_prefix = old_root.to_string_lossy().into_owned();
+    let new_prefix = new_root.to_string_lossy().into_owned();
+
+    let mut rewritten: Vec<(PathBuf, String)> = Vec::new();
+    for entry in fs::read_dir(trial_dir).with_path(trial_dir)? {
+        let path = entry.with_path(trial_dir)?.path();
+        if path.extension().map_or(true, |e| e != "json") {
+            continue;
+        }
+        let json_string = fs::read_to_string(&path).with_path(&path)?;
+        let mut value: serde_json::Value = serde_json::from_str(&json_string).with_path(&path)?;
+        replace_path_prefix(&mut value, &old_prefix, &new_prefix);
+        let json_string = serde_json::to_string_pretty(&value).with_path(&path)?;
+        rewritten.push((path, json_string));
+    }
+    for (path, json_string) in rewritten {
+        storage::write_atomic(&path, json_string.as_bytes())?;
+        info!("relocate result: {:?}", path);
+    }
+    Ok(())
+}
+
+fn replace_path_prefix(value: &mut serde_json::Value, old_prefix: &str, new_prefix: &str) {
+    match value {
+        serde_json::Value::String(s) if is_under(s, old_prefix) => {
+            *s = format!("{}{}", new_prefix, &s[old_prefix.len()..]);
+        }
+        serde_json::Value::Array(values) => {
+            for v in values {
+                replace_path_prefix(v, old_prefix, new_prefix);
+            }
+        }
+        serde_json::Value::Object(map) => {
+            for v in map.values_mut() {
+                replace_path_prefix(v, old_prefix, new_prefix);
+            }
+        }
+        _ => {}
+    }
+}
+
+// pathがdirectory自身かその下のパスかどうか(文字列として判定)
+fn is_under(path: &str, directory: &str) -> bool {
+    match path.strip_prefix(directory) {
+        Some(rest) => rest.is_empty() || rest.starts_with(std::path::MAIN_SEPARATOR),
+        None => false,
+    }
+}
+
+// テストの編集の種類=========================================================
+// Cosmetic: 説明や参加者など結果に影響しない変更
+// Structural: カテゴリや出題数，制限時間など結果の互換性が失われる変更
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum EditKind {
+    Cosmetic,
+    Structural,
 }
 
 //実験参加者の状態を表す列挙型================================================
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum ParticipantStatus {
-    Yet,  //未受験
+    Yet,  //未受験(受験回数が参加者ごとの受験回数に達していない)
     Done, //受験済み
 }
 
+// 参加者ごとの受験回数の既定値(以前のテストは1回のみ)
+pub fn default_sessions_per_participant() -> usize {
+    1
+}
+
+// 受験前アンケートの設定=====================================================
+// require_consent: 受験前に同意を必須とするか，consent_text: 同意文(markdown)
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct PreTestSetting {
+    pub require_consent: bool,
+    pub consent_text: String,
+    pub questions: Vec<Question>,
+}
+impl PreTestSetting {
+    pub fn validate(&self) -> Result<()> {
+        validate_questions(&self.questions)
+    }
+}
+
+// 受験者に表示する文言の設定================================================
+// instruction: 回答中に表示する説明(markdown)
+// score_labels: 評価値ごとのラベル(MOSは1から5の順，サーストン法はA, Bの順)
+// start_button / submit_button: テスト開始とアンケート送信のボタンの文言
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DisplayText {
+    pub instruction: String,
+    pub score_labels: Vec<String>,
+    pub start_button: String,
+    pub submit_button: String,
+}
+impl DisplayText {
+    pub fn mos_default() -> DisplayText {
+        DisplayText::new(vec!["非常に悪い", "悪い", "普通", "良い", "非常に良い"])
+    }
+
+    pub fn thurstone_default() -> DisplayText {
+        DisplayText::new(vec!["A", "B"])
+    }
+
+    fn new(score_labels: Vec<&str>) -> DisplayText {
+        DisplayText {
+            instruction: String::new(),
+            score_labels: score_labels.iter().map(|l| l.to_string()).collect(),
+            start_button: "テストを開始".to_string(),
+            submit_button: "送信".to_string(),
+        }
+    }
+
+    // ラベルの数が評価値の数と一致し，ラベルとボタンの文言が空でないこと-----------------------
+    pub fn validate(&self, num_labels: usize) -> Result<()> {
+        if self.score_labels.len() != num_labels {
+            return Err(anyhow!(ApplicationError::InvalidDisplayTextError(format!(
+                "{} score labels are required, but {} given",
+                num_labels,
+                self.score_labels.len()
+            ))));
+        }
+        if self.score_labels.iter().any(|l| l.trim().is_empty()) {
+            return Err(anyhow!(ApplicationError::InvalidDisplayTextError(
+                "score label is empty".to_string()
+            )));
+        }
+        if self.start_button.trim().is_empty() || self.submit_button.trim().is_empty() {
+            return Err(anyhow!(ApplicationError::InvalidDisplayTextError(
+                "button caption is empty".to_string()
+            )));
+        }
+        Ok(())
+    }
+}
+
+// 実験参加者の記録==========================================================
+// 結果ファイルは匿名化したidで保存し，実名との対応はテストの設定にのみ持つ
+// 質問idが age_range, native_language, hearing_impairment, headphone_model の回答は各項目に格納
+// display_name: 入力された参加者名(記録する前の参加者や参加者プールから加えた参加者はNone)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ParticipantRecord {
+    anonymized_id: String,
+    #[serde(default)]
+    display_name: Option<String>,
+    age_range: Option<String>,
+    native_language: Option<String>,
+    hearing_impairment: Option<bool>,
+    headphone_model: Option<String>,
+    consent_at: Option<DateTime<Local>>,
+    answers: Answers,
+}
+impl ParticipantRecord {
+    pub fn new(display_name: Option<String>) -> ParticipantRecord {
+        let random: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(8)
+            .map(char::from)
+            .collect();
+        ParticipantRecord {
+            anonymized_id: format!("P{}", random.to_uppercase()),
+            display_name: display_name,
+            age_range: None,
+            native_language: None,
+            hearing_impairment: None,
+            headphone_model: None,
+            consent_at: None,
+            answers: Answers::new(),
+        }
+    }
+
+    // 受験前アンケートの回答を検証して記録する------------------------------------------
+    pub fn register(
+        &mut self,
+        setting: &PreTestSetting,
+        answers: Answers,
+        consent: bool,
+    ) -> Result<()> {
+        if setting.require_consent && consent == false {
+            return Err(anyhow!(ApplicationError::ConsentRequiredError(
+                self.anonymized_id.clone()
+            )));
+        }
+        validate_answers(&setting.questions, &answers)?;
+
+        let mut answers = answers;
+        self.age_range = answers.remove("age_range");
+        self.native_language = answers.remove("native_language");
+        self.hearing_impairment = answers.remove("hearing_impairment").map(|a| a == "yes");
+        self.headphone_model = answers.remove("headphone_model");
+        self.answers = answers;
+        if consent {
+            self.consent_at = Some(Local::now());
+        }
+        Ok(())
+    }
+
+    // 受験できる状態かどうか(同意が必要な場合は同意済みであること)------------------------------
+    pub fn check_ready(&self, setting: &PreTestSetting) -> Result<()> {
+        if setting.require_consent && self.consent_at.is_none() {
+            return Err(anyhow!(ApplicationError::ConsentRequiredError(
+                self.anonymized_id.clone()
+            )));
+        }
+        Ok(())
+    }
+
+    // 記録をdirectory/<匿名id>.jsonに保存----------------------------------------------
+    pub fn save(&self, directory: &PathBuf) -> Result<()> {
+        if directory.exists() == false {
+            fs::create_dir_all(directory).with_path(directory)?;
+        }
+        let path = directory.join(format!("{}.json", self.anonymized_id));
+        let json_string = serde_json::to_string_pretty(&self).with_path(&path)?;
+        storage::write_atomic(&path, json_string.as_bytes())?;
+        info!("save participant record: {:?}", &path);
+        Ok(())
+    }
+
+    pub fn get_anonymized_id(&self) -> String {
+        self.anonymized_id.clone()
+    }
+}
+
 // テストの比較対象のカテゴリを操作する構造体========================================
 // 注意：カテゴリ内の音声ファイルの名前はカテゴリ間で同じものとする必要あり．
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -44,24 +382,48 @@ pub struct Categories {
     names: Vec<String>,
     original_paths: Vec<PathBuf>,
     filenames: Vec<String>,
+    #[serde(default)]
+    metadata: Vec<Vec<AudioMetadata>>, // [カテゴリ][ファイル]の順
+    #[serde(default)]
+    report: ValidationReport,
+    #[serde(default)]
+    stored_filenames: Vec<String>, // テストのディレクトリにコピーした後のファイル名(空なら元と同じ)
+    #[serde(default)]
+    loudness: Vec<Vec<LoudnessRecord>>, // ラウドネス正規化で適用したゲイン([カテゴリ][ファイル]の順)
 }
 impl Categories {
-    fn new(names: Vec<String>, original_paths: Vec<PathBuf>, filenames: Vec<String>) -> Categories {
+    fn new(
+        names: Vec<String>,
+        original_paths: Vec<PathBuf>,
+        filenames: Vec<String>,
+        metadata: Vec<Vec<AudioMetadata>>,
+        report: ValidationReport,
+    ) -> Categories {
         Categories {
             names: names,
             original_paths: original_paths,
             filenames: filenames,
+            metadata: metadata,
+            report: report,
+            stored_filenames: Vec::new(),
+            loudness: Vec::new(),
         }
     }
 
     // 構造体を構成------------------------------------------------------------
     pub fn setup(categories: Vec<(String, PathBuf)>) -> Result<Categories> {
-        let mut names: Vec<String> = Vec::new();
         let mut original_paths: Vec<PathBuf> = Vec::new();
         let mut filenames: Vec<String> = Vec::new();
 
+        // カテゴリ名はディレクトリ名になる(空白はアンダーバーに置換．同じ名前になるカテゴリはエラー)
+        let slugs = categories
+            .iter()
+            .map(|category| Slug::from_display_name(&category.0, IdentifierKind::Category))
+            .collect::<Result<Vec<Slug>>>()?;
+        Slug::check_unique(&slugs, IdentifierKind::Category)?;
+        let names: Vec<String> = slugs.into_iter().map(|slug| slug.into_string()).collect();
+
         for (i, category) in categories.iter().enumerate() {
-            names.push(category.0.replace(" ", "_")); // カテゴリ名の空白をアンダーバーに置換
             original_paths.push(category.1.clone());
 
             let _filenames = Categories::glob_audio_filenames(category.1.clone())?; // 音声ファイルを取得
@@ -79,15 +441,109 @@ impl Categories {
             }
         }
 
-        Ok(Categories::new(names, original_paths, filenames))
+        // 音声ファイルを検証．エラーがあればセットアップを中止し，警告はログに残して保存
+        let (metadata, report) = Categories::validate_audio(&names, &original_paths, &filenames);
+        if report.errors.is_empty() == false {
+            return Err(anyhow!(ApplicationError::AudioValidationError(report)));
+        }
+        for warning in &report.warnings {
+            warn!("audio validation: {}", warning);
+        }
+
+        Ok(Categories::new(
+            names,
+            original_paths,
+            filenames,
+            metadata,
+            report,
+        ))
+    }
+
+    // 各音声ファイルを読み込んでメタデータを取得し，検証結果をまとめる--------------------------
+    // エラー：デコードできないファイル
+    // 警告：カテゴリ内でサンプリング周波数・チャンネル数が揃っていない，同名ファイルの長さが大きく異なる
+    fn validate_audio(
+        names: &Vec<String>,
+        original_paths: &Vec<PathBuf>,
+        filenames: &Vec<String>,
+    ) -> (Vec<Vec<AudioMetadata>>, ValidationReport) {
+        let mut metadata: Vec<Vec<AudioMetadata>> = Vec::new();
+        let mut report = ValidationReport::default();
+
+        for (name, path) in names.iter().zip(original_paths.iter()) {
+            let mut category_metadata: Vec<AudioMetadata> = Vec::new();
+            for filename in filenames {
+                match probe_audio(&path.join(filename)) {
+                    Ok(m) => {
+                        if m.verified == false {
+                            report.warnings.push(format!(
+                                "{}/{}: {} cannot be verified by decoding",
+                                name, filename, m.codec
+                            ));
+                        }
+                        category_metadata.push(m);
+                    }
+                    Err(e) => report
+                        .errors
+                        .push(format!("{}/{}: unable to decode ({})", name, filename, e)),
+                }
+            }
+            info!("audio probed: {}", name);
+
+            if let Some(first) = category_metadata.first() {
+                if category_metadata
+                    .iter()
+                    .any(|m| m.sample_rate != first.sample_rate)
+                {
+                    report
+                        .warnings
+                        .push(format!("{}: mixed sample rates", name));
+                }
+                if category_metadata
+                    .iter()
+                    .any(|m| m.channels != first.channels)
+                {
+                    report
+                        .warnings
+                        .push(format!("{}: mixed mono/stereo files", name));
+                }
+            }
+            metadata.push(category_metadata);
+        }
+
+        // デコードに失敗したファイルがあると添字が揃わないので，以降の比較はおこなわない
+        if report.errors.is_empty() == false {
+            return (metadata, report);
+        }
+
+        for (i, filename) in filenames.iter().enumerate() {
+            let durations: Vec<f64> = metadata.iter().map(|m| m[i].duration_sec).collect();
+            let max = durations.iter().cloned().fold(f64::MIN, f64::max);
+            let min = durations.iter().cloned().fold(f64::MAX, f64::min);
+            if max - min > DURATION_MISMATCH_TOLERANCE_SEC {
+                report.warnings.push(format!(
+                    "{}: durations differ across categories ({:.2}s - {:.2}s)",
+                    filename, min, max
+                ));
+            }
+            let sample_rates: Vec<u32> = metadata.iter().map(|m| m[i].sample_rate).collect();
+            if sample_rates.iter().any(|r| *r != sample_rates[0]) {
+                report.warnings.push(format!(
+                    "{}: sample rates differ across categories {:?}",
+                    filename, sample_rates
+                ));
+            }
+        }
+
+        (metadata, report)
     }
 
     // 有効な拡張子をもつ音声ファイルを取得---------------------------------------------
     fn glob_audio_filenames(path: PathBuf) -> Result<Vec<String>> {
         let mut filenames: Vec<String> = Vec::new();
-        let entries = fs::read_dir(&path)?;
+        let entries = fs::read_dir(&path).with_path(&path)?;
         for entry in entries {
-            let file_path = entry?.path();
+            let file_path = entry.with_path(&path)?.path();
             let file_name = file_path
                 .file_name()
                 .unwrap()
@@ -95,9 +551,9 @@ impl Categories {
                 .into_owned();
             let period_splitted_name: Vec<&str> = file_name.split('.').collect();
 
-            //有効な拡張子を持つファイルかどうか
+            //有効な拡張子を持つファイルかどうか(大文字小文字は区別しない)
             for extention in AVAILABLE_AUDIO_FILE_EXTENTION {
-                if extention == *period_splitted_name.last().unwrap() {
+                if extention.eq_ignore_ascii_case(period_splitted_name.last().unwrap()) {
                     filenames.push(file_name);
                     break;
                 }
@@ -110,10 +566,339 @@ impl Categories {
     pub fn get_names(&self) -> Vec<String> {
         self.names.clone()
     }
+    // セットアップ情報のカテゴリ(名前とパス)と同じかどうか
+    pub fn matches(&self, categories: &Vec<(String, PathBuf)>) -> bool {
+        self.names.len() == categories.len()
+            && self
+                .names
+                .iter()
+                .zip(self.original_paths.iter())
+                .zip(categories.iter())
+                .all(|((name, path), (new_name, new_path))| {
+                    Slug::from_display_name(new_name, IdentifierKind::Category)
+                        .map_or(false, |slug| slug.as_str() == name)
+                        && path == new_path
+                })
+    }
+    // テストのディレクトリ内の音声ファイル名を返す
     pub fn get_audio_filenames(&self) -> Vec<String> {
-        self.filenames.clone()
+        if self.stored_filenames.is_empty() {
+            return self.filenames.clone();
+        }
+        self.stored_filenames.clone()
+    }
+
+    // 各カテゴリの音声ファイルをdestination_root/<カテゴリ名>にコピーする------------------------
+    // ラウドネス正規化が指定されている場合，またはtranscodeが有効でWebviewが再生できない形式の場合は
+    // デコードしてwavファイルとして書き出す(拡張子はwavに変わる)
+    pub fn copy_into(
+        &mut self,
+        destination_root: &PathBuf,
+        normalization: &Option<LoudnessNormalization>,
+        transcode: bool,
+    ) -> Result<()> {
+        self.copy_except(destination_root, normalization, transcode, None)
+    }
+
+    /*編集で追加・変更されたカテゴリだけを一時ディレクトリ(staging_root)にコピーする--------------------
+    以前と同じカテゴリはコピー済みのファイルと正規化の記録を引き継ぐ
+    テストの設定を保存してからswap_staged()でテストのディレクトリに移す
+    */
+    pub fn stage_added_into(
+        &mut self,
+        previous: &Categories,
+        staging_root: &PathBuf,
+        normalization: &Option<LoudnessNormalization>,
+        transcode: bool,
+    ) -> Result<()> {
+        Categories::discard_staged(staging_root)?; // 以前の編集で残ったものは使わない
+        self.copy_except(staging_root, normalization, transcode, Some(previous))
+    }
+
+    /*一時ディレクトリにコピーしたカテゴリでテストのディレクトリ(destination_root)のカテゴリを置き換える-----
+    削除されたカテゴリのディレクトリを先に消す(大文字と小文字だけが違う名前に変えた場合に備えて)
+    */
+    pub fn swap_staged(
+        &self,
+        previous: &Categories,
+        staging_root: &PathBuf,
+        destination_root: &PathBuf,
+    ) -> Result<()> {
+        for name in &previous.names {
+            let directory = destination_root.join(name);
+            if self.names.contains(name) == false && directory.exists() {
+                fs::remove_dir_all(&directory).with_path(&directory)?;
+                info!("removed category directory: {:?}", directory);
+            }
+        }
+        for name in &self.names {
+            let staged = staging_root.join(name);
+            if staged.exists() == false {
+                continue;
+            }
+            let directory = destination_root.join(name);
+            if directory.exists() {
+                fs::remove_dir_all(&directory).with_path(&directory)?;
+            }
+            fs::rename(&staged, &directory).with_path(&staged)?;
+            info!("category directory replaced: {:?}", directory);
+        }
+        Categories::discard_staged(staging_root)
+    }
+
+    // 一時ディレクトリにコピーしたカテゴリを消す
+    pub fn discard_staged(staging_root: &PathBuf) -> Result<()> {
+        if staging_root.exists() {
+            fs::remove_dir_all(staging_root).with_path(staging_root)?;
+        }
+        Ok(())
+    }
+
+    /*デコードして書き出すファイルが全てデコードできるか検証する(コピーの前に呼ぶ)-----------------------
+    デコーダがないコーデック(Opusなど)はコンテナの情報だけでメタデータを取得しているため(verified: false)，
+    ラウドネス正規化や変換が必要な場合はコピーの途中で失敗しないようにセットアップの時点でエラーにする
+    */
+    pub fn validate_rewrite(
+        &self,
+        normalization: &Option<LoudnessNormalization>,
+        transcode: bool,
+    ) -> Result<()> {
+        let rewrite = self.rewrite_flags(normalization, transcode);
+        let mut errors: Vec<String> = Vec::new();
+        for (name, category_metadata) in self.names.iter().zip(self.metadata.iter()) {
+            for (i, filename) in self.filenames.iter().enumerate() {
+                match category_metadata.get(i) {
+                    Some(m) if rewrite[i] && m.verified == false => errors.push(format!(
+                        "{}/{}: {} cannot be decoded for loudness normalization or transcoding",
+                        name, filename, m.codec
+                    )),
+                    _ => {}
+                }
+            }
+        }
+        if errors.is_empty() == false {
+            return Err(anyhow!(ApplicationError::AudioValidationError(
+                ValidationReport {
+                    errors: errors,
+                    warnings: self.report.warnings.clone(),
+                }
+            )));
+        }
+        Ok(())
+    }
+
+    // ファイルごとにデコードしてwavとして書き出すかどうか
+    fn rewrite_flags(
+        &self,
+        normalization: &Option<LoudnessNormalization>,
+        transcode: bool,
+    ) -> Vec<bool> {
+        self.filenames
+            .iter()
+            .map(|f| normalization.is_some() || (transcode && Categories::is_playable(f) == false))
+            .collect()
     }
-    pub fn get_name_path_iter(&self) -> impl Iterator<Item = (&String, &PathBuf)> {
-        self.names.iter().zip(self.original_paths.iter())
+
+    fn copy_except(
+        &mut self,
+        destination_root: &PathBuf,
+        normalization: &Option<LoudnessNormalization>,
+        transcode: bool,
+        previous: Option<&Categories>,
+    ) -> Result<()> {
+        self.validate_rewrite(normalization, transcode)?;
+        let rewrite = self.rewrite_flags(normalization, transcode);
+        let stored_filenames: Vec<String> = self
+            .filenames
+            .iter()
+            .zip(rewrite.iter())
+            .map(|(f, r)| {
+                if *r {
+                    Categories::to_wav_filename(f)
+                } else {
+                    f.clone()
+                }
+            })
+            .collect();
+        let unique: HashSet<&String> = stored_filenames.iter().collect();
+        if unique.len() != stored_filenames.len() {
+            return Err(anyhow!(ApplicationError::InvalidCategoriesError(
+                "filenames collide after conversion to wav".to_string()
+            )));
+        }
+
+        let mut loudness: Vec<Vec<LoudnessRecord>> = Vec::new();
+        for (name, path) in self.names.iter().zip(self.original_paths.iter()) {
+            // 以前と同じカテゴリ(名前，パス，ファイル)はコピーしない
+            let copied = match previous {
+                Some(previous) if previous.filenames == self.filenames => previous
+                    .names
+                    .iter()
+                    .zip(previous.original_paths.iter())
+                    .position(|(n, p)| n == name && p == path)
+                    .map(|j| previous.loudness.get(j).cloned().unwrap_or_default()),
+                _ => None,
+            };
+            if let Some(records) = copied {
+                info!("category already copied: {}", name);
+                loudness.push(records);
+                continue;
+            }
+
+            let destination = destination_root.join(name);
+            fs::create_dir_all(&destination).with_path(&destination)?;
+
+            let mut records: Vec<LoudnessRecord> = Vec::new();
+            for (i, filename) in self.filenames.iter().enumerate() {
+                let source_path = path.join(filename);
+                let destination_path = destination.join(&stored_filenames[i]);
+                if rewrite[i] == false {
+                    fs::copy(&source_path, &destination_path).with_path(&source_path)?;
+                    continue;
+                }
+
+                let mut audio = decode_audio(&source_path)?;
+                if let Some(option) = normalization {
+                    let record = normalize(&mut audio, option);
+                    info!("loudness normalized: {}/{} ({:?})", name, filename, record);
+                    records.push(record);
+                }
+                write_wav(&destination_path, &audio)?;
+                info!(
+                    "audio converted: {:?} -> {:?}",
+                    source_path, destination_path
+                );
+            }
+            info!("directory copied: from {:?} to {:?}", path, destination);
+            loudness.push(records);
+        }
+
+        self.stored_filenames = stored_filenames;
+        if normalization.is_some() {
+            self.loudness = loudness;
+        }
+        Ok(())
+    }
+
+    // Webviewがそのまま再生できる形式かどうか
+    fn is_playable(filename: &String) -> bool {
+        let extension = filename.rsplit('.').next().unwrap_or("");
+        WEBVIEW_PLAYABLE_AUDIO_FILE_EXTENTION
+            .iter()
+            .any(|e| e.eq_ignore_ascii_case(extension))
+    }
+
+    fn to_wav_filename(filename: &String) -> String {
+        let path = PathBuf::from(filename);
+        match path.extension() {
+            Some(e) if e.eq_ignore_ascii_case("wav") => filename.clone(),
+            _ => path.with_extension("wav").to_string_lossy().into_owned(),
+        }
+    }
+}
+
+// 音声ファイルの検証結果=======================================================
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ValidationReport {
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+}
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.errors.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::TRIAL_DIRNAME;
+    use serde_json::{json, Value};
+
+    // テストごとに空の一時ディレクトリを用意する
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("tloyt_relocate_{}_{}", name, std::process::id()));
+        if dir.exists() {
+            fs::remove_dir_all(&dir).unwrap();
+        }
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn path_string(path: PathBuf) -> String {
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn rewrites_paths_under_old_root() {
+        let dir = scratch_dir("rewrite");
+        let old_root = dir.join("old_test");
+        let new_root = dir.join("new_test");
+        let trial_dir = dir.join(TRIAL_DIRNAME);
+        fs::create_dir_all(&trial_dir).unwrap();
+        let sibling = path_string(dir.join("old_test_2").join("a.wav"));
+        let result = json!({
+            "schema_version": 1,
+            "scores": [{
+                "category": "old_test",
+                "audio_file_path": path_string(old_root.join("categories").join("a.wav")),
+                "other": sibling.clone(),
+                "nested": [path_string(old_root.clone())]
+            }]
+        });
+        let result_path = trial_dir.join("P1.json");
+        fs::write(&result_path, result.to_string()).unwrap();
+        let note_path = trial_dir.join("note.txt");
+        fs::write(&note_path, path_string(old_root.clone())).unwrap();
+
+        relocate_results(&trial_dir, &old_root, &new_root).unwrap();
+
+        let relocated: Value = storage::read_json(&result_path).unwrap();
+        let score = &relocated["scores"][0];
+        assert_eq!(
+            score["audio_file_path"],
+            path_string(new_root.join("categories").join("a.wav"))
+        );
+        assert_eq!(score["nested"][0], path_string(new_root.clone()));
+        // 名前の前方が一致するだけの別のディレクトリやパスでない値は書き換えない
+        assert_eq!(score["other"], sibling);
+        assert_eq!(score["category"], "old_test");
+        assert_eq!(relocated["schema_version"], 1);
+        assert_eq!(
+            fs::read_to_string(&note_path).unwrap(),
+            path_string(old_root.clone())
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn ignores_missing_trial_dir() {
+        let dir = scratch_dir("missing");
+        let trial_dir = dir.join(TRIAL_DIRNAME);
+        assert!(relocate_results(&trial_dir, &dir.join("a"), &dir.join("b")).is_ok());
+        assert!(trial_dir.exists() == false);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    // 読めないファイルがあれば何も書き換えない
+    #[test]
+    fn leaves_results_untouched_on_error() {
+        let dir = scratch_dir("corrupt");
+        let old_root = dir.join("old_test");
+        let trial_dir = dir.join(TRIAL_DIRNAME);
+        fs::create_dir_all(&trial_dir).unwrap();
+        let original = json!({"audio_file_path": path_string(old_root.join("a.wav"))}).to_string();
+        let valid_path = trial_dir.join("P1.json");
+        fs::write(&valid_path, &original).unwrap();
+        fs::write(trial_dir.join("P2.json"), "{\"scores\": ").unwrap();
+
+        let err = relocate_results(&trial_dir, &old_root, &dir.join("new_test")).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<ApplicationError>(),
+            Some(ApplicationError::FileFormatError(..))
+        ));
+        assert_eq!(fs::read_to_string(&valid_path).unwrap(), original);
+        fs::remove_dir_all(&dir).unwrap();
     }
 }
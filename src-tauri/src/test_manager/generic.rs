@@ -0,0 +1,1033 @@
+use crate::app::TestType;
+use crate::audio::loudness::LoudnessNormalization;
+use crate::constants::{
+    CATEGORIES_DIRNAME, CATEGORIES_STAGING_DIRNAME, PARTICIPANT_DIRNAME, QUESTIONNAIRE_DIRNAME,
+    TEST_MANAGER_DIRNAME, TEST_MANAGER_SETTING_FILENAME, TRIAL_DIRNAME,
+};
+use crate::error::{invalid_setup, ApplicationError, WithPath};
+use crate::identifier::{IdentifierKind, Slug};
+use crate::questionnaire::{
+    validate_answers, validate_questions, Answers, Question, QuestionnaireResponse,
+};
+use crate::schema;
+use crate::storage;
+use crate::test_manager::{
+    default_sessions_per_participant, participant_slugs, relocate_results, Categories, DisplayText,
+    EditKind, ParticipantRecord, ParticipantStatus, PreTestSetting, TestManager, TestMethod,
+    TestTypeDescription, TrialResult, TrialView,
+};
+use crate::test_trial::{
+    ScoreInput, SessionRecord, TestTrial, TrialProgress, TrialRule, TrialStatus,
+};
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use chrono::{Local, NaiveDate};
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use serde_json;
+
+/*テストのセットアップのための情報を保持する構造体==========================================
+フロントエンドとの情報共有をこの構造体をシリアライズした文字列を通しておこなう
+方式固有の設定(MOSの繰り返し回数など)はmethodとしてトップレベルに展開される
+*/
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(bound = "M: TestMethod")]
+struct SetupInfo<M: TestMethod> {
+    name: String,
+    author: String,
+    description: String,
+    participants: Vec<String>,
+    #[serde(default)]
+    participant_groups: Vec<String>, // 参加者プールのグループ
+    #[serde(default = "default_sessions_per_participant")]
+    sessions_per_participant: usize, // 参加者ごとの受験回数
+    categories: Vec<(String, PathBuf)>,
+
+    time_limit: usize,
+    #[serde(default)]
+    trial_rule: TrialRule,
+    #[serde(default)]
+    loudness_normalization: Option<LoudnessNormalization>,
+    #[serde(default)]
+    transcode_unplayable: bool,
+    #[serde(default)]
+    pre_test: PreTestSetting,
+    #[serde(default)]
+    post_test: Vec<Question>, // 受験後アンケート
+    #[serde(default)]
+    forked_from: Option<String>, // 複製元のテスト名
+    #[serde(default = "M::default_display_text")]
+    display_text: DisplayText,
+    #[serde(flatten)]
+    method: M,
+}
+
+/*テストの方式に共通のテストマネージャ=============================================
+参加者・カテゴリ・アンケート・保存などの処理をまとめ，出題の生成と採点はトライアルの型(M::Trial)に任せる
+*/
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(bound = "M: TestMethod")]
+pub struct GenericManager<M: TestMethod> {
+    manager_data_root: PathBuf,
+    name: String,
+    #[serde(default)]
+    display_name: Option<String>, // 入力されたテスト名(記録する前のテストはNone)
+    test_type: TestType,
+    author: String,
+    created_date: NaiveDate,
+    modified_date: NaiveDate,
+    description: String,
+    categories: Categories,
+    participants: HashMap<String, ParticipantStatus>,
+    #[serde(default)]
+    participant_records: HashMap<String, ParticipantRecord>, // 名前 -> 参加者の記録
+    #[serde(default)]
+    participant_groups: Vec<String>,
+    #[serde(default = "default_sessions_per_participant")]
+    sessions_per_participant: usize, // 参加者ごとの受験回数
+    #[serde(default)]
+    sessions: HashMap<String, Vec<SessionRecord>>, // 名前 -> 受験済みのセッション
+    time_limit: usize,
+    #[serde(default)]
+    trial_rule: TrialRule,
+    #[serde(default)]
+    loudness_normalization: Option<LoudnessNormalization>,
+    #[serde(default)]
+    transcode_unplayable: bool,
+    #[serde(default)]
+    pre_test: PreTestSetting,
+    #[serde(default)]
+    post_test: Vec<Question>, // 受験後アンケート
+    #[serde(default)]
+    forked_from: Option<String>, // 複製元のテスト名
+    #[serde(default = "M::default_display_text")]
+    display_text: DisplayText,
+    #[serde(flatten)]
+    method: M,
+    active_trial: Option<M::Trial>,
+    #[serde(default)]
+    active_session: Option<usize>, // 実施中のトライアルが何回目の受験か
+}
+
+#[allow(dead_code)]
+impl<M: TestMethod> TestManager for GenericManager<M> {
+    fn get_name(&self) -> String {
+        self.name.clone()
+    }
+
+    // 新しいトライアルを生成------------------------------------------------------
+    fn launch_trial(&mut self, examinee: String) -> Result<()> {
+        // 参加者のリストの中に，受験者の名前がないとエラー
+        if self.participants.contains_key(&examinee) == false {
+            error!("there is no participant: {}", &examinee);
+            return Err(anyhow!(ApplicationError::UnregisteredParticipantError(
+                examinee,
+                self.name.clone()
+            )));
+        }
+        //　受験者が既に全ての回数を受けていたらエラー
+        let session_index = match self.next_session_index(&examinee) {
+            Some(index) => index,
+            None => {
+                error!("this participant has already taken test: {}", examinee);
+                return Err(anyhow!(ApplicationError::AlreadyTakenTrialError(examinee)));
+            }
+        };
+        // 受験前アンケート(同意)が済んでいなければエラー
+        match self.participant_records.get(&examinee) {
+            Some(record) => record.check_ready(&self.pre_test)?,
+            None if self.pre_test.require_consent => {
+                return Err(anyhow!(ApplicationError::ConsentRequiredError(examinee)));
+            }
+            None => {}
+        }
+
+        // 結果は匿名化したidとセッションの番号で保存する
+        let new_trial = self.method.generate_trial(
+            self.manager_data_root.clone(),
+            self.get_session_key(&examinee, session_index),
+            self.categories.clone(),
+            self.trial_rule.clone(),
+        )?;
+
+        self.active_trial = Some(new_trial);
+        self.active_session = Some(session_index);
+        info!("session {} of {}: {}", session_index, self.name, examinee);
+        Ok(())
+    }
+
+    //トライアルを終了させる-------------------------------------------------
+    fn close_trial(&mut self, examinee: String) -> Result<()> {
+        // トライアルの結果を保存して受験済みのセッションに加える(実施中の受験者でなければエラー)
+        if let (Some(trial), Some(session_index)) = (&self.active_trial, self.active_session) {
+            if trial.get_examinee() != self.get_session_key(&examinee, session_index) {
+                error!(
+                    "{} is not taking the active trial of {}",
+                    &examinee, self.name
+                );
+                return Err(anyhow!(ApplicationError::InactiveExamineeError(examinee)));
+            }
+            let session = trial.save_result(session_index)?;
+            self.add_session(&examinee, session);
+        }
+
+        self.active_trial = None;
+        self.active_session = None;
+        self.save_setting()?;
+        info!(
+            "trial finished: test: {}, examinee: {}",
+            self.name, examinee
+        );
+        Ok(())
+    }
+
+    // 最後のセッションの結果をごみ箱(trash_path)に移動し，そのセッションの記録を返す-------------
+    fn delete_trial(&mut self, examinee: String, trash_path: &PathBuf) -> Result<SessionRecord> {
+        let session = match self.sessions.get(&examinee).and_then(|s| s.last()) {
+            Some(session) => session.clone(),
+            None => {
+                let trial_json_path = self.get_trial_json_path(&examinee, 1);
+                error!("there is no trial data: {:?}", &trial_json_path);
+                return Err(anyhow!(ApplicationError::TrialDataNotFoundError(
+                    trial_json_path
+                )));
+            }
+        };
+        let trial_json_path = self.get_trial_json_path(&examinee, session.index);
+
+        // 削除するデータがそもそも無い場合はエラー
+        if trial_json_path.exists() == false {
+            error!("there is no trial data: {:?}", &trial_json_path);
+            return Err(anyhow!(ApplicationError::TrialDataNotFoundError(
+                trial_json_path
+            )));
+        }
+
+        storage::move_file(&trial_json_path, trash_path)?; //ファイルをごみ箱に移動
+        info!("trial data moved to trash: {:?}", trash_path);
+        self.remove_session(&examinee, session.index); // 受験者のステータスを更新
+        self.save_setting()?;
+        Ok(session)
+    }
+
+    /*ごみ箱(trash_path)からセッションの結果を戻す---------------------------------------------
+    参加者から外された場合や，削除した後に同じ回を受験し直した場合は戻さない
+    */
+    fn restore_trial(
+        &mut self,
+        examinee: String,
+        session: SessionRecord,
+        trash_path: &PathBuf,
+    ) -> Result<()> {
+        if self.participants.contains_key(&examinee) == false {
+            return Err(anyhow!(ApplicationError::UnregisteredParticipantError(
+                examinee,
+                self.name.clone()
+            )));
+        }
+        let trial_json_path = self.get_trial_json_path(&examinee, session.index);
+        if self
+            .get_sessions(&examinee)
+            .iter()
+            .any(|s| s.index == session.index)
+            || trial_json_path.exists()
+        {
+            return Err(anyhow!(ApplicationError::AlreadyTakenTrialError(examinee)));
+        }
+        if let Some(trial_dir) = trial_json_path.parent() {
+            fs::create_dir_all(trial_dir).with_path(trial_dir)?;
+        }
+
+        storage::move_file(trash_path, &trial_json_path)?;
+        info!("trial data restored: {:?}", trial_json_path);
+        self.add_session(&examinee, session);
+        self.save_setting()?;
+        Ok(())
+    }
+
+    // テストのプレビューを開始-------------------------------------------------
+    fn launch_preview(&mut self) -> Result<()> {
+        // 受験者の名前を設定せずにトライアルを生成
+        let preview_trial = self.method.generate_trial(
+            self.manager_data_root.clone(),
+            String::new(),
+            self.categories.clone(),
+            self.trial_rule.clone(),
+        )?;
+
+        self.active_trial = Some(preview_trial);
+        Ok(())
+    }
+
+    // テストのプレビューを終了--------------------------------------------------
+    fn close_preview(&mut self) -> Result<()> {
+        // 結果は保存しない
+        self.active_trial = None;
+        self.active_session = None;
+        Ok(())
+    }
+
+    // 実施中のトライアルを結果を保存せずに中止する(受験者は未受験のまま)---------------------
+    fn abort_trial(&mut self) -> Result<()> {
+        if self.active_trial.is_none() {
+            return Err(anyhow!(ApplicationError::NoActiveTrialError));
+        }
+        self.active_trial = None;
+        self.active_session = None;
+        info!("trial aborted: {}", self.name);
+        Ok(())
+    }
+
+    // マネージャの情報を編集---------------------------------------------------
+    fn edit(&mut self, json_string: String) -> Result<()> {
+        let info: SetupInfo<M> = serde_json::from_str(&json_string).map_err(invalid_setup)?;
+        info!("test edit: {:?}", info.clone());
+        let participants = participant_slugs(&info.participants)?;
+
+        // 結果のあるテストは構造的な変更を受け付けない(新しいバージョンとして複製する)
+        if self.classify_edit(&info, &participants) == EditKind::Structural && self.has_results() {
+            error!("structural edit is refused: {}", self.name);
+            return Err(anyhow!(ApplicationError::StructuralEditError(
+                self.name.clone()
+            )));
+        }
+        info.pre_test.validate()?;
+        validate_questions(&info.post_test)?;
+        info.display_text.validate(M::num_score_labels())?;
+        GenericManager::<M>::validate_sessions_per_participant(info.sessions_per_participant)?;
+
+        /*カテゴリが変わった場合は追加されたカテゴリの音声を一時ディレクトリにコピーしておき，
+        設定を保存してからテストのディレクトリに移す(失敗した場合は元の設定とカテゴリのまま)
+        */
+        let previous = self.clone();
+        let staging_root = self.manager_data_root.join(CATEGORIES_STAGING_DIRNAME);
+        let categories_changed = self.categories.matches(&info.categories) == false;
+        if categories_changed {
+            let mut categories = Categories::setup(info.categories)?;
+            if let Err(e) = categories.stage_added_into(
+                &self.categories,
+                &staging_root,
+                &self.loudness_normalization,
+                self.transcode_unplayable,
+            ) {
+                Categories::discard_staged(&staging_root)?;
+                return Err(e);
+            }
+            self.categories = categories;
+        }
+
+        // 名前の変更はrenameでおこなう(ディレクトリの移動が必要なため)
+        self.author = info.author;
+        self.modified_date = Local::now().date_naive();
+        self.description = info.description;
+        self.pre_test = info.pre_test;
+        self.post_test = info.post_test;
+        self.display_text = info.display_text;
+        self.edit_participants(participants);
+        self.participant_groups = info.participant_groups;
+        self.sessions_per_participant = info.sessions_per_participant;
+        self.update_all_status();
+        self.time_limit = info.time_limit;
+        self.trial_rule = info.trial_rule;
+        self.method = info.method;
+        if let Err(e) = self.save_setting() {
+            error!("failed to save edited test: {}", e);
+            *self = previous;
+            Categories::discard_staged(&staging_root)?;
+            return Err(e);
+        }
+        if categories_changed {
+            self.categories.swap_staged(
+                &previous.categories,
+                &staging_root,
+                &self.manager_data_root.join(CATEGORIES_DIRNAME),
+            )?;
+        }
+        Ok(())
+    }
+
+    // テスト名を変更する(ディレクトリの移動はApplicationManagerがおこなう)-----------------------
+    // 結果ファイル内のパスを新しいディレクトリに書き換え，保存に失敗した場合は元に戻す
+    fn rename(&mut self, new_name: Slug, new_data_root: PathBuf) -> Result<()> {
+        let trial_dir = new_data_root.join(TRIAL_DIRNAME);
+        let old_name = self.name.clone();
+        let old_data_root = self.manager_data_root.clone();
+        relocate_results(&trial_dir, &old_data_root, &new_data_root)?;
+
+        let old_display_name = self.display_name.clone();
+        self.display_name = Some(new_name.display_name().to_string());
+        self.name = new_name.into_string();
+        self.manager_data_root = new_data_root.clone();
+        if let Err(e) = self.save_setting() {
+            error!("failed to save renamed test: {}", e);
+            self.name = old_name;
+            self.display_name = old_display_name;
+            self.manager_data_root = old_data_root.clone();
+            relocate_results(&trial_dir, &new_data_root, &old_data_root)?;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    // テスト音声のトークンを返す------------------------------------------------
+    fn get_audio(&mut self) -> Result<Vec<String>> {
+        let tokens = self
+            .active_trial
+            .as_mut()
+            .ok_or(anyhow!(ApplicationError::NoActiveTrialError))?
+            .get_audio()?;
+        Ok(tokens)
+    }
+
+    // トークンに対応する音声ファイルのパスを返す---------------------------------------
+    fn resolve_audio(&mut self, token: &str) -> Result<PathBuf> {
+        match &mut self.active_trial {
+            Some(trial) => trial.resolve_audio(token),
+            None => Err(anyhow!(ApplicationError::NoActiveTrialError)),
+        }
+    }
+
+    // 音声の再生終了を記録する---------------------------------------------------
+    fn report_playback_end(&mut self, token: &str) -> Result<()> {
+        match &mut self.active_trial {
+            Some(trial) => trial.report_playback_end(token),
+            None => Err(anyhow!(ApplicationError::NoActiveTrialError)),
+        }
+    }
+
+    // 評価結果を格納--------------------------------------------------------
+    fn set_score(&mut self, input: ScoreInput) -> Result<TrialStatus> {
+        let trial = self
+            .active_trial
+            .as_mut()
+            .ok_or(anyhow!(ApplicationError::NoActiveTrialError))?;
+        trial.set_score(input)?;
+        let status = trial.to_next()?;
+        Ok(status)
+    }
+
+    // 現在の設問を回答せずに飛ばす------------------------------------------------------
+    fn skip_item(&mut self) -> Result<TrialStatus> {
+        match self.active_trial.as_mut() {
+            Some(trial) => trial.skip_item(),
+            None => Err(anyhow!(ApplicationError::NoActiveTrialError)),
+        }
+    }
+
+    // 制限時間を過ぎた設問を時間切れとして記録する(記録した場合は進んだ後の状態を返す)------------
+    fn check_expiry(&mut self) -> Result<Option<TrialStatus>> {
+        match self.active_trial.as_mut() {
+            Some(trial) => trial.check_expiry(),
+            None => Err(anyhow!(ApplicationError::NoActiveTrialError)),
+        }
+    }
+
+    // 実施中のトライアルの進み具合-------------------------------------------------
+    fn get_progress(&self) -> Result<TrialProgress> {
+        match &self.active_trial {
+            Some(trial) => Ok(trial.get_progress()),
+            None => Err(anyhow!(ApplicationError::NoActiveTrialError)),
+        }
+    }
+
+    // 受験前アンケートの回答と同意を記録------------------------------------------------
+    fn register_participant(
+        &mut self,
+        examinee: String,
+        answers: Answers,
+        consent: bool,
+    ) -> Result<()> {
+        if self.participants.contains_key(&examinee) == false {
+            error!("there is no participant: {}", &examinee);
+            return Err(anyhow!(ApplicationError::UnregisteredParticipantError(
+                examinee,
+                self.name.clone()
+            )));
+        }
+        if self.next_session_index(&examinee).is_none() {
+            return Err(anyhow!(ApplicationError::AlreadyTakenTrialError(examinee)));
+        }
+
+        let record = self
+            .participant_records
+            .entry(examinee.clone())
+            .or_insert_with(|| ParticipantRecord::new(None));
+        record.register(&self.pre_test, answers, consent)?;
+        record.save(&self.manager_data_root.join(PARTICIPANT_DIRNAME))?;
+        info!("participant registered: {}", record.get_anonymized_id());
+        self.save_setting()?;
+        Ok(())
+    }
+
+    // 受験後アンケートの回答を検証して最後のセッションの結果と同じキーで保存-----------------------
+    fn submit_post_test(&mut self, examinee: String, answers: Answers) -> Result<()> {
+        if self.participants.contains_key(&examinee) == false {
+            error!("there is no participant: {}", &examinee);
+            return Err(anyhow!(ApplicationError::UnregisteredParticipantError(
+                examinee,
+                self.name.clone()
+            )));
+        }
+        // テストを終えた受験者のみ回答できる
+        let session_index = match self.get_sessions(&examinee).last() {
+            Some(session) => session.index,
+            None => {
+                error!("this participant has not finished test: {}", &examinee);
+                return Err(anyhow!(ApplicationError::UnfinishedTrialError(examinee)));
+            }
+        };
+        validate_answers(&self.post_test, &answers)?;
+
+        let response = QuestionnaireResponse::new(answers);
+        response.save(
+            &self.manager_data_root.join(QUESTIONNAIRE_DIRNAME),
+            &self.get_session_key(&examinee, session_index),
+        )?;
+        Ok(())
+    }
+
+    // 参加者プールのグループに追加されたメンバーを参加者に加える---------------------------------
+    fn add_participants(&mut self, participants: Vec<String>) -> Result<()> {
+        let mut added = false;
+        for p in participants {
+            if self.participants.contains_key(&p) == false {
+                self.participants.insert(p.clone(), ParticipantStatus::Yet);
+                self.participant_records
+                    .insert(p, ParticipantRecord::new(None));
+                added = true;
+            }
+        }
+        if added {
+            self.save_setting()?;
+        }
+        Ok(())
+    }
+
+    fn get_participant_status(&self, examinee: &String) -> Option<ParticipantStatus> {
+        self.participants.get(examinee).cloned()
+    }
+
+    fn get_participant_groups(&self) -> Vec<String> {
+        self.participant_groups.clone()
+    }
+
+    // 結果ファイルを全て読み込む(保存時のバックアップ(.bak)や一時ファイル(.tmp)は除く)---------------
+    fn get_results(&self) -> Result<Vec<TrialResult>> {
+        let trial_dir = self.manager_data_root.join(TRIAL_DIRNAME);
+        let mut results: Vec<TrialResult> = Vec::new();
+        if trial_dir.exists() == false {
+            return Ok(results);
+        }
+        for entry in fs::read_dir(&trial_dir).with_path(&trial_dir)? {
+            let path = entry.with_path(&trial_dir)?.path();
+            if path.extension().map_or(true, |e| e != "json") {
+                continue;
+            }
+            let key = match path.file_stem() {
+                Some(stem) => stem.to_string_lossy().into_owned(),
+                None => continue,
+            };
+            let scores: serde_json::Value = schema::read_document(&path, M::RESULT_DOCUMENT)?;
+            results.push(TrialResult {
+                key: key,
+                scores: scores,
+            });
+        }
+        results.sort_by(|a, b| a.key.cmp(&b.key));
+        Ok(results)
+    }
+
+    // セットアップ時にカテゴリのフォルダをアプリケーションデータのフォルダにコピー-----------------
+    // テストのディレクトリはここで初めて作る(setupでは作らない)
+    fn copy_categories(&mut self) -> Result<()> {
+        fs::create_dir_all(&self.manager_data_root).with_path(&self.manager_data_root)?;
+        let destination_root = self.manager_data_root.join(CATEGORIES_DIRNAME);
+        self.categories.copy_into(
+            &destination_root,
+            &self.loudness_normalization,
+            self.transcode_unplayable,
+        )?;
+        info!("all category copied successfully");
+        Ok(())
+    }
+
+    // マネージャの設定を保存-------------------------------------------------------
+    fn save_setting(&self) -> Result<()> {
+        let json_path = self.manager_data_root.join(TEST_MANAGER_SETTING_FILENAME);
+
+        let document = schema::to_document(M::SETTING_DOCUMENT, &self)?;
+        let json_string = serde_json::to_string_pretty(&document).with_path(&json_path)?;
+        storage::write_atomic(&json_path, json_string.as_bytes())?;
+        info!(
+            "save setting successfully: {:?}\ndata: {}",
+            json_path, json_string
+        );
+        Ok(())
+    }
+
+    // 参加者名と結果ファイルの対応(匿名化したid)はフロントエンドに渡さない
+    fn get_setting(&self) -> Result<String> {
+        let mut setting = serde_json::to_value(&self)?;
+        if let Some(records) = setting
+            .get_mut("participant_records")
+            .and_then(|records| records.as_object_mut())
+        {
+            for record in records.values_mut() {
+                if let Some(record) = record.as_object_mut() {
+                    record.remove("anonymized_id");
+                }
+            }
+        }
+        let json_string = serde_json::to_string_pretty(&setting)?;
+        Ok(json_string)
+    }
+
+    fn get_trial_view(&self) -> TrialView {
+        TrialView {
+            name: self.name.clone(),
+            display_name: self.display_name.clone(),
+            test_type: self.test_type.clone(),
+            description: self.description.clone(),
+            time_limit: self.time_limit,
+            display_text: self.display_text.clone(),
+        }
+    }
+}
+
+impl<M: TestMethod> GenericManager<M> {
+    // jsonファイルをデシリアライズして構造体を生成------------------------------------------
+    pub fn from_json(path_to_test_config: PathBuf) -> Result<GenericManager<M>> {
+        // ファイルもバックアップもなければエラー
+        if storage::exists(&path_to_test_config) == false {
+            return Err(anyhow!(ApplicationError::TestDataNotFoundError(
+                path_to_test_config
+            )));
+        }
+        let mut this_test: GenericManager<M> =
+            schema::read_document(&path_to_test_config, M::SETTING_DOCUMENT)?;
+        this_test.fill_legacy_sessions();
+        return Ok(this_test);
+    }
+
+    // フロントエンドから送られたセットアップ情報から構造体を構成------------------------------
+    pub fn setup(app_data_root: PathBuf, json_string: String) -> Result<GenericManager<M>> {
+        let info: SetupInfo<M> = serde_json::from_str(&json_string).map_err(invalid_setup)?;
+        let name = Slug::from_display_name(&info.name, IdentifierKind::Test)?;
+        let participants = participant_slugs(&info.participants)?;
+        let categories = Categories::setup(info.categories)?;
+        categories.validate_rewrite(&info.loudness_normalization, info.transcode_unplayable)?;
+        let manager_data_root = GenericManager::<M>::get_manager_data_root(app_data_root, &name);
+        info.pre_test.validate()?;
+        validate_questions(&info.post_test)?;
+        info.display_text.validate(M::num_score_labels())?;
+        GenericManager::<M>::validate_sessions_per_participant(info.sessions_per_participant)?;
+
+        return Ok(GenericManager {
+            manager_data_root: manager_data_root,
+            display_name: Some(name.display_name().to_string()),
+            name: name.into_string(),
+            test_type: TestType::new(M::TYPE_ID),
+            author: info.author,
+            created_date: Local::now().date_naive(),
+            modified_date: Local::now().date_naive(),
+            description: info.description,
+            categories: categories,
+            participant_records: GenericManager::<M>::setup_participant_records(&participants),
+            participants: GenericManager::<M>::setup_participants(&participants),
+            participant_groups: info.participant_groups,
+            sessions_per_participant: info.sessions_per_participant,
+            sessions: HashMap::new(),
+            time_limit: info.time_limit,
+            trial_rule: info.trial_rule,
+            loudness_normalization: info.loudness_normalization,
+            transcode_unplayable: info.transcode_unplayable,
+            pre_test: info.pre_test,
+            post_test: info.post_test,
+            display_text: info.display_text,
+            forked_from: info.forked_from,
+            method: info.method,
+            active_trial: None,
+            active_session: None,
+        });
+    }
+
+    // フロントエンドに渡すテストの種類の説明(方式固有の設定項目は既定値で示す)-------------------
+    pub fn describe() -> TestTypeDescription {
+        TestTypeDescription {
+            id: TestType::new(M::TYPE_ID),
+            display_name: M::DISPLAY_NAME.to_string(),
+            num_score_labels: M::num_score_labels(),
+            default_display_text: M::default_display_text(),
+            method_setting: serde_json::to_value(M::default()).unwrap_or_default(),
+        }
+    }
+
+    // マネージャの情報を保存するディレクトリを返す-------------------------------------------
+    // テストのディレクトリのパス(ディレクトリはcopy_categoriesで作る)
+    fn get_manager_data_root(app_data_root: PathBuf, test_name: &Slug) -> PathBuf {
+        app_data_root
+            .join(TEST_MANAGER_DIRNAME)
+            .join(test_name.as_str())
+    }
+
+    // 受験者の情報をセットアップ--------------------------------------------------------
+    fn setup_participants(participants: &Vec<Slug>) -> HashMap<String, ParticipantStatus> {
+        let mut new_participants: HashMap<String, ParticipantStatus> = HashMap::new();
+        for participant in participants {
+            new_participants.insert(participant.as_str().to_string(), ParticipantStatus::Yet);
+        }
+        return new_participants;
+    }
+
+    // 受験者ごとに匿名化したidを持つ記録を作成------------------------------------------------
+    fn setup_participant_records(participants: &Vec<Slug>) -> HashMap<String, ParticipantRecord> {
+        participants
+            .iter()
+            .map(|p| {
+                let record = ParticipantRecord::new(Some(p.display_name().to_string()));
+                (p.as_str().to_string(), record)
+            })
+            .collect()
+    }
+
+    // 結果ファイルの名前に使うキー(匿名化したid．記録のない以前の参加者は名前)---------------------
+    fn get_result_key(&self, examinee: &String) -> String {
+        match self.participant_records.get(examinee) {
+            Some(record) => record.get_anonymized_id(),
+            None => examinee.clone(),
+        }
+    }
+
+    // セッションごとの結果ファイルのキー(1回目は以前と同じ<キー>，2回目以降は<キー>_session<n>)
+    fn get_session_key(&self, examinee: &String, session_index: usize) -> String {
+        let key = self.get_result_key(examinee);
+        if session_index <= 1 {
+            return key;
+        }
+        format!("{}_session{}", key, session_index)
+    }
+
+    fn get_trial_json_path(&self, examinee: &String, session_index: usize) -> PathBuf {
+        self.manager_data_root.join(TRIAL_DIRNAME).join(format!(
+            "{}.json",
+            self.get_session_key(examinee, session_index)
+        ))
+    }
+
+    /*参加者ごとのセッション==========================================================*/
+    fn get_sessions(&self, examinee: &String) -> Vec<SessionRecord> {
+        self.sessions.get(examinee).cloned().unwrap_or_default()
+    }
+
+    // 次に受験するセッションの番号(削除して空いた回を優先する)．全て受験済みならNone
+    fn next_session_index(&self, examinee: &String) -> Option<usize> {
+        let sessions = self.get_sessions(examinee);
+        (1..=self.sessions_per_participant)
+            .find(|i| sessions.iter().any(|s| s.index == *i) == false)
+    }
+
+    fn add_session(&mut self, examinee: &String, session: SessionRecord) {
+        let sessions = self.sessions.entry(examinee.clone()).or_default();
+        sessions.push(session);
+        sessions.sort_by_key(|s| s.index);
+        self.update_status(examinee);
+    }
+
+    fn remove_session(&mut self, examinee: &String, session_index: usize) {
+        if let Some(sessions) = self.sessions.get_mut(examinee) {
+            sessions.retain(|s| s.index != session_index);
+            if sessions.is_empty() {
+                self.sessions.remove(examinee);
+            }
+        }
+        self.update_status(examinee);
+    }
+
+    // 受験済みのセッションの数が受験回数に達したら受験済みにする
+    fn update_status(&mut self, examinee: &String) {
+        let done = self.get_sessions(examinee).len() >= self.sessions_per_participant;
+        if let Some(status) = self.participants.get_mut(examinee) {
+            *status = if done {
+                ParticipantStatus::Done
+            } else {
+                ParticipantStatus::Yet
+            };
+        }
+    }
+
+    fn update_all_status(&mut self) {
+        let participants: Vec<String> = self.participants.keys().cloned().collect();
+        for p in participants {
+            self.update_status(&p);
+        }
+    }
+
+    // セッションを記録する前に受験済みになった参加者は1回目を受験したものとする
+    fn fill_legacy_sessions(&mut self) {
+        for (p, status) in self.participants.iter() {
+            if matches!(status, ParticipantStatus::Done) && self.sessions.contains_key(p) == false {
+                self.sessions
+                    .insert(p.clone(), vec![SessionRecord::unknown(1)]);
+            }
+        }
+    }
+
+    fn validate_sessions_per_participant(sessions_per_participant: usize) -> Result<()> {
+        if sessions_per_participant == 0 {
+            return Err(anyhow!(ApplicationError::InvalidSetupError(
+                "sessions_per_participant must be at least 1".to_string()
+            )));
+        }
+        Ok(())
+    }
+
+    /*編集の種類を判定する----------------------------------------------------------
+    カテゴリ，方式固有の設定(出題数など)，制限時間，ルールの変更と，受験済みの参加者の削除は構造的な変更
+    */
+    fn classify_edit(&self, info: &SetupInfo<M>, participants: &Vec<Slug>) -> EditKind {
+        let removes_done = self
+            .sessions
+            .keys()
+            .any(|p| participants.iter().any(|slug| slug.as_str() == p) == false);
+        if self.categories.matches(&info.categories) == false
+            || self.method.is_structural_change(&info.method)
+            || self.time_limit != info.time_limit
+            || self.trial_rule != info.trial_rule
+            || removes_done
+        {
+            return EditKind::Structural;
+        }
+        EditKind::Cosmetic
+    }
+
+    // 受験済みのセッションがあるかどうか
+    fn has_results(&self) -> bool {
+        self.sessions
+            .values()
+            .any(|sessions| sessions.is_empty() == false)
+    }
+
+    // editメソッドの中で呼び出される．受験者の削除と追加をおこなう---------------
+    // 追加した参加者は入力された表示名を記録する
+    fn edit_participants(&mut self, participants: Vec<Slug>) {
+        let display_names: HashMap<String, String> = participants
+            .iter()
+            .map(|p| (p.as_str().to_string(), p.display_name().to_string()))
+            .collect();
+        let old: HashSet<_> = self.participants.clone().keys().cloned().collect();
+        let new: HashSet<_> = display_names.keys().cloned().collect();
+
+        // HashSetで削除された要素と追加された要素を抽出
+        let added: Vec<String> = new.difference(&old).cloned().collect();
+        let removed: Vec<String> = old.difference(&new).cloned().collect();
+
+        for p in removed {
+            self.participants.remove(&p);
+            self.participant_records.remove(&p);
+            self.sessions.remove(&p);
+        }
+        for p in added {
+            let display_name = display_names.get(&p).cloned();
+            self.participants.insert(p.clone(), ParticipantStatus::Yet);
+            self.participant_records
+                .insert(p, ParticipantRecord::new(display_name));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::fixture_path;
+    use crate::test_manager::mos::MosMethod;
+    use crate::test_manager::thurstone::ThurstoneMethod;
+
+    // 以前のバージョンの設定で省略されていた項目が既定値で補われることを確認する
+    fn assert_legacy_defaults<M: TestMethod>(manager: &GenericManager<M>) {
+        assert_eq!(manager.display_name, None);
+        assert_eq!(manager.sessions_per_participant, 1);
+        assert!(manager.participant_records.is_empty());
+        assert!(manager.participant_groups.is_empty());
+        assert_eq!(manager.trial_rule, TrialRule::default());
+        assert!(manager.loudness_normalization.is_none());
+        assert!(manager.transcode_unplayable == false);
+        assert!(manager.post_test.is_empty());
+        assert_eq!(manager.forked_from, None);
+        assert!(manager.active_trial.is_none());
+        assert_eq!(manager.active_session, None);
+    }
+
+    #[test]
+    fn loads_legacy_mos_setting() {
+        let manager =
+            GenericManager::<MosMethod>::from_json(fixture_path("legacy_mos_setting.json"))
+                .unwrap();
+        assert_eq!(manager.name, "listening_test");
+        assert_eq!(manager.test_type, TestType::new("Mos"));
+        assert_eq!(manager.author, "author");
+        assert_eq!(manager.created_date.to_string(), "2024-07-01");
+        assert_eq!(manager.time_limit, 5);
+        assert_eq!(
+            manager.categories.get_names(),
+            vec!["original".to_string(), "synthesized".to_string()]
+        );
+        assert_eq!(
+            manager.categories.get_audio_filenames(),
+            vec!["sample_01.wav".to_string(), "sample_02.wav".to_string()]
+        );
+        assert!(matches!(
+            manager.participants["participant_1"],
+            ParticipantStatus::Done
+        ));
+        assert!(matches!(
+            manager.participants["participant_2"],
+            ParticipantStatus::Yet
+        ));
+        // 受験済みの参加者には時刻不明のセッションが1回分補われる
+        assert_eq!(
+            manager.sessions["participant_1"],
+            vec![SessionRecord::unknown(1)]
+        );
+        assert!(manager.sessions.contains_key("participant_2") == false);
+        assert_eq!(
+            serde_json::to_value(&manager.method).unwrap()["num_repeat"],
+            1
+        );
+        assert_legacy_defaults(&manager);
+    }
+
+    // 読み込んだ設定と同じ内容の編集情報にoverridesを上書きする
+    fn edit_info(overrides: serde_json::Value) -> (SetupInfo<MosMethod>, Vec<Slug>) {
+        let mut info = serde_json::json!({
+            "name": "listening_test",
+            "author": "author",
+            "description": "",
+            "participants": ["participant_1", "participant_2"],
+            "categories": [["original", "/data/original"], ["synthesized", "/data/synthesized"]],
+            "time_limit": 5,
+            "num_repeat": 1
+        });
+        for (key, value) in overrides.as_object().unwrap() {
+            info[key] = value.clone();
+        }
+        let info: SetupInfo<MosMethod> = serde_json::from_value(info).unwrap();
+        let participants = participant_slugs(&info.participants).unwrap();
+        (info, participants)
+    }
+
+    fn classify(overrides: serde_json::Value) -> EditKind {
+        let manager =
+            GenericManager::<MosMethod>::from_json(fixture_path("legacy_mos_setting.json"))
+                .unwrap();
+        let (info, participants) = edit_info(overrides);
+        manager.classify_edit(&info, &participants)
+    }
+
+    #[test]
+    fn classifies_cosmetic_edits() {
+        assert_eq!(classify(serde_json::json!({})), EditKind::Cosmetic);
+        assert_eq!(
+            classify(serde_json::json!({"author": "someone", "description": "new"})),
+            EditKind::Cosmetic
+        );
+        assert_eq!(
+            classify(serde_json::json!({
+                "participants": ["participant_1", "participant_2", "participant_3"]
+            })),
+            EditKind::Cosmetic
+        );
+        // 受験していない参加者の削除は結果に影響しない
+        assert_eq!(
+            classify(serde_json::json!({"participants": ["participant_1"]})),
+            EditKind::Cosmetic
+        );
+    }
+
+    #[test]
+    fn classifies_structural_edits() {
+        // 受験済みの参加者の削除
+        assert_eq!(
+            classify(serde_json::json!({"participants": ["participant_2"]})),
+            EditKind::Structural
+        );
+        assert_eq!(
+            classify(serde_json::json!({
+                "categories": [["original", "/data/original"], ["vocoded", "/data/synthesized"]]
+            })),
+            EditKind::Structural
+        );
+        assert_eq!(
+            classify(serde_json::json!({
+                "categories": [["original", "/data/original"], ["synthesized", "/data/other"]]
+            })),
+            EditKind::Structural
+        );
+        assert_eq!(
+            classify(serde_json::json!({"categories": [["original", "/data/original"]]})),
+            EditKind::Structural
+        );
+        assert_eq!(
+            classify(serde_json::json!({"time_limit": 10})),
+            EditKind::Structural
+        );
+        assert_eq!(
+            classify(serde_json::json!({"num_repeat": 2})),
+            EditKind::Structural
+        );
+        assert_eq!(
+            classify(serde_json::json!({"trial_rule": {"min_full_listens": 1}})),
+            EditKind::Structural
+        );
+    }
+
+    // フロントエンドに渡す設定には匿名化したidを含めない
+    #[test]
+    fn hides_anonymized_ids_from_setting() {
+        let mut manager =
+            GenericManager::<MosMethod>::from_json(fixture_path("legacy_mos_setting.json"))
+                .unwrap();
+        let record = ParticipantRecord::new(Some("Participant 1".to_string()));
+        let anonymized_id = record.get_anonymized_id();
+        manager
+            .participant_records
+            .insert("participant_1".to_string(), record);
+
+        let setting = manager.get_setting().unwrap();
+        assert!(setting.contains(&anonymized_id) == false);
+        let setting: serde_json::Value = serde_json::from_str(&setting).unwrap();
+        let record = &setting["participant_records"]["participant_1"];
+        assert_eq!(record["display_name"], "Participant 1");
+        assert!(record.get("anonymized_id").is_none());
+    }
+
+    #[test]
+    fn loads_legacy_thurstone_setting() {
+        let manager = GenericManager::<ThurstoneMethod>::from_json(fixture_path(
+            "legacy_thurstone_setting.json",
+        ))
+        .unwrap();
+        assert_eq!(manager.name, "pair_test");
+        assert_eq!(manager.test_type, TestType::new("Thurstone"));
+        assert_eq!(manager.description, "pair comparison");
+        assert_eq!(manager.modified_date.to_string(), "2024-07-02");
+        assert_eq!(manager.time_limit, 3);
+        assert_eq!(
+            manager.categories.get_names(),
+            vec!["original".to_string(), "synthesized".to_string()]
+        );
+        assert!(matches!(
+            manager.participants["participant_1"],
+            ParticipantStatus::Yet
+        ));
+        assert_eq!(
+            manager.sessions["participant_2"],
+            vec![SessionRecord::unknown(1)]
+        );
+        assert!(manager.sessions.contains_key("participant_1") == false);
+        assert_legacy_defaults(&manager);
+    }
+}
@@ -0,0 +1,89 @@
+use crate::app::TestType;
+use crate::error::ApplicationError;
+use crate::test_manager::generic::GenericManager;
+use crate::test_manager::mos::MosMethod;
+use crate::test_manager::thurstone::ThurstoneMethod;
+use crate::test_manager::{TestManager, TestMethod, TestTypeDescription};
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+
+// テストの種類ごとのマネージャの生成方法------------------------------------------------
+struct TestTypeFactory {
+    setup: fn(PathBuf, String) -> Result<Box<dyn TestManager>>,
+    load: fn(PathBuf) -> Result<Box<dyn TestManager>>,
+    describe: fn() -> TestTypeDescription,
+}
+
+/*テストの種類のレジストリ========================================================
+種類のid(test_list.jsonやフロントエンドとやりとりする文字列)ごとに生成・読み込み・説明の方法を持つ
+新しい種類はTestMethodを実装してwith_builtin_types()でregisterする
+*/
+pub struct TestTypeRegistry {
+    factories: HashMap<TestType, TestTypeFactory>,
+}
+
+impl TestTypeRegistry {
+    pub fn with_builtin_types() -> TestTypeRegistry {
+        let mut registry = TestTypeRegistry {
+            factories: HashMap::new(),
+        };
+        registry.register::<MosMethod>();
+        registry.register::<ThurstoneMethod>();
+        registry
+    }
+
+    pub fn register<M: TestMethod>(&mut self) {
+        self.factories.insert(
+            TestType::new(M::TYPE_ID),
+            TestTypeFactory {
+                setup: |app_data_root, json_string| {
+                    Ok(Box::new(GenericManager::<M>::setup(
+                        app_data_root,
+                        json_string,
+                    )?))
+                },
+                load: |setting_json_path| {
+                    Ok(Box::new(GenericManager::<M>::from_json(setting_json_path)?))
+                },
+                describe: GenericManager::<M>::describe,
+            },
+        );
+    }
+
+    // フロントエンドから送られたセットアップ情報からマネージャを生成-----------------------------
+    pub fn setup(
+        &self,
+        test_type: &TestType,
+        app_data_root: PathBuf,
+        json_string: String,
+    ) -> Result<Box<dyn TestManager>> {
+        (self.get(test_type)?.setup)(app_data_root, json_string)
+    }
+
+    // 保存された設定ファイルからマネージャを生成-------------------------------------------
+    pub fn load(
+        &self,
+        test_type: &TestType,
+        setting_json_path: PathBuf,
+    ) -> Result<Box<dyn TestManager>> {
+        (self.get(test_type)?.load)(setting_json_path)
+    }
+
+    pub fn describe_all(&self) -> Vec<TestTypeDescription> {
+        let mut descriptions: Vec<TestTypeDescription> =
+            self.factories.values().map(|f| (f.describe)()).collect();
+        descriptions.sort_by(|a, b| a.id.cmp(&b.id));
+        descriptions
+    }
+
+    fn get(&self, test_type: &TestType) -> Result<&TestTypeFactory> {
+        self.factories
+            .get(test_type)
+            .ok_or(anyhow!(ApplicationError::UnknownTestTypeError(
+                test_type.to_string()
+            )))
+    }
+}
@@ -1,20 +1,665 @@
 pub mod mos;
 pub mod thurstone;
 
-use anyhow::Result;
+use crate::audio::probe_audio;
+use crate::constants::PLAYBACK_END_TOLERANCE_SEC;
+use crate::error::ApplicationError;
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
 pub enum TrialStatus {
     Doing,
     Done,
 }
 
+// 参加者の1回分の受験(セッション)の記録===============================================
+// index: 何回目の受験か(1から)，started_at / finished_at: 開始と終了の時刻(以前の結果では不明)
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SessionRecord {
+    pub index: usize,
+    pub started_at: Option<DateTime<Local>>,
+    pub finished_at: Option<DateTime<Local>>,
+}
+impl SessionRecord {
+    pub fn new(index: usize, started_at: DateTime<Local>) -> SessionRecord {
+        SessionRecord {
+            index: index,
+            started_at: Some(started_at),
+            finished_at: Some(Local::now()),
+        }
+    }
+
+    // セッションを記録する前に保存された結果
+    pub fn unknown(index: usize) -> SessionRecord {
+        SessionRecord {
+            index: index,
+            started_at: None,
+            finished_at: None,
+        }
+    }
+}
+
+// フロントエンドから送られる回答の情報==============================================
+// answer: 回答(設問の種類に合ったもの)，presented_at / answered_at: 提示時刻と回答時刻
+// playback: 刺激ごとの再生記録，comment: 評価についての自由記述(任意)
+// tokens: 回答した設問でget_audioから受け取った音声のトークン(時間切れの後に届いた回答を見分ける)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ScoreInput {
+    pub answer: Answer,
+    pub tokens: Vec<String>,
+    pub presented_at: DateTime<Local>,
+    pub answered_at: DateTime<Local>,
+    pub playback: Vec<PlaybackRecord>,
+    #[serde(default)]
+    pub comment: Option<String>,
+}
+
+impl ScoreInput {
+    // 空白のみのコメントは無いものとして扱う
+    pub fn get_comment(&self) -> Option<String> {
+        self.comment
+            .as_ref()
+            .filter(|c| c.trim().is_empty() == false)
+            .cloned()
+    }
+}
+
+/*回答の内容=========================================================================
+{"type": "rating", "value": 4} のようにtypeで種類を指定する
+rating: 評価値，choice: 選択肢(A, Bなど)，multi_rating: 複数の評価値，
+ranking: 選択肢の順位付け(良い順)，text: 自由記述
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Answer {
+    Rating { value: isize },
+    Choice { option: String },
+    MultiRating { values: Vec<isize> },
+    Ranking { order: Vec<String> },
+    Text { text: String },
+}
+
+impl Answer {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Answer::Rating { .. } => "rating",
+            Answer::Choice { .. } => "choice",
+            Answer::MultiRating { .. } => "multi_rating",
+            Answer::Ranking { .. } => "ranking",
+            Answer::Text { .. } => "text",
+        }
+    }
+
+    // 評価値の回答の値(他の種類の回答はエラー)---------------------------------------------
+    pub fn as_rating(&self) -> Result<isize> {
+        match self {
+            Answer::Rating { value } => Ok(*value),
+            _ => Err(anyhow!(ApplicationError::AnswerTypeMismatchError(
+                "rating".to_string(),
+                self.kind().to_string()
+            ))),
+        }
+    }
+
+    // 選択肢の回答の値(他の種類の回答はエラー)---------------------------------------------
+    pub fn as_choice(&self) -> Result<&str> {
+        match self {
+            Answer::Choice { option } => Ok(option),
+            _ => Err(anyhow!(ApplicationError::AnswerTypeMismatchError(
+                "choice".to_string(),
+                self.kind().to_string()
+            ))),
+        }
+    }
+}
+
+// 設問の種類(受け付ける回答の種類と範囲)---------------------------------------------
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ItemType {
+    Rating {
+        min: isize,
+        max: isize,
+    },
+    Choice {
+        options: Vec<String>,
+    },
+    MultiRating {
+        count: usize,
+        min: isize,
+        max: isize,
+    },
+    Ranking {
+        options: Vec<String>,
+    },
+    Text,
+}
+
+impl ItemType {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ItemType::Rating { .. } => "rating",
+            ItemType::Choice { .. } => "choice",
+            ItemType::MultiRating { .. } => "multi_rating",
+            ItemType::Ranking { .. } => "ranking",
+            ItemType::Text => "text",
+        }
+    }
+
+    // 回答が設問の種類に合っているか検証する-------------------------------------------
+    pub fn validate(&self, answer: &Answer) -> Result<()> {
+        match (self, answer) {
+            (ItemType::Rating { min, max }, Answer::Rating { value }) => {
+                check_range(*value, *min, *max)
+            }
+            (ItemType::Choice { options }, Answer::Choice { option }) => {
+                if options.contains(option) == false {
+                    return Err(anyhow!(ApplicationError::AnswerOutOfRangeError(format!(
+                        "{} is not one of {:?}",
+                        option, options
+                    ))));
+                }
+                Ok(())
+            }
+            (ItemType::MultiRating { count, min, max }, Answer::MultiRating { values }) => {
+                if values.len() != *count {
+                    return Err(anyhow!(ApplicationError::AnswerOutOfRangeError(format!(
+                        "expected {} ratings, got {}",
+                        count,
+                        values.len()
+                    ))));
+                }
+                for value in values {
+                    check_range(*value, *min, *max)?;
+                }
+                Ok(())
+            }
+            (ItemType::Ranking { options }, Answer::Ranking { order }) => {
+                let mut sorted_options = options.clone();
+                let mut sorted_order = order.clone();
+                sorted_options.sort();
+                sorted_order.sort();
+                if sorted_options != sorted_order {
+                    return Err(anyhow!(ApplicationError::AnswerOutOfRangeError(format!(
+                        "ranking must order each of {:?} exactly once",
+                        options
+                    ))));
+                }
+                Ok(())
+            }
+            (ItemType::Text, Answer::Text { .. }) => Ok(()),
+            _ => Err(anyhow!(ApplicationError::AnswerTypeMismatchError(
+                self.kind().to_string(),
+                answer.kind().to_string()
+            ))),
+        }
+    }
+}
+
+fn check_range(value: isize, min: isize, max: isize) -> Result<()> {
+    if value < min || value > max {
+        return Err(anyhow!(ApplicationError::AnswerOutOfRangeError(format!(
+            "{} is not between {} and {}",
+            value, min, max
+        ))));
+    }
+    Ok(())
+}
+
+// 設問の段階(warm_up: 練習として結果に含めない設問，scored: 評価に使う設問)--------------------
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemPhase {
+    WarmUp,
+    Scored,
+}
+
+/*トライアルの進み具合==================================================================
+index: 現在の設問の番号(0始まり)，total: 設問の総数
+phase / item_type: 現在の設問の段階と種類(全ての設問に回答済みならNone)
+remaining_in_block: 現在のブロック(同じ段階が続く設問のまとまり)の残りの設問数(現在の設問を含む)
+*/
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TrialProgress {
+    pub index: usize,
+    pub total: usize,
+    pub phase: Option<ItemPhase>,
+    pub item_type: Option<ItemType>,
+    pub remaining_in_block: usize,
+}
+
+impl TrialProgress {
+    // phasesは各設問の段階を出題順に並べたもの
+    pub fn new(phases: &[ItemPhase], current_idx: usize, item_type: ItemType) -> TrialProgress {
+        let phase = phases.get(current_idx).copied();
+        let remaining_in_block = match phase {
+            Some(phase) => phases[current_idx..]
+                .iter()
+                .take_while(|p| **p == phase)
+                .count(),
+            None => 0,
+        };
+        TrialProgress {
+            index: current_idx,
+            total: phases.len(),
+            phase: phase,
+            item_type: phase.map(|_| item_type),
+            remaining_in_block: remaining_in_block,
+        }
+    }
+}
+
+// 刺激ごとの再生記録（再生回数，最後まで再生された回数，最後まで再生されたかどうか）---------------
+// full_play_count / played_to_endはフロントエンドの値ではなく，バックエンドで数えた回数で記録する
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PlaybackRecord {
+    pub play_count: usize,
+    #[serde(default)]
+    pub full_play_count: usize,
+    pub played_to_end: bool,
+}
+
+// 設問ごとの結果（回答済み or 時間切れ）-----------------------------------------------
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum ItemOutcome {
+    Answered,
+    TimedOut,
+    Skipped, // 実験者が飛ばした
+}
+
+// 制限時間の判定の結果------------------------------------------------------------
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Expiry {
+    None,
+    Item,    // 現在の設問の制限時間を超えた
+    Session, // トライアル全体の制限時間を超えた
+}
+
+// トライアル中にバックエンド側で強制するルール========================================
+// min_full_listens: 回答前に各刺激を最後まで再生しなければならない回数
+// item_time_limit: 設問の提示(前の設問から進んだ時刻)からの制限時間(秒)．超えた場合は時間切れとして記録
+// session_time_limit: トライアル開始からの制限時間(秒)．超えた場合は残りの設問を出題せずに終了
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct TrialRule {
+    pub min_full_listens: usize,
+    pub item_time_limit: Option<usize>,
+    pub session_time_limit: Option<usize>,
+}
+impl TrialRule {
+    // セッションと現在の設問の制限時間を超えているかどうか(セッションの方を優先する)--------------
+    pub fn check_expiry(
+        &self,
+        started_at: DateTime<Local>,
+        presented_at: DateTime<Local>,
+        now: DateTime<Local>,
+    ) -> Expiry {
+        if self.is_session_timed_out(started_at, now) {
+            return Expiry::Session;
+        }
+        if self.is_item_timed_out(presented_at, now) {
+            return Expiry::Item;
+        }
+        Expiry::None
+    }
+
+    // セッション全体の制限時間を超えているかどうか----------------------------------------
+    pub fn is_session_timed_out(&self, started_at: DateTime<Local>, now: DateTime<Local>) -> bool {
+        match self.session_time_limit {
+            Some(limit) => (now - started_at).num_seconds() > limit as i64,
+            None => false,
+        }
+    }
+
+    // 各刺激が規定の回数だけ最後まで再生されていなければエラー-----------------------------
+    // full_listensはバックエンドで数えた刺激ごとの再生回数(AudioTokens::full_listens)
+    pub fn check_listens(&self, full_listens: &[usize]) -> Result<()> {
+        if self.min_full_listens == 0 {
+            return Ok(());
+        }
+        if full_listens.is_empty() || full_listens.iter().any(|n| *n < self.min_full_listens) {
+            return Err(anyhow!(ApplicationError::InsufficientListeningError(
+                self.min_full_listens
+            )));
+        }
+        Ok(())
+    }
+
+    // 設問の制限時間を超えているかどうか------------------------------------------------
+    pub fn is_item_timed_out(&self, presented_at: DateTime<Local>, now: DateTime<Local>) -> bool {
+        match self.item_time_limit {
+            Some(limit) => (now - presented_at).num_seconds() > limit as i64,
+            None => false,
+        }
+    }
+}
+
+// 各設問の回答時の振る舞いの記録====================================================
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ResponseRecord {
+    presented_at: DateTime<Local>,
+    answered_at: DateTime<Local>,
+    response_time_ms: i64,
+    playback: Vec<PlaybackRecord>,
+}
+impl ResponseRecord {
+    // 回答の情報から記録を生成．再生記録の数が刺激の数と異なる場合はエラー-----------------
+    // 最後まで再生された回数はバックエンドで数えた回数(full_listens)で置き換える
+    pub fn from_input(
+        input: &ScoreInput,
+        num_stimuli: usize,
+        full_listens: &[usize],
+    ) -> Result<ResponseRecord> {
+        if input.playback.len() != num_stimuli {
+            return Err(anyhow!(ApplicationError::InvalidResponseRecordError(
+                format!(
+                    "expected playback records for {} stimuli, got {}",
+                    num_stimuli,
+                    input.playback.len()
+                )
+            )));
+        }
+        if input.answered_at < input.presented_at {
+            return Err(anyhow!(ApplicationError::InvalidResponseRecordError(
+                "answered before presented".to_string()
+            )));
+        }
+        let response_time = input.answered_at - input.presented_at;
+        Ok(ResponseRecord {
+            presented_at: input.presented_at,
+            answered_at: input.answered_at,
+            response_time_ms: response_time.num_milliseconds(),
+            playback: input
+                .playback
+                .iter()
+                .enumerate()
+                .map(|(i, record)| {
+                    let full_play_count = full_listens.get(i).copied().unwrap_or(0);
+                    PlaybackRecord {
+                        play_count: record.play_count,
+                        full_play_count: full_play_count,
+                        played_to_end: full_play_count > 0,
+                    }
+                })
+                .collect(),
+        })
+    }
+}
+
+/*現在の設問の音声に割り当てた不透明なトークン=========================================
+フロントエンドにはファイルパスではなくトークンのみを渡し，カテゴリ名が分からないようにする
+最後まで再生された回数もトークンごとにバックエンドで数える
+(配信してから，または前回の再生終了から音声の長さ以上経っていない再生終了の報告は受け付けない)
+*/
+#[derive(Debug, Clone, Default)]
+pub struct AudioTokens {
+    tokens: Vec<IssuedAudio>,
+}
+
+#[derive(Debug, Clone)]
+struct IssuedAudio {
+    token: String,
+    path: PathBuf,
+    served_at: Option<DateTime<Local>>, // プロトコルで最初に配信した時刻
+    last_end_at: Option<DateTime<Local>>, // 最後に再生終了を受け付けた時刻
+    duration_sec: Option<f64>,          // 音声の長さ(最初の再生終了の報告で取得)
+    full_listens: usize,
+}
+
+impl AudioTokens {
+    // まだ発行していなければ音声ファイルごとにトークンを発行し，トークンのリストを返す-----------
+    pub fn issue(&mut self, paths: Vec<PathBuf>) -> Vec<String> {
+        if self.tokens.is_empty() {
+            self.tokens = paths
+                .into_iter()
+                .map(|path| IssuedAudio {
+                    token: AudioTokens::generate_token(),
+                    path: path,
+                    served_at: None,
+                    last_end_at: None,
+                    duration_sec: None,
+                    full_listens: 0,
+                })
+                .collect();
+        }
+        self.tokens.iter().map(|a| a.token.clone()).collect()
+    }
+
+    // トークンに対応する音声ファイルのパスを返し，配信した時刻を記録する--------------------------
+    pub fn resolve(&mut self, token: &str) -> Result<PathBuf> {
+        let audio = self.find_mut(token)?;
+        if audio.served_at.is_none() {
+            audio.served_at = Some(Local::now());
+        }
+        Ok(audio.path.clone())
+    }
+
+    // フロントエンドからの再生終了の報告を検証して，最後まで再生された回数を数える--------------------
+    pub fn report_end(&mut self, token: &str) -> Result<()> {
+        let audio = self.find_mut(token)?;
+        let since = match audio.last_end_at.or(audio.served_at) {
+            Some(since) => since,
+            None => {
+                return Err(anyhow!(ApplicationError::InvalidPlaybackReportError(
+                    format!("{} has not been served", token)
+                )))
+            }
+        };
+        let duration_sec = match audio.duration_sec {
+            Some(d) => d,
+            None => {
+                let d = probe_audio(&audio.path)?.duration_sec;
+                audio.duration_sec = Some(d);
+                d
+            }
+        };
+        let now = Local::now();
+        let elapsed_sec = (now - since).num_milliseconds() as f64 / 1000.0;
+        if elapsed_sec + PLAYBACK_END_TOLERANCE_SEC < duration_sec {
+            return Err(anyhow!(ApplicationError::InvalidPlaybackReportError(
+                format!(
+                    "{} ended {:.1} sec after playback could start, but it is {:.1} sec long",
+                    token, elapsed_sec, duration_sec
+                )
+            )));
+        }
+        audio.last_end_at = Some(now);
+        audio.full_listens += 1;
+        Ok(())
+    }
+
+    // 回答に付いてきたトークンが現在の設問に発行したものと同じかどうか----------------------------
+    pub fn matches(&self, tokens: &[String]) -> bool {
+        self.tokens.len() == tokens.len()
+            && self.tokens.iter().zip(tokens).all(|(a, t)| &a.token == t)
+    }
+
+    // 刺激ごとの最後まで再生された回数(発行した順)
+    pub fn full_listens(&self) -> Vec<usize> {
+        self.tokens.iter().map(|a| a.full_listens).collect()
+    }
+
+    fn find_mut(&mut self, token: &str) -> Result<&mut IssuedAudio> {
+        self.tokens
+            .iter_mut()
+            .find(|a| a.token == token)
+            .ok_or(anyhow!(ApplicationError::InvalidAudioTokenError(
+                token.to_string()
+            )))
+    }
+
+    // 次の設問に移るときに破棄する-----------------------------------------------------
+    pub fn clear(&mut self) {
+        self.tokens.clear();
+    }
+
+    // ランダムな文字列(拡張子も付けず，ファイルの形式も分からないようにする)
+    fn generate_token() -> String {
+        rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(32)
+            .map(char::from)
+            .collect()
+    }
+}
+
 #[allow(dead_code)]
 pub trait TestTrial {
-    fn get_audio(&mut self) -> Result<Vec<PathBuf>>;
-    fn set_score(&mut self, score: Vec<String>) -> Result<()>;
+    fn get_audio(&mut self) -> Result<Vec<String>>;
+    fn resolve_audio(&mut self, token: &str) -> Result<PathBuf>;
+    fn report_playback_end(&mut self, token: &str) -> Result<()>;
+    fn current_item_type(&self) -> ItemType;
+    fn get_examinee(&self) -> String; // 結果の保存に使う受験者のキー(匿名化したidとセッションの番号)
+    fn get_progress(&self) -> TrialProgress;
+    fn check_expiry(&mut self) -> Result<Option<TrialStatus>>;
+    fn set_score(&mut self, input: ScoreInput) -> Result<()>;
+    fn skip_item(&mut self) -> Result<TrialStatus>;
     fn to_next(&mut self) -> Result<TrialStatus>;
-    fn save_result(&self) -> Result<()>;
+    fn save_result(&self, session_index: usize) -> Result<SessionRecord>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn is_error(result: Result<()>, expected: fn(&ApplicationError) -> bool) -> bool {
+        match result {
+            Ok(_) => false,
+            Err(e) => e.downcast_ref::<ApplicationError>().map_or(false, expected),
+        }
+    }
+
+    fn seconds_ago(now: DateTime<Local>, seconds: i64) -> DateTime<Local> {
+        now - Duration::seconds(seconds)
+    }
+
+    #[test]
+    fn never_expires_without_limits() {
+        let now = Local::now();
+        let rule = TrialRule::default();
+        assert_eq!(
+            rule.check_expiry(seconds_ago(now, 86400), seconds_ago(now, 86400), now),
+            Expiry::None
+        );
+    }
+
+    #[test]
+    fn expires_item_after_its_limit() {
+        let now = Local::now();
+        let rule = TrialRule {
+            item_time_limit: Some(10),
+            ..TrialRule::default()
+        };
+        let started_at = seconds_ago(now, 100);
+        assert_eq!(
+            rule.check_expiry(started_at, seconds_ago(now, 10), now),
+            Expiry::None
+        );
+        assert_eq!(
+            rule.check_expiry(started_at, seconds_ago(now, 11), now),
+            Expiry::Item
+        );
+    }
+
+    // セッションの制限時間は設問の制限時間より優先する
+    #[test]
+    fn expires_session_before_item() {
+        let now = Local::now();
+        let rule = TrialRule {
+            min_full_listens: 0,
+            item_time_limit: Some(10),
+            session_time_limit: Some(60),
+        };
+        assert_eq!(
+            rule.check_expiry(seconds_ago(now, 60), seconds_ago(now, 5), now),
+            Expiry::None
+        );
+        assert_eq!(
+            rule.check_expiry(seconds_ago(now, 61), seconds_ago(now, 5), now),
+            Expiry::Session
+        );
+        assert_eq!(
+            rule.check_expiry(seconds_ago(now, 61), seconds_ago(now, 11), now),
+            Expiry::Session
+        );
+    }
+
+    #[test]
+    fn requires_full_listens() {
+        let insufficient =
+            |e: &ApplicationError| matches!(e, ApplicationError::InsufficientListeningError(2));
+        assert!(TrialRule::default().check_listens(&[]).is_ok());
+        let rule = TrialRule {
+            min_full_listens: 2,
+            ..TrialRule::default()
+        };
+        assert!(rule.check_listens(&[2, 3]).is_ok());
+        assert!(is_error(rule.check_listens(&[2, 1]), insufficient));
+        assert!(is_error(rule.check_listens(&[]), insufficient));
+    }
+
+    // 長さ(duration_sec)が分かっている音声のトークンを発行する(ファイルは読まない)
+    fn issue_tokens(duration_sec: f64) -> (AudioTokens, Vec<String>) {
+        let mut tokens = AudioTokens::default();
+        let issued = tokens.issue(vec![PathBuf::from("a.wav"), PathBuf::from("b.wav")]);
+        for audio in tokens.tokens.iter_mut() {
+            audio.duration_sec = Some(duration_sec);
+        }
+        (tokens, issued)
+    }
+
+    #[test]
+    fn issues_tokens_once_per_item() {
+        let (mut tokens, issued) = issue_tokens(3.0);
+        assert_eq!(issued.len(), 2);
+        assert_ne!(issued[0], issued[1]);
+        assert_eq!(tokens.issue(vec![PathBuf::from("c.wav")]), issued);
+        assert_eq!(tokens.resolve(&issued[1]).unwrap(), PathBuf::from("b.wav"));
+        assert!(tokens.matches(&issued));
+        assert!(tokens.matches(&[issued[1].clone(), issued[0].clone()]) == false);
+        assert!(tokens.matches(&issued[..1]) == false);
+
+        tokens.clear();
+        assert!(tokens.matches(&issued) == false);
+        assert!(tokens.resolve(&issued[0]).is_err());
+    }
+
+    #[test]
+    fn rejects_playback_end_before_serving() {
+        let (mut tokens, issued) = issue_tokens(3.0);
+        assert!(is_error(tokens.report_end(&issued[0]), |e| matches!(
+            e,
+            ApplicationError::InvalidPlaybackReportError(_)
+        )));
+        assert!(is_error(tokens.report_end("unknown"), |e| matches!(
+            e,
+            ApplicationError::InvalidAudioTokenError(_)
+        )));
+        assert_eq!(tokens.full_listens(), vec![0, 0]);
+    }
+
+    // 配信や前回の再生終了から音声の長さ以上経っていない報告は数えない
+    #[test]
+    fn counts_only_plausible_playback_ends() {
+        let (mut tokens, issued) = issue_tokens(3.0);
+        tokens.resolve(&issued[0]).unwrap();
+        assert!(tokens.report_end(&issued[0]).is_err());
+        assert_eq!(tokens.full_listens(), vec![0, 0]);
+
+        let now = Local::now();
+        tokens.tokens[0].served_at = Some(seconds_ago(now, 3));
+        tokens.report_end(&issued[0]).unwrap();
+        assert_eq!(tokens.full_listens(), vec![1, 0]);
+        assert!(tokens.report_end(&issued[0]).is_err());
+
+        tokens.tokens[0].last_end_at = Some(seconds_ago(now, 5));
+        tokens.report_end(&issued[0]).unwrap();
+        assert_eq!(tokens.full_listens(), vec![2, 0]);
+    }
 }
@@ -1,12 +1,18 @@
-use crate::constants::{CATEGORIES_DIRNAME, TRIAL_DIRNAME};
+use crate::constants::{CATEGORIES_DIRNAME, MOS_SCORE_POINTS, TRIAL_DIRNAME};
+use crate::error::{ApplicationError, WithPath};
+use crate::schema::{self, DocumentKind};
+use crate::storage;
 use crate::test_manager::Categories;
-use crate::test_trial::{TestTrial, TrialStatus};
+use crate::test_trial::{
+    AudioTokens, Expiry, ItemOutcome, ItemPhase, ItemType, ResponseRecord, ScoreInput,
+    SessionRecord, TestTrial, TrialProgress, TrialRule, TrialStatus,
+};
 
-use std::io::Write;
+use std::fs;
 use std::path::PathBuf;
-use std::{fs, fs::File};
 
 use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local};
 use log::{error, info};
 use rand::seq::SliceRandom;
 use serde::{Deserialize, Serialize};
@@ -23,6 +29,10 @@ pub struct MosScore {
     score_type: ScoreType,
     audio_file_path: PathBuf,
     score: Option<isize>,
+    response: Option<ResponseRecord>,
+    outcome: Option<ItemOutcome>,
+    #[serde(default)]
+    comment: Option<String>, // 評価についての自由記述
 }
 impl MosScore {
     pub fn new(category: String, score_type: ScoreType, audio_file_path: PathBuf) -> MosScore {
@@ -31,6 +41,16 @@ impl MosScore {
             score_type: score_type,
             audio_file_path: audio_file_path,
             score: None,
+            response: None,
+            outcome: None,
+            comment: None,
+        }
+    }
+    // ダミー(各カテゴリ1つずつ最初に出題する)は練習として扱う
+    pub fn get_phase(&self) -> ItemPhase {
+        match self.score_type {
+            ScoreType::Dummy => ItemPhase::WarmUp,
+            ScoreType::Valid => ItemPhase::Scored,
         }
     }
     pub fn get_audio_file_path(&self) -> PathBuf {
@@ -38,9 +58,20 @@ impl MosScore {
         info!("get audio file: {:?}", &path);
         return path;
     }
-    pub fn set_score(&mut self, score: isize) {
+    pub fn set_score(&mut self, score: isize, response: ResponseRecord, comment: Option<String>) {
         self.score = Some(score);
-        info!("set score: {:?}", score);
+        info!("set score: {:?}, response: {:?}", score, response);
+        self.response = Some(response);
+        self.outcome = Some(ItemOutcome::Answered);
+        self.comment = comment;
+    }
+    pub fn set_skipped(&mut self) {
+        info!("item skipped: {:?}", &self.audio_file_path);
+        self.outcome = Some(ItemOutcome::Skipped);
+    }
+    pub fn set_timed_out(&mut self) {
+        info!("item timed out: {:?}", &self.audio_file_path);
+        self.outcome = Some(ItemOutcome::TimedOut);
     }
 }
 
@@ -49,21 +80,94 @@ impl MosScore {
 pub struct MosTrial {
     trial_data_root: PathBuf,
     examinee: String,
+    rule: TrialRule,
+    started_at: DateTime<Local>,
+    presented_at: Option<DateTime<Local>>, // 現在の設問に進んだ時刻(設問の制限時間の起点)
+    #[serde(skip)]
+    tokens: AudioTokens,
     score_list: Vec<MosScore>,
     current_idx: usize,
 }
 
 impl TestTrial for MosTrial {
-    fn get_audio(&mut self) -> Result<Vec<PathBuf>> {
+    fn get_audio(&mut self) -> Result<Vec<String>> {
         let audio_path = self.score_list[self.current_idx].get_audio_file_path();
-        Ok(vec![audio_path])
+        Ok(self.tokens.issue(vec![audio_path]))
+    }
+    fn resolve_audio(&mut self, token: &str) -> Result<PathBuf> {
+        self.tokens.resolve(token)
+    }
+    fn report_playback_end(&mut self, token: &str) -> Result<()> {
+        self.tokens.report_end(token)
+    }
+    // 1からMOS_SCORE_POINTSまでの評価値
+    fn get_examinee(&self) -> String {
+        self.examinee.clone()
     }
-    fn set_score(&mut self, score: Vec<String>) -> Result<()> {
-        let _score = score[0].parse::<isize>()?;
-        self.score_list[self.current_idx].set_score(_score);
+    fn current_item_type(&self) -> ItemType {
+        ItemType::Rating {
+            min: 1,
+            max: MOS_SCORE_POINTS as isize,
+        }
+    }
+    fn get_progress(&self) -> TrialProgress {
+        let phases: Vec<ItemPhase> = self.score_list.iter().map(|s| s.get_phase()).collect();
+        TrialProgress::new(&phases, self.current_idx, self.current_item_type())
+    }
+    /*制限時間を超えていれば現在の設問を時間切れとして記録する---------------------------------
+    設問の制限時間を超えた場合は次の設問に進み，セッションの制限時間を超えた場合は残りを出題せずに終える
+    記録した場合は進んだ後の状態を返す
+    */
+    fn check_expiry(&mut self) -> Result<Option<TrialStatus>> {
+        if self.current_idx >= self.score_list.len() {
+            return Ok(None);
+        }
+        // 設問に進んだ時刻を記録する前に保存したトライアルは開始時刻を起点にする
+        let presented_at = self.presented_at.unwrap_or(self.started_at);
+        match self
+            .rule
+            .check_expiry(self.started_at, presented_at, Local::now())
+        {
+            Expiry::None => Ok(None),
+            Expiry::Item => {
+                self.score_list[self.current_idx].set_timed_out();
+                Ok(Some(self.to_next()?))
+            }
+            Expiry::Session => {
+                self.score_list[self.current_idx].set_timed_out();
+                info!("session timed out");
+                self.presented_at = None;
+                self.tokens.clear();
+                self.current_idx = self.score_list.len();
+                Ok(Some(TrialStatus::Done))
+            }
+        }
+    }
+    fn set_score(&mut self, input: ScoreInput) -> Result<()> {
+        // 時間切れで次の設問に進んだ後に届いた，前の設問への回答は受け付けない
+        if self.tokens.matches(&input.tokens) == false {
+            return Err(anyhow!(ApplicationError::ItemTimedOutError));
+        }
+        let full_listens = self.tokens.full_listens();
+        self.rule.check_listens(&full_listens)?;
+
+        self.current_item_type().validate(&input.answer)?;
+        let score = input.answer.as_rating()?;
+        let response = ResponseRecord::from_input(&input, 1, &full_listens)?; // MOSの刺激は1つ
+        self.score_list[self.current_idx].set_score(score, response, input.get_comment());
         Ok(())
     }
+    // 回答せずに次の設問に進む
+    fn skip_item(&mut self) -> Result<TrialStatus> {
+        match self.score_list.get_mut(self.current_idx) {
+            Some(score) => score.set_skipped(),
+            None => return Err(anyhow!("Test had been ended")),
+        }
+        self.to_next()
+    }
     fn to_next(&mut self) -> Result<TrialStatus> {
+        self.presented_at = Some(Local::now());
+        self.tokens.clear();
         if self.score_list.len() > (self.current_idx + 1) {
             self.current_idx += 1;
             info!("go to next question");
@@ -77,13 +181,16 @@ impl TestTrial for MosTrial {
             return Err(anyhow!("Test had been ended"));
         }
     }
-    fn save_result(&self) -> Result<()> {
-        let json_string = serde_json::to_string_pretty(&self.score_list)?;
+    // 結果と一緒に何回目の受験か(session)を保存する
+    fn save_result(&self, session_index: usize) -> Result<SessionRecord> {
+        let session = SessionRecord::new(session_index, self.started_at);
+        let mut document = schema::to_document(DocumentKind::MosResult, &self.score_list)?;
         let path = self.trial_data_root.join(format!("{}.json", self.examinee));
-        let mut file = File::create(&path)?;
-        file.write_all(json_string.as_bytes())?;
+        document["session"] = serde_json::to_value(&session).with_path(&path)?;
+        let json_string = serde_json::to_string_pretty(&document).with_path(&path)?;
+        storage::write_atomic(&path, json_string.as_bytes())?;
         info!("save result: {:?}", &path);
-        Ok(())
+        Ok(session)
     }
 }
 
@@ -93,6 +200,7 @@ impl MosTrial {
         examinee: String,
         categories: Categories,
         num_repeat: usize,
+        rule: TrialRule,
     ) -> Result<MosTrial> {
         let trial_data_root = MosTrial::get_trial_data_root(manager_data_root.clone())?;
         let score_list = MosTrial::generate_score_list(manager_data_root, categories, num_repeat)?;
@@ -101,6 +209,10 @@ impl MosTrial {
         Ok(MosTrial {
             trial_data_root: trial_data_root,
             examinee: examinee,
+            rule: rule,
+            started_at: Local::now(),
+            presented_at: Some(Local::now()),
+            tokens: AudioTokens::default(),
             score_list: score_list,
             current_idx: 0,
         })
@@ -149,8 +261,41 @@ impl MosTrial {
         let trial_data_root = manager_data_root.join(TRIAL_DIRNAME);
         if trial_data_root.exists() == false {
             info!("create trial result directory: {:?}", &trial_data_root);
-            fs::create_dir_all(&trial_data_root)?;
+            fs::create_dir_all(&trial_data_root).with_path(&trial_data_root)?;
         }
         return Ok(trial_data_root);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::fixture_path;
+
+    // 配列のままの以前の評価結果を読み込むと，新しい項目は空のまま各行が復元される
+    #[test]
+    fn loads_legacy_result() {
+        let scores: Vec<MosScore> = schema::read_document(
+            &fixture_path("legacy_mos_result.json"),
+            DocumentKind::MosResult,
+        )
+        .unwrap();
+        assert_eq!(scores.len(), 3);
+        assert_eq!(scores[0].category, "original");
+        assert!(matches!(scores[0].score_type, ScoreType::Valid));
+        assert_eq!(
+            scores[0].audio_file_path,
+            PathBuf::from("categories/original/sample_01.wav")
+        );
+        assert_eq!(scores[0].score, Some(4));
+        assert!(matches!(scores[1].score_type, ScoreType::Dummy));
+        assert_eq!(scores[1].score, Some(2));
+        assert_eq!(scores[2].category, "synthesized");
+        assert_eq!(scores[2].score, None);
+        for score in scores.iter() {
+            assert!(score.response.is_none());
+            assert!(score.outcome.is_none());
+            assert_eq!(score.comment, None);
+        }
+    }
+}
@@ -1,13 +1,18 @@
 use crate::constants::{CATEGORIES_DIRNAME, TRIAL_DIRNAME};
-use crate::error::ApplicationError;
+use crate::error::{ApplicationError, WithPath};
+use crate::schema::{self, DocumentKind};
+use crate::storage;
 use crate::test_manager::Categories;
-use crate::test_trial::{TestTrial, TrialStatus};
+use crate::test_trial::{
+    AudioTokens, Expiry, ItemOutcome, ItemPhase, ItemType, ResponseRecord, ScoreInput,
+    SessionRecord, TestTrial, TrialProgress, TrialRule, TrialStatus,
+};
 
-use std::io::Write;
+use std::fs;
 use std::path::PathBuf;
-use std::{fs, fs::File};
 
 use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local};
 use itertools::Itertools;
 use log::{error, info};
 use rand::seq::SliceRandom;
@@ -26,6 +31,10 @@ pub struct ThurstoneScore {
     category_b: String,
     audio_file_path_b: PathBuf,
     prefer_to: Option<String>,
+    response: Option<ResponseRecord>,
+    outcome: Option<ItemOutcome>,
+    #[serde(default)]
+    comment: Option<String>, // 評価についての自由記述
 }
 impl ThurstoneScore {
     pub fn new(
@@ -40,6 +49,9 @@ impl ThurstoneScore {
             category_b: category_b,
             audio_file_path_b: audio_file_path_b,
             prefer_to: None,
+            response: None,
+            outcome: None,
+            comment: None,
         }
     }
     pub fn get_audio_file_path(&self) -> Vec<PathBuf> {
@@ -50,13 +62,38 @@ impl ThurstoneScore {
         info!("get audio files: {:?}", paths);
         paths
     }
-    pub fn set_score(&mut self, ab_index: ABIndex) {
+    pub fn set_score(
+        &mut self,
+        ab_index: ABIndex,
+        response: ResponseRecord,
+        comment: Option<String>,
+    ) {
         let score = match ab_index {
             ABIndex::A => self.category_a.clone(),
             ABIndex::B => self.category_b.clone(),
         };
-        info!("set score: {:?} (prefer to {:?})", ab_index, score);
+        info!(
+            "set score: {:?} (prefer to {:?}), response: {:?}",
+            ab_index, score, response
+        );
         self.prefer_to = Some(score);
+        self.response = Some(response);
+        self.outcome = Some(ItemOutcome::Answered);
+        self.comment = comment;
+    }
+    pub fn set_skipped(&mut self) {
+        info!(
+            "item skipped: {:?} vs {:?}",
+            &self.audio_file_path_a, &self.audio_file_path_b
+        );
+        self.outcome = Some(ItemOutcome::Skipped);
+    }
+    pub fn set_timed_out(&mut self) {
+        info!(
+            "item timed out: {:?} vs {:?}",
+            &self.audio_file_path_a, &self.audio_file_path_b
+        );
+        self.outcome = Some(ItemOutcome::TimedOut);
     }
 }
 
@@ -65,32 +102,103 @@ impl ThurstoneScore {
 pub struct ThurstoneTrial {
     trial_data_root: PathBuf,
     examinee: String,
+    rule: TrialRule,
+    started_at: DateTime<Local>,
+    presented_at: Option<DateTime<Local>>, // 現在の設問に進んだ時刻(設問の制限時間の起点)
+    #[serde(skip)]
+    tokens: AudioTokens,
     score_list: Vec<ThurstoneScore>,
     current_idx: usize,
 }
 
 impl TestTrial for ThurstoneTrial {
-    fn get_audio(&mut self) -> Result<Vec<PathBuf>> {
+    fn get_audio(&mut self) -> Result<Vec<String>> {
         let audio_paths = self.score_list[self.current_idx].get_audio_file_path();
-        Ok(audio_paths)
+        Ok(self.tokens.issue(audio_paths))
     }
-    fn set_score(&mut self, score: Vec<String>) -> Result<()> {
-        let ab_score: ABIndex;
-        match score[0].as_str() {
-            "A" => {
-                ab_score = ABIndex::A;
-            }
-            "B" => {
-                ab_score = ABIndex::B;
+    fn resolve_audio(&mut self, token: &str) -> Result<PathBuf> {
+        self.tokens.resolve(token)
+    }
+    fn report_playback_end(&mut self, token: &str) -> Result<()> {
+        self.tokens.report_end(token)
+    }
+    // AとBのどちらかを選ぶ
+    fn get_examinee(&self) -> String {
+        self.examinee.clone()
+    }
+    fn current_item_type(&self) -> ItemType {
+        ItemType::Choice {
+            options: vec!["A".to_string(), "B".to_string()],
+        }
+    }
+    // 練習の設問はないので全て評価に使う
+    fn get_progress(&self) -> TrialProgress {
+        let phases = vec![ItemPhase::Scored; self.score_list.len()];
+        TrialProgress::new(&phases, self.current_idx, self.current_item_type())
+    }
+    /*制限時間を超えていれば現在の設問を時間切れとして記録する---------------------------------
+    設問の制限時間を超えた場合は次の設問に進み，セッションの制限時間を超えた場合は残りを出題せずに終える
+    記録した場合は進んだ後の状態を返す
+    */
+    fn check_expiry(&mut self) -> Result<Option<TrialStatus>> {
+        if self.current_idx >= self.score_list.len() {
+            return Ok(None);
+        }
+        // 設問に進んだ時刻を記録する前に保存したトライアルは開始時刻を起点にする
+        let presented_at = self.presented_at.unwrap_or(self.started_at);
+        match self
+            .rule
+            .check_expiry(self.started_at, presented_at, Local::now())
+        {
+            Expiry::None => Ok(None),
+            Expiry::Item => {
+                self.score_list[self.current_idx].set_timed_out();
+                Ok(Some(self.to_next()?))
             }
-            _ => {
-                return Err(anyhow!(ApplicationError::InvalidScoreInputTypeError));
+            Expiry::Session => {
+                self.score_list[self.current_idx].set_timed_out();
+                info!("session timed out");
+                self.presented_at = None;
+                self.tokens.clear();
+                self.current_idx = self.score_list.len();
+                Ok(Some(TrialStatus::Done))
             }
         }
-        self.score_list[self.current_idx].set_score(ab_score);
+    }
+    fn set_score(&mut self, input: ScoreInput) -> Result<()> {
+        // 時間切れで次の設問に進んだ後に届いた，前の設問への回答は受け付けない
+        if self.tokens.matches(&input.tokens) == false {
+            return Err(anyhow!(ApplicationError::ItemTimedOutError));
+        }
+        let full_listens = self.tokens.full_listens();
+        self.rule.check_listens(&full_listens)?;
+
+        self.current_item_type().validate(&input.answer)?;
+        let ab_score = match input.answer.as_choice()? {
+            "A" => ABIndex::A,
+            "B" => ABIndex::B,
+            option => {
+                return Err(anyhow!(ApplicationError::AnswerOutOfRangeError(format!(
+                    "{} is not one of A, B",
+                    option
+                ))))
+            }
+        };
+        let response = ResponseRecord::from_input(&input, 2, &full_listens)?; // AとBの2つの刺激
+        self.score_list[self.current_idx].set_score(ab_score, response, input.get_comment());
         Ok(())
     }
+    // 回答せずに次の設問に進む
+    fn skip_item(&mut self) -> Result<TrialStatus> {
+        match self.score_list.get_mut(self.current_idx) {
+            Some(score) => score.set_skipped(),
+            None => return Err(anyhow!("Test had been ended")),
+        }
+        self.to_next()
+    }
     fn to_next(&mut self) -> Result<TrialStatus> {
+        self.presented_at = Some(Local::now());
+        self.tokens.clear();
         let status = if self.score_list.len() > (self.current_idx + 1) {
             self.current_idx += 1;
             info!("go to next question");
@@ -106,13 +214,16 @@ impl TestTrial for ThurstoneTrial {
 
         status
     }
-    fn save_result(&self) -> Result<()> {
-        let json_string = serde_json::to_string_pretty(&self.score_list)?;
+    // 結果と一緒に何回目の受験か(session)を保存する
+    fn save_result(&self, session_index: usize) -> Result<SessionRecord> {
+        let session = SessionRecord::new(session_index, self.started_at);
+        let mut document = schema::to_document(DocumentKind::ThurstoneResult, &self.score_list)?;
         let path = self.trial_data_root.join(format!("{}.json", self.examinee));
-        let mut file = File::create(&path)?;
-        file.write_all(json_string.as_bytes())?;
+        document["session"] = serde_json::to_value(&session).with_path(&path)?;
+        let json_string = serde_json::to_string_pretty(&document).with_path(&path)?;
+        storage::write_atomic(&path, json_string.as_bytes())?;
         info!("save result: {:?}", &path);
-        Ok(())
+        Ok(session)
     }
 }
 
@@ -121,6 +232,7 @@ impl ThurstoneTrial {
         manager_data_root: PathBuf,
         examinee: String,
         categories: Categories,
+        rule: TrialRule,
     ) -> Result<ThurstoneTrial> {
         let trial_data_root = ThurstoneTrial::get_trial_data_root(manager_data_root.clone())?;
         let score_list = ThurstoneTrial::generate_score_list(manager_data_root, categories)?;
@@ -129,6 +241,10 @@ impl ThurstoneTrial {
         Ok(ThurstoneTrial {
             trial_data_root: trial_data_root,
             examinee: examinee,
+            rule: rule,
+            started_at: Local::now(),
+            presented_at: Some(Local::now()),
+            tokens: AudioTokens::default(),
             score_list: score_list,
             current_idx: 0,
         })
@@ -173,8 +289,39 @@ impl ThurstoneTrial {
         let trial_data_root = manager_data_root.join(TRIAL_DIRNAME);
         if trial_data_root.exists() == false {
             info!("create trial result directory: {:?}", &trial_data_root);
-            fs::create_dir_all(&trial_data_root)?;
+            fs::create_dir_all(&trial_data_root).with_path(&trial_data_root)?;
         }
         return Ok(trial_data_root);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::fixture_path;
+
+    // 配列のままの以前の評価結果を読み込むと，新しい項目は空のまま各行が復元される
+    #[test]
+    fn loads_legacy_result() {
+        let scores: Vec<ThurstoneScore> = schema::read_document(
+            &fixture_path("legacy_thurstone_result.json"),
+            DocumentKind::ThurstoneResult,
+        )
+        .unwrap();
+        assert_eq!(scores.len(), 2);
+        assert_eq!(scores[0].category_a, "original");
+        assert_eq!(scores[0].category_b, "synthesized");
+        assert_eq!(
+            scores[0].audio_file_path_b,
+            PathBuf::from("categories/synthesized/sample_01.wav")
+        );
+        assert_eq!(scores[0].prefer_to, Some("original".to_string()));
+        assert_eq!(scores[1].category_a, "synthesized");
+        assert_eq!(scores[1].prefer_to, None);
+        for score in scores.iter() {
+            assert!(score.response.is_none());
+            assert!(score.outcome.is_none());
+            assert_eq!(score.comment, None);
+        }
+    }
+}
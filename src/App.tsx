@@ -3,8 +3,14 @@ import "./App.css";
 import {Home} from "./component/home.tsx";
 import {AppProvider} from "./component/context.tsx";
 import {TrialFromURL} from "./component/trial.tsx"
+import {Kiosk} from "./component/kiosk.tsx";
+import { getCurrent } from "@tauri-apps/api/window";
 
 function App() {
+  // 受験者用のキオスクでは実施中のトライアルの回答欄だけを表示する
+  if (getCurrent().label === "kiosk") {
+    return (<Kiosk/>);
+  }
   return (
     <div>
       <AppProvider>
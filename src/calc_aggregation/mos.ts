@@ -1,13 +1,23 @@
-import {fs} from '@tauri-apps/api';
 import * as math from 'mathjs';
+import { TrialResult } from '../tauri_commands.ts';
 
 
 // JSONのテスト結果ファイルのインターフェース==========================
+// score: 回答しなかった設問(時間切れ・スキップ)はnull
+// outcome: 設問の結果(記録する前の古い結果ファイルにはない)
 interface MosScore {
     category: string;
     score_type: "Valid" | "Dummy";
     audio_file_path: string;
-    score: number,
+    score: number | null,
+    outcome?: "Answered" | "TimedOut" | "Skipped" | null,
+}
+
+// 集計に使う回答(ダミーと，時間切れ・スキップした設問は除く)
+const isAnsweredMos = (d: MosScore): d is MosScore & {score: number} => {
+    if (d.score_type !== "Valid" || d.score === null) return false;
+    // outcomeのない古い結果ファイルは評価値があれば回答したものとする
+    return d.outcome === "Answered" || d.outcome === undefined || d.outcome === null;
 }
 
 
@@ -17,7 +27,7 @@ interface MosScore {
 const analyzeDataMos =(parsedData: MosScore[]): Record<string, number[]> => {
 
     let resultData: Record<string, number[]> = {};
-    for(let d of parsedData as MosScore[]){
+    for(let d of parsedData.filter(isAnsweredMos)){
         if(! resultData[d.category]){
             resultData[d.category] = [d.score];
         }
@@ -30,7 +40,7 @@ const analyzeDataMos =(parsedData: MosScore[]): Record<string, number[]> => {
 
 //==============================================================================
 // 回答結果から得点表を作成
-export const getResultTableMos = async (categories: string[], fileEntries: fs.FileEntry[]) => {
+export const getResultTableMos = async (categories: string[], results: TrialResult[]) => {
 
     let resultTable: Record<string, number[]> = {};
 
@@ -40,9 +50,9 @@ export const getResultTableMos = async (categories: string[], fileEntries: fs.Fi
     })
 
     // result analysis---------------------------
-    for(let entry of fileEntries){
-        const jsonString = await fs.readTextFile(entry.path);
-        const parsedData = JSON.parse(jsonString) as MosScore[];
+    // 古い形式の結果ファイルはバックエンドで現在の形式にしてある
+    for(let result of results){
+        const parsedData = result.scores as MosScore[];
         const analyzedData = analyzeDataMos(parsedData);
         for(let [category, scores] of Object.entries(analyzedData)){
             resultTable[category] = [...resultTable[category], ...scores]
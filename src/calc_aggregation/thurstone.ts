@@ -1,13 +1,23 @@
-import { fs } from "@tauri-apps/api"
 import * as math from 'mathjs';
 import { normal } from "jstat";
+import { TrialResult } from "../tauri_commands.ts";
 
+// prefer_to: 回答しなかった設問(時間切れ・スキップ)はnull
+// outcome: 設問の結果(記録する前の古い結果ファイルにはない)
 interface ThurstoneScore {
     category_a: string;
     audio_file_path_a: string;
     category_b: string;
     audio_file_path_b: string;
-    prefer_to: string;
+    prefer_to: string | null;
+    outcome?: "Answered" | "TimedOut" | "Skipped" | null;
+}
+
+// 集計に使う回答(時間切れ・スキップした設問は除く)
+const isAnsweredThurstone = (d: ThurstoneScore): d is ThurstoneScore & {prefer_to: string} => {
+    if (d.prefer_to === null) return false;
+    // outcomeのない古い結果ファイルは選択があれば回答したものとする
+    return d.outcome === "Answered" || d.outcome === undefined || d.outcome === null;
 }
 
 // サーストン法の集計===============================================================
@@ -16,7 +26,7 @@ interface ThurstoneScore {
 const analyzeDataThurstone = (parsedData: ThurstoneScore[]): Record<string, Record<string, number>> => {
     
     let resultData: Record<string, Record<string, number>> = {}
-    for(let d of parsedData){
+    for(let d of parsedData.filter(isAnsweredThurstone)){
         const win = d.prefer_to
         const loose = d.prefer_to == d.category_a ? d.category_b : d.category_a
 
@@ -35,7 +45,7 @@ const analyzeDataThurstone = (parsedData: ThurstoneScore[]): Record<string, Reco
 
 //====================================================================
 // 回答結果の得点表を参加者ごとに集計してまとめる
-export const getResultTableThurstone= async(categories: string[], fileEntries: fs.FileEntry[]) => {
+export const getResultTableThurstone= async(categories: string[], results: TrialResult[]) => {
 
     let resultTable: Record<string, Record<string, number>> = {};
 
@@ -50,9 +60,9 @@ export const getResultTableThurstone= async(categories: string[], fileEntries: f
     })
 
     //result analysis-------------------------------------
-    for(let entry of fileEntries){
-        const jsonString = await fs.readTextFile(entry.path);
-        const parsedData = JSON.parse(jsonString) as ThurstoneScore[];
+    // 古い形式の結果ファイルはバックエンドで現在の形式にしてある
+    for(let result of results){
+        const parsedData = result.scores as ThurstoneScore[];
 
         const analyzedData = analyzeDataThurstone(parsedData);
         for(let [win, values] of Object.entries(analyzedData)){
@@ -0,0 +1,119 @@
+import { useState, useEffect } from 'react';
+import { confirm } from '@tauri-apps/api/dialog';
+import { Dialog, DialogPanel } from '@headlessui/react'
+
+import "../App.css";
+import { AdminStatus, tauriGetAdminStatus, tauriUnlockAdmin, tauriLockAdmin, tauriSetAdminPin,
+         errorMessage } from '../tauri_commands.ts';
+import { TextButton, RemoveButton } from "./button.tsx";
+
+
+/*==============================================================
+管理者のPINのボタン
+PINが設定されている場合はロックを解除している間だけテストの追加・編集・削除や結果の集計ができる
+*/
+export const AdminLockButton=()=>{
+	const [isOpen, setIsOpen] = useState<boolean>(false);
+	const [status, setStatus] = useState<AdminStatus|undefined>(undefined);
+
+	const updateStatus = () => {
+		tauriGetAdminStatus().then((_status) => setStatus(_status)).catch((err) => console.error(err));
+	};
+
+	useEffect(() => {
+		updateStatus();
+	}, [isOpen]);
+
+	const label = status === undefined || status.enabled == false ? "管理者PIN" : status.unlocked ? "ロック解除中" : "ロック中";
+
+	// jsx------------------------------------------------------------
+	return (
+		<div>
+			<TextButton text={label} type='button' onClick={()=>setIsOpen(true)} className="py-2 px-2 font-bold"/>
+			<Dialog open={isOpen} as='div' onClose={()=>setIsOpen(false)}>
+				<div className='fixed inset-0 z-40 bg-[rgb(0_0_0/0.6)] flex justify-center items-center'>
+					<div className="w-1/2 p-8 bg-white rounded-lg">
+						<DialogPanel>
+							{status === undefined ? (null) : (
+								<AdminLockPanel status={status} onUpdate={updateStatus}/>
+							)}
+						</DialogPanel>
+					</div>
+				</div>
+			</Dialog>
+		</div>
+	);
+};
+
+
+interface AdminLockPanelProps {
+	status: AdminStatus;
+	onUpdate: () => void;
+}
+const AdminLockPanel=({status, onUpdate}: AdminLockPanelProps)=>{
+	const [pin, setPin] = useState<string>("");
+	const [newPin, setNewPin] = useState<string>("");
+
+	const unlock = () => {
+		tauriUnlockAdmin(pin).then(() => {
+			setPin("");
+			onUpdate();
+		}).catch((err) => alert(errorMessage(err)));
+	};
+
+	const lock = () => {
+		tauriLockAdmin().then(() => onUpdate()).catch((err) => alert(errorMessage(err)));
+	};
+
+	const setAdminPin = () => {
+		tauriSetAdminPin(newPin).then(() => {
+			setNewPin("");
+			onUpdate();
+		}).catch((err) => alert(errorMessage(err)));
+	};
+
+	const removeAdminPin = async () => {
+		const isOk = await confirm("管理者のPINを削除しますか？誰でもテストの編集や削除ができるようになります。");
+		if (isOk == false) return;
+		tauriSetAdminPin(null).then(() => onUpdate()).catch((err) => alert(errorMessage(err)));
+	};
+
+	// ロック中はPINの入力だけを受け付ける---------------------------------------
+	if (status.unlocked == false) {
+		return (
+			<div className="flex flex-col space-y-4">
+				<p className="text-xl font-bold pb-1 border-b-2">ロックの解除</p>
+				<p className="text-sm text-gray-500">テストの作成・編集・削除や結果の集計には管理者のPINが必要です</p>
+				<div className="flex flex-row space-x-2">
+					<input type="password" value={pin} onChange={(e) => setPin(e.target.value)} className="border rounded px-2"/>
+					<TextButton text="解除" type="button" className="py-1 px-4" onClick={unlock}/>
+				</div>
+			</div>
+		);
+	}
+
+	// jsx------------------------------------------------------------
+	return (
+		<div className="flex flex-col space-y-4">
+			<p className="text-xl font-bold pb-1 border-b-2">管理者PIN</p>
+			{status.enabled ? (
+				<div className="flex flex-row items-center space-x-4">
+					<p className="text-sm text-gray-500">
+						{status.unlocked_until === null ? "" : `${new Date(status.unlocked_until).toLocaleTimeString()}まで解除中`}
+					</p>
+					<TextButton text="今すぐロック" type="button" className="py-1 px-4" onClick={lock}/>
+				</div>
+			) : (
+				<p className="text-sm text-gray-500">PINが設定されていません。PINを設定すると管理操作にPINが必要になります</p>
+			)}
+			<div className="flex flex-row space-x-2">
+				<input type="password" value={newPin} placeholder={status.enabled ? "新しいPIN" : "PIN"}
+				       onChange={(e) => setNewPin(e.target.value)} className="border rounded px-2"/>
+				<TextButton text={status.enabled ? "変更" : "設定"} type="button" className="py-1 px-4" onClick={setAdminPin}/>
+				{status.enabled ? (
+					<RemoveButton text="PINを削除" type="button" className="py-1 px-4" onClick={removeAdminPin}/>
+				) : (null)}
+			</div>
+		</div>
+	);
+};
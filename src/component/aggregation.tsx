@@ -1,12 +1,11 @@
 import { useContext, ReactNode, useState, useEffect, FC } from "react";
-import {path, fs} from '@tauri-apps/api';
 import { ResponsiveContainer, XAxis, YAxis,CartesianGrid, Cell, LabelList } from "recharts";
 import { BarChart, Bar, ScatterChart, Scatter } from "recharts";
 
 import "../App.css";
 import { ListElement } from "./list.tsx";
 import { SettingContext  } from "./context.tsx";
-import { tauriTestType } from "../tauri_commands.ts"
+import { tauriTestType, TrialResult, tauriGetResults, errorMessage } from "../tauri_commands.ts"
 import { MosResultProperty, getResultTableMos, getResultMos } from "../calc_aggregation/mos.ts";
 import { getResultTableThurstone, getResultThurstone } from "../calc_aggregation/thurstone.ts";
 
@@ -151,30 +150,30 @@ const ThurstoneResultGraph: FC<ThurstoneResultGraphProps> = ({result}) => {
 
 interface ResultComponentProps{
     testType: tauriTestType;
-    fileEntries : fs.FileEntry[];
+    results : TrialResult[];
     categories: string[];
 }
-const ResultCompornent: FC<ResultComponentProps> = ({testType, fileEntries, categories}) => {
+const ResultCompornent: FC<ResultComponentProps> = ({testType, results, categories}) => {
 
     const [graph, setGraph] = useState<ReactNode>(null);
     const [table, setTable] = useState<ReactNode>(null);
 
     useEffect(() => {
-        if (fileEntries.length == 0) {
+        if (results.length == 0) {
             return
         }
 
         const fetchResult=async() => {
             switch(testType) {
                 case "Mos": {
-                    const resultTable = await getResultTableMos(categories, fileEntries);
+                    const resultTable = await getResultTableMos(categories, results);
                     const result = await getResultMos(resultTable);
                     setTable(<MosResultTable result={result}/>)
                     setGraph(<MosResultGraph result={result}/>)
                     break;
                 }
                 case "Thurstone": {
-                    const resultTable = await getResultTableThurstone(categories, fileEntries);
+                    const resultTable = await getResultTableThurstone(categories, results);
                     const result = getResultThurstone(resultTable);
                     setTable(<ThurstoneResultTable resultTable={resultTable} result={result}/>)
                     setGraph(<ThurstoneResultGraph result={result}/>)
@@ -184,7 +183,7 @@ const ResultCompornent: FC<ResultComponentProps> = ({testType, fileEntries, cate
         };
 
         fetchResult();
-    }, [fileEntries]);
+    }, [results]);
 
     return (
         <div className="flex flex-col space-y-8 justify-center">
@@ -200,36 +199,32 @@ export const Aggregation = ()=> {
     if (settingContext === undefined) return null;
     const info = settingContext.info;
 
-    const [fileEntries, setFileEntries] = useState<fs.FileEntry[]>([]);
-    const [examinees, setExaminees] = useState<string[]>([]);
+    const [results, setResults] = useState<TrialResult[]>([]);
+    const [lockedMessage, setLockedMessage] = useState<string|undefined>(undefined);
     const categories = info.categories.names;
+    const examinees = results.map(result => result.key);
 
     useEffect(()=> {
-        const fetchFileEntries= async() => {
-            try{
-                const _trialDataPath= await path.join(info.manager_data_root, "trials");
-                const _fileEntries = await fs.readDir(_trialDataPath);
-                const _fileNamesOptional = _fileEntries.map(fileEntry => (fileEntry.name));
-                const _fileNames = _fileNamesOptional.filter((fileName):fileName is string => fileName !== undefined);
-                const _examinees = _fileNames.map(fileName => (fileName.split('.')[0]));
-
-                setExaminees(_examinees);
-                setFileEntries(_fileEntries)
-            }
-            catch (e){
-                console.error(e);
-            }
-        }
-
-        fetchFileEntries();
-        
+        // 管理者のPINでロックされている場合はバックエンドがエラーを返す
+        tauriGetResults(info.name)
+            .then((_results) => setResults(_results))
+            .catch((e) => setLockedMessage(errorMessage(e)));
     }, [])
 
+    if (lockedMessage !== undefined) {
+        return (
+            <div>
+                <p className="text-xl font-bold mb-2 pb-1 border-b-2">集計結果</p>
+                <p className="text-red-500">{lockedMessage}</p>
+            </div>
+        )
+    }
+
     return (
         <div>
             <p className="text-xl font-bold mb-2 pb-1 border-b-2">集計結果</p>
             <p className="mb-4">回答者: {examinees.length}名 ({examinees.join(", ")})</p>
-            <ResultCompornent testType={info.test_type} fileEntries={fileEntries} categories={categories} />
+            <ResultCompornent testType={info.test_type} results={results} categories={categories} />
         </div>
     )
 }
\ No newline at end of file
@@ -1,6 +1,6 @@
 import { useContext, useState, useEffect, ReactNode, FC } from 'react';
 
-import { tauriCloseTest, tauriClosePreview } from '../../tauri_commands.ts';
+import { tauriCloseTest, tauriClosePreview, tauriGetProgress } from '../../tauri_commands.ts';
 import { TrialContext, TrialStatus } from "../context.tsx";
 
 import { MosAnswer } from "./mos.tsx";
@@ -18,8 +18,9 @@ export enum AnswerState{
 // 回答欄のコンポーネント========================================================
 interface AnswerProps {
 	preview?: boolean;
+	onFinish?: () => void;  // 指定すると最後の設問の回答後にトライアルを終了せずに呼ぶ(キオスク用)
 }
-export const Answer: FC<AnswerProps> =({preview}) =>{
+export const Answer: FC<AnswerProps> =({preview, onFinish}) =>{
 	const trialContext = useContext(TrialContext);
 	if (trialContext === undefined){
 		return null
@@ -41,7 +42,17 @@ export const Answer: FC<AnswerProps> =({preview}) =>{
 	}
 
 	
-	const onClose = preview === true ? closePreview : closeTrial;
+	const onClose = onFinish ?? (preview === true ? closePreview : closeTrial);
+
+	// 回答がなくても設問やセッションの制限時間がバックエンドで処理されるように進み具合を問い合わせる
+	// (時間切れはtimed_out / finishedイベントで通知される)
+	useEffect(() => {
+		if (preview === true) return;
+		const intervalId = setInterval(() => {
+			tauriGetProgress().catch(() => clearInterval(intervalId));
+		}, 1000);
+		return () => { clearInterval(intervalId); }
+	}, []);
 
 	const scoreMap: {[key: string]: ReactNode} = {
 		"Mos": <MosAnswer onClose={onClose}/>,
@@ -111,4 +122,18 @@ export const ProgressBar: FC<ProgressBarProps> = ({timeLimit, state, onEnd}) =>
 			</div>
 		</div>
   );
-};
\ No newline at end of file
+};
+// 評価についての自由記述欄(任意)========================================================
+interface CommentInputProps {
+	comment: string;
+	setComment: (comment: string) => void;
+}
+export const CommentInput: FC<CommentInputProps> = ({comment, setComment}) => {
+	return (
+		<div className="w-full px-4 mb-6">
+			<p className="text-sm text-gray-500">コメント(任意)</p>
+			<textarea value={comment} onChange={(e) => setComment(e.target.value)} rows={2}
+			className="w-full bg-gray-50 border border-gray-300 rounded-lg px-2 py-1 text-sm"/>
+		</div>
+	);
+};
@@ -1,11 +1,10 @@
 import { useContext, useState, useEffect, ReactNode, FC } from 'react';
 import { PiSpeakerHighFill } from "react-icons/pi";
-import { convertFileSrc } from "@tauri-apps/api/tauri";
 import { Howl } from 'howler';
 
 import { TrialContext } from "../context.tsx";
-import { AnswerState, ProgressBar } from "./answer.tsx";
-import { tauriGetAudio, tauriSetScore } from '../../tauri_commands.ts';
+import { AnswerState, ProgressBar, CommentInput } from "./answer.tsx";
+import { tauriGetAudio, tauriSetScore, tauriReportPlaybackEnd, stimulusSrc, STIMULUS_FORMAT, PlaybackRecord } from '../../tauri_commands.ts';
 
 
 // MOSテストの回答ページ====================================================
@@ -22,28 +21,42 @@ export const MosAnswer: FC<MosAnswerProps>=(props)=>{
 	const [count, setCount] = useState<number>(1);
 	const [selectedScore, setSelectedScore] = useState<number>(3); //3 -> 普通
 	const [sound, setSound] = useState<Howl|undefined>(undefined);
+	const [tokens, setTokens] = useState<string[]>([]);
+	const [presentedAt, setPresentedAt] = useState<Date>(new Date());
+	const [comment, setComment] = useState<string>("");
+	const [playback, setPlayback] = useState<PlaybackRecord>({play_count: 0, full_play_count: 0, played_to_end: false});
 
 
 	// 音声ファイルをバックエンドから取得してくる-----------------------------
 	const getSound= async() => {
-        await tauriGetAudio().then((paths) => {
-            const path = paths[0];
+        await tauriGetAudio().then((_tokens) => {
+            const token = _tokens[0];
             const _sound = new Howl({
-                src: convertFileSrc(path),
-                onend: () => { setState(AnswerState.Answering);} //再生終了したらAnsweringに移行
+                src: stimulusSrc(token),
+                format: STIMULUS_FORMAT,
+                onplay: () => { setPlayback((prev) => ({...prev, play_count: prev.play_count + 1})); },
+                onend: () => {
+                    tauriReportPlaybackEnd(token).catch(() => {});
+                    setPlayback((prev) => ({...prev, full_play_count: prev.full_play_count + 1, played_to_end: true}));
+                    setState(AnswerState.Answering); //再生終了したらAnsweringに移行
+                }
             });
 	    setSound(_sound);
+	    setTokens(_tokens);
+	    setPresentedAt(new Date());
+	    setPlayback({play_count: 0, full_play_count: 0, played_to_end: false});
 		}).catch((err) => console.error(err));
 	};
 
     // スコア保存--------------------------------------------------------------
 	const setScore= async() => {
-		await tauriSetScore([String(selectedScore)]).then((resultStatus) => {
+		await tauriSetScore({ type: "rating", value: selectedScore }, tokens, presentedAt, [playback], comment).then((resultStatus) => {
 			switch (resultStatus) {
             //テスト継続 => カウントアップしてPreparingに戻る----------
 			case "Doing":
 				setCount((prevCount) => prevCount + 1);
 				setSelectedScore(3);
+				setComment("");
 				setState(AnswerState.Preparing);
 				break;
             //テスト終了 => 終了処理----------------------------------
@@ -76,7 +89,7 @@ export const MosAnswer: FC<MosAnswerProps>=(props)=>{
 
 	// 回答のラジオボタンの要素を作成--------------------------------------------
 	const getInput=(): ReactNode[] =>{
-		const label_list: string[] = ["非常に悪い", "悪い", "普通", "良い", "非常に良い"];
+		const label_list: string[] = trialContext.info.display_text?.score_labels ?? ["非常に悪い", "悪い", "普通", "良い", "非常に良い"];
 
 		let l: ReactNode[] = [];
 		for (let [i, label] of label_list.entries()){
@@ -112,6 +125,8 @@ export const MosAnswer: FC<MosAnswerProps>=(props)=>{
 		        </form>
 	        </div>
 	        
+	        {/* コメント欄 */}
+	        <CommentInput comment={comment} setComment={setComment}/>
 	        {/* 回答時間のプログレスバー------------------------------ */}
 		    <ProgressBar timeLimit={trialContext.info.time_limit} state={state}
 		    onEnd={() => setState(AnswerState.Finished)}/>
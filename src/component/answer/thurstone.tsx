@@ -1,12 +1,11 @@
 import { useContext, useState, useEffect, FC } from 'react';
 import { PiSpeakerHighFill } from "react-icons/pi";
-import { convertFileSrc } from "@tauri-apps/api/tauri";
 import { Howl } from 'howler';
 import {Radio, RadioGroup} from '@headlessui/react';
 
 import { TrialContext } from "../context.tsx";
-import { AnswerState, ProgressBar } from "./answer.tsx";
-import { tauriGetAudio, tauriSetScore } from '../../tauri_commands.ts';
+import { AnswerState, ProgressBar, CommentInput } from "./answer.tsx";
+import { tauriGetAudio, tauriSetScore, tauriReportPlaybackEnd, stimulusSrc, STIMULUS_FORMAT, PlaybackRecord } from '../../tauri_commands.ts';
 
 
 interface ThurstoneAnswerProps {
@@ -22,31 +21,57 @@ export const ThurstoneAnswer: FC<ThurstoneAnswerProps> =(props)=>{
 	const [count, setCount] = useState<number>(1);
 	const [selectedScore, setSelectedScore] = useState<'A'|'B'>('A');
 	const [sound, setSound] = useState<Howl[]>([]);
+	const [tokens, setTokens] = useState<string[]>([]);
 	const [ABIndex, setABIndex] = useState<'A'|'B'>('A');  //再生中(再生予定)の音声がABのどちらか
+	const [presentedAt, setPresentedAt] = useState<Date>(new Date());
+	const [comment, setComment] = useState<string>("");
+	const [playback, setPlayback] = useState<PlaybackRecord[]>([]);
+	const [labelA, labelB]: string[] = trialContext.info.display_text?.score_labels ?? ["A", "B"];
+
+	// i番目の刺激の再生記録を更新-----------------------------------------
+	const updatePlayback = (i: number, update: (prev: PlaybackRecord) => PlaybackRecord) => {
+		setPlayback((prev) => prev.map((p, j) => (i === j ? update(p) : p)));
+	};
 
 	// 音声ファイルをバックエンドから取得してくる-----------------------------
 	const getSound= async() => {
-		await tauriGetAudio().then((paths) => {
+		await tauriGetAudio().then((_tokens) => {
 	    const sound_a = new Howl({
-	      src: convertFileSrc(paths[0]),
+	      src: stimulusSrc(_tokens[0]),
+	      format: STIMULUS_FORMAT,
+	      onplay: () => { updatePlayback(0, (p) => ({...p, play_count: p.play_count + 1})); },
           // 再生終了したらReadyに戻して2秒間隔をあける
-	      onend: () => { setTimeout(()=>{setState(AnswerState.Ready); setABIndex("B");}, 2000);}
+	      onend: () => {
+	        tauriReportPlaybackEnd(_tokens[0]).catch(() => {});
+	        updatePlayback(0, (p) => ({...p, full_play_count: p.full_play_count + 1, played_to_end: true}));
+	        setTimeout(()=>{setState(AnswerState.Ready); setABIndex("B");}, 2000);
+	      }
 	    });
 	    const sound_b = new Howl({
-	      src: convertFileSrc(paths[1]),
+	      src: stimulusSrc(_tokens[1]),
+	      format: STIMULUS_FORMAT,
+	      onplay: () => { updatePlayback(1, (p) => ({...p, play_count: p.play_count + 1})); },
           // 再生終了したらAnsweringに移行
-	      onend: () => { setTimeout(()=>{setState(AnswerState.Answering); setABIndex("A");}, 2000);}
+	      onend: () => {
+	        tauriReportPlaybackEnd(_tokens[1]).catch(() => {});
+	        updatePlayback(1, (p) => ({...p, full_play_count: p.full_play_count + 1, played_to_end: true}));
+	        setTimeout(()=>{setState(AnswerState.Answering); setABIndex("A");}, 2000);
+	      }
 	    });
 	    setSound([sound_a, sound_b]);
+	    setTokens(_tokens);
+	    setPresentedAt(new Date());
+	    setPlayback([{play_count: 0, full_play_count: 0, played_to_end: false}, {play_count: 0, full_play_count: 0, played_to_end: false}]);
 		}).catch((err) => console.error(err));
 	};
 
     // スコアを保存-------------------------------------------------------------
 	const setScore= async() => {
-		await tauriSetScore([String(selectedScore)]).then((resultStatus) => {
+		await tauriSetScore({ type: "choice", option: selectedScore }, tokens, presentedAt, playback, comment).then((resultStatus) => {
 			if (resultStatus === "Doing"){
 				setCount((prevCount) => prevCount + 1);
 				setSelectedScore('A');
+				setComment("");
 				setState(AnswerState.Preparing);		
 			}
 			else if (resultStatus === "Done"){
@@ -96,7 +121,7 @@ export const ThurstoneAnswer: FC<ThurstoneAnswerProps> =(props)=>{
 					<PiSpeakerHighFill  size={30} className=
 			    		{(state == AnswerState.AudioPlaying) && ABIndex=="A" ? 
 			    		"animate-pulse text-blue-700" : "opacity-0"}/>
-					<Radio value='A' className="px-8 py-4 rounded-lg cursor-pointer text-center text-2xl bg-gray-50 data-[checked]:bg-blue-500 data-[checked]:text-white">A</Radio>
+					<Radio value='A' className="px-8 py-4 rounded-lg cursor-pointer text-center text-2xl bg-gray-50 data-[checked]:bg-blue-500 data-[checked]:text-white">{labelA}</Radio>
     			</div>
 
           			{/* Bの回答部分-------------------------------- */}
@@ -105,10 +130,12 @@ export const ThurstoneAnswer: FC<ThurstoneAnswerProps> =(props)=>{
 					<PiSpeakerHighFill  size={30} className=
 				    	{(state == AnswerState.AudioPlaying) && ABIndex=="B" ? 
 				    	"animate-pulse text-blue-700" : "opacity-0"}/>
-					<Radio value='B' className="px-8 py-4 rounded-lg cursor-pointer text-center text-2xl bg-gray-50 data-[checked]:bg-blue-500 data-[checked]:text-white">B</Radio>
+					<Radio value='B' className="px-8 py-4 rounded-lg cursor-pointer text-center text-2xl bg-gray-50 data-[checked]:bg-blue-500 data-[checked]:text-white">{labelB}</Radio>
     			</div>
 	      	</RadioGroup>
 
+        	{/* コメント欄 */}
+        	<CommentInput comment={comment} setComment={setComment}/>
         	{/* 回答時間のプログレスバー -------------------------------------- */}
 		    <ProgressBar timeLimit={trialContext.info.time_limit} state={state}
 		    onEnd={() => setState(AnswerState.Finished)}/>
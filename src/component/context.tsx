@@ -1,6 +1,6 @@
 import {createContext, useState, useEffect, useContext, 
         ReactNode, FC, Dispatch, SetStateAction} from 'react';
-import { tauriGetSettings } from '../tauri_commands.ts';
+import { tauriGetSettings, listenTestEvent, listenTrialEvent } from '../tauri_commands.ts';
 
 ///アプリケーション全体の情報を保管するコンテクスト===============================================--
 //テストマネージャのリストを保管
@@ -22,6 +22,21 @@ export const AppProvider: FC<AppProviderProps> = ({ children }) => {
       });
   }, []);
 
+  //他のウィンドウでテストが変更されたり受験が終わったりしたら取得し直す
+  useEffect(() => {
+      const refresh = () => {
+        tauriGetSettings().then((_managers) => setManagers(_managers)).catch((err) => console.error(err));
+      };
+      const unlistenTest = listenTestEvent(() => refresh());
+      const unlistenTrial = listenTrialEvent((event) => {
+        if (event.kind !== "progressed") refresh();
+      });
+      return () => {
+        unlistenTest.then((unlisten) => unlisten());
+        unlistenTrial.then((unlisten) => unlisten());
+      };
+  }, []);
+
   return (
     <AppContext.Provider value={{managers, setManagers}}>
       {children}
@@ -34,6 +49,7 @@ export const AppProvider: FC<AppProviderProps> = ({ children }) => {
 export enum TrialStatus {
   Ready,  // スタート前 => 受験者選択画面
   Doing,  // テスト中
+  Monitoring, // キオスクでテスト中 => 実験者のコンソール
   Finished  //テスト終了
 }
 //型定義-------------------------------------------------------
@@ -49,17 +65,19 @@ interface TrialProviderProps {
   children: ReactNode;
   test: string;
   examinee?: string;
+  info?: {[key: string]: any};  // 指定しなければテストマネージャの情報を使う(キオスクではget_settingsを使えない)
+  initialStatus?: TrialStatus;
 }
 //-------------------------------------------------------------------------
 export const TrialContext = createContext<TrialContextType|undefined>(undefined);
-export const TrialProvider: FC<TrialProviderProps> = ({children, test, examinee }) => {
+export const TrialProvider: FC<TrialProviderProps> = ({children, test, examinee, info: givenInfo, initialStatus }) => {
   const app_context = useContext(AppContext);
 
   const managers = app_context?.managers || {};
   const [testName] = useState<string>(test);
   const [examineeName, setExamineeName] = useState<string|undefined>(examinee);
-  const [info] = useState(managers[test]);
-  const [status, setStatus] = useState<TrialStatus>(TrialStatus.Ready);
+  const [info] = useState(givenInfo ?? managers[test]);
+  const [status, setStatus] = useState<TrialStatus>(initialStatus ?? TrialStatus.Ready);
   const context = {testName, examineeName, setExamineeName, info, status, setStatus}
 
   return (
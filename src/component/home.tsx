@@ -1,15 +1,20 @@
 import { useContext, useEffect, useState, FC } from "react";
+import { confirm } from '@tauri-apps/api/dialog';
 import { useNavigate, useLocation } from 'react-router-dom';
 import { PiBeltDuotone } from "react-icons/pi";
 import { Dialog, DialogPanel } from '@headlessui/react'
 
 import "../App.css";
-import { tauriGetSettings, tauriTestType, testTypeToString } from '../tauri_commands.ts';
+import { tauriGetSettings, tauriTestType, testTypeToString, TestTypeDescription, tauriGetTestTypes,
+         BrokenTest, tauriGetBrokenTests, tauriReloadBrokenTest, tauriRemoveBrokenTest, tauriRestoreTestBackup, errorMessage, testDisplayName } from '../tauri_commands.ts';
 import { AppContext } from "./context.tsx";
-import { TextButton } from "./button.tsx";
+import { TextButton, RemoveButton } from "./button.tsx";
 import { ListElement } from "./list.tsx";
 import { SetupForm } from "./setup_form.tsx";
 import { Setting } from "./setting.tsx";
+import { ParticipantPoolPanel } from "./participant_pool.tsx";
+import { AdminLockButton } from "./admin_lock.tsx";
+import { RecycleBinPanel } from "./recycle_bin.tsx";
 
 //=======================================================================
 export const Home = () => {
@@ -18,13 +23,21 @@ export const Home = () => {
   const appContext = useContext(AppContext);
   if (appContext === undefined) return;
   const {managers, setManagers} = appContext;
+  const [brokenTests, setBrokenTests] = useState<BrokenTest[]>([]);
+
+  const updateManagers = () => {
+    tauriGetSettings().then((_managers) => {
+      setManagers(_managers);
+    });
+    tauriGetBrokenTests().then((_brokenTests) => {
+      setBrokenTests(_brokenTests);
+    });
+  };
 
   //他のページから戻ってきた場合にマネージャを更新する--------------------
   useEffect(() => {
     if (location.pathname === '/') {
-      tauriGetSettings().then((_managers) => {
-        setManagers(_managers);
-      });
+      updateManagers();
     }
   }, [location]);
 
@@ -36,11 +49,17 @@ export const Home = () => {
         <PiBeltDuotone size={30}/>
         <p className="px-3 pr-5 text-2xl text-bold text-black">TLOYT</p>
         <AddTestButton/>
+        <div className="pl-3"><PoolButton/></div>
+        <div className="pl-3"><TrashButton onRestore={updateManagers}/></div>
+        <div className="pl-3"><AdminLockButton/></div>
       </div>
       <div className="m-6 grid grid-cols-3 gap-5 justify-center">
         {managers===undefined ? (null):(Object.entries(managers).map(([name, info]) => (
           <TestComponent key={name} info={info}/>
         )))}
+        {brokenTests.map((brokenTest) => (
+          <BrokenTestComponent key={brokenTest.name} brokenTest={brokenTest} onUpdate={updateManagers}/>
+        ))}
       </div>
     </div>
     );
@@ -56,7 +75,7 @@ const TestComponent: FC<TestComponentProps> = (props) => {
   // jsx---------------------------------------------------------------
   return (
     <div className="px-6 py-4 bg-white rounded-xl shadow-lg flex-col flex">
-      <p className="mb-2 text-xl font-medium text-black">{props.info.name}</p>
+      <p className="mb-2 text-xl font-medium text-black">{testDisplayName(props.info)}</p>
       <div className="flex flex-col space-y-1">
         <ListElement>
           <p className="my-0.5 text-sm text-gray-500">{testTypeToString(props.info.test_type)}</p>
@@ -74,6 +93,58 @@ const TestComponent: FC<TestComponentProps> = (props) => {
 };
 
 
+/*=======================================================================
+読み込めなかったテストの要素(理由の表示と修復操作)
+*/
+interface BrokenTestComponentProps {
+  brokenTest: BrokenTest;
+  onUpdate: () => void;
+}
+const BrokenTestComponent: FC<BrokenTestComponentProps> = ({brokenTest, onUpdate}) => {
+
+  const reload = () => {
+    tauriReloadBrokenTest(brokenTest.name).then(() => onUpdate()).catch((err) => alert(errorMessage(err)));
+  };
+
+  const restoreBackup = async () => {
+    const isOk = await confirm(`${brokenTest.name}の設定をバックアップで置き換えます．よろしいですか？`);
+    if (!isOk) return;
+    tauriRestoreTestBackup(brokenTest.name).then(() => onUpdate()).catch((err) => {
+      alert(errorMessage(err));
+      onUpdate();
+    });
+  };
+
+  const remove = async () => {
+    const isOk = await confirm(`${brokenTest.name}をテストの一覧から外します(データは削除されません)．よろしいですか？`);
+    if (!isOk) return;
+    tauriRemoveBrokenTest(brokenTest.name).then(() => onUpdate()).catch((err) => alert(errorMessage(err)));
+  };
+
+  // jsx---------------------------------------------------------------
+  return (
+    <div className="px-6 py-4 bg-white rounded-xl shadow-lg flex-col flex border-2 border-red-300">
+      <p className="mb-2 text-xl font-medium text-black">{brokenTest.name}</p>
+      <div className="flex flex-col space-y-1">
+        <ListElement>
+          <p className="my-0.5 text-sm text-gray-500">{testTypeToString(brokenTest.test_type)}</p>
+        </ListElement>
+        <ListElement>
+          <p className="my-0.5 text-sm text-red-500 break-all">読み込みに失敗しました: {brokenTest.reason}</p>
+        </ListElement>
+      </div>
+      <div className="pt-4 flex flex-row space-x-2">
+        <TextButton text="再読み込み" type="button" className="py-1 px-2 text-sm" onClick={reload}/>
+        {brokenTest.has_backup ? (
+          <TextButton text="バックアップから復元" type="button" className="py-1 px-2 text-sm" onClick={restoreBackup}/>
+        ) : (null)}
+        <RemoveButton text="一覧から外す" type="button" className="py-1 px-2 text-sm" onClick={remove}/>
+      </div>
+    </div>
+  );
+};
+
+
 //テスト作成のボタン============================================================
 const AddTestButton =()=>{
   const appContext = useContext(AppContext);
@@ -103,11 +174,19 @@ interface TestTypeSelectorProps {
   onChange: (e: React.ChangeEvent<HTMLSelectElement>) => void;
 }
 const TestTypeSelector: FC<TestTypeSelectorProps> =({testType, onChange})=> {
+  const [testTypes, setTestTypes] = useState<TestTypeDescription[]>([]);
+
+  // バックエンドに登録されているテストの種類を取得
+  useEffect(() => {
+    tauriGetTestTypes().then((_testTypes) => setTestTypes(_testTypes)).catch((err) => alert(errorMessage(err)));
+  }, []);
+
   return (
     <div className="text-lg">
       <select value={testType} onChange={onChange}>
-        <option key="Mos" value="Mos" >平均オピニオン評価 (MOS)</option>
-        <option key="Thurstone" value="Thurstone">一対比較法 (サーストン法)</option>
+        {testTypes.map((t) => (
+          <option key={t.id} value={t.id}>{t.display_name}</option>
+        ))}
       </select>
     </div>
   )
@@ -143,6 +222,51 @@ const AddTestModal: FC<AddTestModalProps> = ({isOpen, onClose}) => {
 
 
 
+//参加者プールのボタン==========================================================
+const PoolButton =()=>{
+  const [isOpen, setIsOpen] = useState<boolean>(false);
+
+  // jsx------------------------------------------------------------
+  return (
+    <div>
+      <TextButton text="参加者プール" type='button' onClick={()=>setIsOpen(true)} className="py-2 px-2 font-bold"/>
+      <Dialog open={isOpen} as='div' onClose={()=>setIsOpen(false)}>
+        <div className='fixed inset-0 z-40 bg-[rgb(0_0_0/0.6)] flex justify-center'>
+          <div className="w-5/6 my-5 p-8 bg-white rounded-lg overflow-auto">
+            <DialogPanel>
+              <ParticipantPoolPanel/>
+            </DialogPanel>
+          </div>
+        </div>
+      </Dialog>
+    </div>
+  );
+};
+
+//ごみ箱のボタン==============================================================
+interface TrashButtonProps {
+  onRestore: () => void;
+}
+const TrashButton: FC<TrashButtonProps> =({onRestore})=>{
+  const [isOpen, setIsOpen] = useState<boolean>(false);
+
+  // jsx------------------------------------------------------------
+  return (
+    <div>
+      <TextButton text="ごみ箱" type='button' onClick={()=>setIsOpen(true)} className="py-2 px-2 font-bold"/>
+      <Dialog open={isOpen} as='div' onClose={()=>setIsOpen(false)}>
+        <div className='fixed inset-0 z-40 bg-[rgb(0_0_0/0.6)] flex justify-center'>
+          <div className="w-5/6 my-5 p-8 bg-white rounded-lg overflow-auto">
+            <DialogPanel>
+              <RecycleBinPanel onRestore={onRestore}/>
+            </DialogPanel>
+          </div>
+        </div>
+      </Dialog>
+    </div>
+  );
+};
+
 //=======================================================================
 interface SettingButtonProps {
   info: {[key: string]: any};
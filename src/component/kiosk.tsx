@@ -0,0 +1,199 @@
+import { useContext, useState, useEffect } from 'react';
+import { confirm } from '@tauri-apps/api/dialog';
+
+import "../App.css";
+import { TrialContext, TrialProvider, TrialStatus } from "./context.tsx";
+import { TextButton } from "./button.tsx";
+import { Answer } from './answer/answer.tsx';
+import { MarkdownRenderer } from './markdown.tsx';
+import { TrialView, TrialProgress, tauriGetTrialView, tauriGetProgress, tauriPauseTrial, tauriResumeTrial,
+         tauriSkipItem, tauriAbortTrial, tauriCloseTest, tauriCloseKiosk, listenTrialEvent, listenKioskTrialEvent,
+         errorMessage, participantDisplayName } from '../tauri_commands.ts';
+
+
+/*==============================================================
+受験者用のキオスク(kioskウィンドウ)
+実験者がトライアルを開始すると回答欄を表示し，それ以外は待機画面を表示する
+テストの設定や結果は取得できないので，表示に必要な情報はget_trial_viewで取得する
+*/
+export const Kiosk=()=>{
+	const [view, setView] = useState<TrialView|undefined>(undefined);
+	const [paused, setPaused] = useState<boolean>(false);
+	const [finished, setFinished] = useState<boolean>(false);
+	const [generation, setGeneration] = useState<number>(0);  // 設問が飛ばされたら回答欄を作り直す
+
+	const loadView = () => {
+		tauriGetTrialView().then((_view) => {
+			setView(_view);
+			setPaused(false);
+			setFinished(false);
+		}).catch(() => setView(undefined));
+	};
+
+	useEffect(() => {
+		loadView();
+		const unlisten = listenKioskTrialEvent((event) => {
+			switch (event.kind) {
+			case "started":
+				loadView();
+				break;
+			case "paused":
+				setPaused(true);
+				break;
+			case "resumed":
+				setPaused(false);
+				break;
+			case "skipped":
+			case "timed_out":
+				if (event.progress.phase === null) setFinished(true);
+				else setGeneration((prev) => prev + 1);
+				break;
+			case "finished":
+				setFinished(true);
+				break;
+			case "aborted":
+				setView(undefined);
+				break;
+			}
+		});
+		return () => { unlisten.then((f) => f()); };
+	}, []);
+
+	// jsx---------------------------------------------------------------
+	if (view === undefined) {
+		return (
+			<div className="flex h-screen items-center justify-center text-lg text-gray-500">
+				しばらくお待ちください
+			</div>
+		);
+	}
+	if (finished) {
+		return (
+			<div className="flex h-screen items-center justify-center text-bold text-lg">
+				ご協力ありがとうございました。
+			</div>
+		);
+	}
+	return (
+		<div className="relative flex h-screen items-center justify-center p-8">
+			<TrialProvider test={view.name} info={view} initialStatus={TrialStatus.Doing}>
+				<div className="flex flex-col w-3/5">
+					{view.display_text.instruction ? (
+						<div className="pb-6 prose">
+							<MarkdownRenderer>{view.display_text.instruction}</MarkdownRenderer>
+						</div>
+					) : (null)}
+					<Answer key={generation} onFinish={() => setFinished(true)}/>
+				</div>
+			</TrialProvider>
+			{paused ? (
+				<div className="absolute inset-0 flex items-center justify-center bg-white/90 text-lg">
+					一時停止中です。しばらくお待ちください
+				</div>
+			) : (null)}
+		</div>
+	);
+};
+
+
+/*==============================================================
+実験者のコンソール(キオスクでトライアルを実施している間に実験者のウィンドウに表示)
+進み具合を表示し，一時停止・設問のスキップ・中止ができる
+全ての設問が終わったらトライアルを終了し，終了のイベント(時間切れで終了した場合も含む)でキオスクを閉じる
+*/
+const phaseToString = (phase: TrialProgress["phase"]): string => {
+	switch (phase) {
+	case "warm_up":
+		return "練習";
+	case "scored":
+		return "本番";
+	default:
+		return "終了";
+	}
+};
+
+export const ExperimenterConsole=()=>{
+	const trialContext = useContext(TrialContext);
+	if (trialContext === undefined) return null;
+
+	const [progress, setProgress] = useState<TrialProgress|undefined>(undefined);
+	const [paused, setPaused] = useState<boolean>(false);
+
+	// 全ての設問が終わったら結果を保存する-----------------------------------------
+	const finishTrial = () => {
+		if (trialContext.examineeName === undefined) return;
+		tauriCloseTest(trialContext.examineeName).catch((err) => alert(errorMessage(err)));
+	};
+
+	// トライアルが終了したらキオスクを閉じる----------------------------------------
+	const closeKiosk = () => {
+		tauriCloseKiosk().then(() => {
+			trialContext.setStatus(TrialStatus.Finished);
+		}).catch((err) => alert(errorMessage(err)));
+	};
+
+	useEffect(() => {
+		tauriGetProgress().then((_progress) => setProgress(_progress)).catch((err) => console.error(err));
+		const unlisten = listenTrialEvent((event) => {
+			switch (event.kind) {
+			case "progressed":
+			case "skipped":
+				setProgress(event.progress);
+				if (event.progress.phase === null) finishTrial();
+				break;
+			case "timed_out":
+				setProgress(event.progress);
+				break;
+			case "finished":
+				closeKiosk();
+				break;
+			case "paused":
+				setPaused(true);
+				break;
+			case "resumed":
+				setPaused(false);
+				break;
+			}
+		});
+		return () => { unlisten.then((f) => f()); };
+	}, []);
+
+	const togglePause = () => {
+		const result = paused ? tauriResumeTrial() : tauriPauseTrial();
+		result.catch((err) => alert(errorMessage(err)));
+	};
+
+	const skip = () => {
+		tauriSkipItem().catch((err) => alert(errorMessage(err)));
+	};
+
+	// 中止したトライアルの結果は保存されず，受験者は未受験のまま
+	const abort = async () => {
+		const isOk = await confirm("トライアルを中止しますか？回答は保存されません。");
+		if (isOk == false) return;
+		tauriAbortTrial().then(() => {
+			return tauriCloseKiosk();
+		}).then(() => {
+			trialContext.setStatus(TrialStatus.Ready);
+		}).catch((err) => alert(errorMessage(err)));
+	};
+
+	// jsx---------------------------------------------------------------
+	return (
+		<div className="flex flex-col space-y-6">
+			<p className="text-lg">{participantDisplayName(trialContext.info, trialContext.examineeName)} さんがキオスクで受験中です</p>
+			{progress !== undefined ? (
+				<div className="flex flex-col space-y-2">
+					<p>{Math.min(progress.index + 1, progress.total)} / {progress.total} 問目({phaseToString(progress.phase)})</p>
+					<p className="text-sm text-gray-500">このブロックの残り: {progress.remaining_in_block} 問</p>
+				</div>
+			) : (null)}
+			{paused ? (<p className="text-red-500">一時停止中</p>) : (null)}
+			<div className="flex flex-row space-x-4">
+				<TextButton text={paused ? "再開" : "一時停止"} className="py-2 px-4" onClick={togglePause}/>
+				<TextButton text="この設問を飛ばす" className="py-2 px-4" onClick={skip}/>
+				<TextButton text="中止" className="py-2 px-4" onClick={abort}/>
+			</div>
+		</div>
+	);
+};
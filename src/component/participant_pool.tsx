@@ -0,0 +1,175 @@
+import { useState, useEffect, FC } from "react";
+import { confirm } from '@tauri-apps/api/dialog';
+
+import "../App.css";
+import { ParticipantPool, ParticipantOverview, PoolTestStatus,
+         tauriGetParticipantPool, tauriAddPoolMember, tauriRemovePoolMember,
+         tauriSetPoolGroup, tauriDeletePoolGroup, tauriGetParticipantOverview, errorMessage } from '../tauri_commands.ts';
+import { TextButton, RemoveButton } from "./button.tsx";
+import { ListElement } from "./list.tsx";
+
+const poolTestStatusToString: Record<PoolTestStatus, string> = {
+  "Pending": "未受験", "InProgress": "受験中", "Taken": "受験済み"
+};
+
+/*=======================================================================
+参加者プールの管理パネル
+<メンバーの一覧> <グループの一覧> <選択したメンバーのテスト一覧>
+*/
+export const ParticipantPoolPanel = () => {
+  const [pool, setPool] = useState<ParticipantPool>({members: [], groups: {}});
+  const [selected, setSelected] = useState<string|undefined>(undefined);
+
+  const updatePool = () => {
+    tauriGetParticipantPool().then((_pool) => setPool(_pool)).catch((err) => alert(errorMessage(err)));
+  };
+  useEffect(() => {
+    updatePool();
+  }, []);
+
+  // jsx---------------------------------------------------------------
+  return (
+    <div className="grid grid-cols-3 gap-6">
+      <PoolMembers pool={pool} onUpdate={updatePool} selected={selected} setSelected={setSelected}/>
+      <PoolGroups pool={pool} onUpdate={updatePool}/>
+      {selected === undefined ? (null) : <Overview name={selected}/>}
+    </div>
+  );
+};
+
+//メンバーの一覧と追加・削除===================================================
+interface PoolMembersProps {
+  pool: ParticipantPool;
+  onUpdate: () => void;
+  selected: string | undefined;
+  setSelected: (name: string | undefined) => void;
+}
+const PoolMembers: FC<PoolMembersProps> = ({pool, onUpdate, selected, setSelected}) => {
+  const [currentMember, setCurrentMember] = useState<string>("");
+
+  const addMember = () => {
+    tauriAddPoolMember(currentMember).then(() => {
+      setCurrentMember("");
+      onUpdate();
+    }).catch((err) => alert(errorMessage(err)));
+  };
+
+  const removeMember = async (name: string) => {
+    const isOk = await confirm(`${name}をプールから削除します．よろしいですか？`);
+    if (!isOk) return;
+    tauriRemovePoolMember(name).then(() => {
+      if (selected === name) setSelected(undefined);
+      onUpdate();
+    }).catch((err) => alert(errorMessage(err)));
+  };
+
+  return (
+    <div className="flex flex-col space-y-1">
+      <p className="text-lg font-bold">メンバー</p>
+      {pool.members.map((name) => (
+        <ListElement key={name} invisible={selected !== name}>
+          <div className="flex flex-row justify-between items-center">
+            <p className="pl-2 text-sm cursor-pointer" onClick={() => setSelected(name)}>{name}</p>
+            <RemoveButton text="削除" type="button" className="text-xs px-4 py-1 rounded-md" onClick={() => removeMember(name)}/>
+          </div>
+        </ListElement>
+      ))}
+      <div className="pt-2 flex flex-row space-x-2">
+        <input type="text" value={currentMember} onChange={(e) => setCurrentMember(e.target.value)}
+        className="w-9/12 bg-gray-50 border border-gray-300 rounded-lg px-2 py-1 text-sm"/>
+        <TextButton text="追加" type="button" className="w-3/12 text-xs px-4 py-1 rounded-md"
+        onClick={addMember} disabled={currentMember === ""}/>
+      </div>
+    </div>
+  );
+};
+
+//グループの一覧と作成・削除====================================================
+interface PoolGroupsProps {
+  pool: ParticipantPool;
+  onUpdate: () => void;
+}
+const PoolGroups: FC<PoolGroupsProps> = ({pool, onUpdate}) => {
+  const [groupName, setGroupName] = useState<string>("");
+  const [groupMembers, setGroupMembers] = useState<string[]>([]);
+
+  const toggleMember = (name: string, checked: boolean) => {
+    setGroupMembers(checked ? [...groupMembers, name] : groupMembers.filter((m) => m !== name));
+  };
+
+  // 同じ名前のグループは上書き(このグループを使うテストにはメンバーが追加される)
+  const saveGroup = () => {
+    tauriSetPoolGroup(groupName, groupMembers).then(() => {
+      setGroupName("");
+      setGroupMembers([]);
+      onUpdate();
+    }).catch((err) => alert(errorMessage(err)));
+  };
+
+  const editGroup = (group: string) => {
+    setGroupName(group);
+    setGroupMembers(pool.groups[group]);
+  };
+
+  const deleteGroup = async (group: string) => {
+    const isOk = await confirm(`グループ${group}を削除します．よろしいですか？`);
+    if (!isOk) return;
+    tauriDeletePoolGroup(group).then(() => onUpdate()).catch((err) => alert(errorMessage(err)));
+  };
+
+  return (
+    <div className="flex flex-col space-y-1">
+      <p className="text-lg font-bold">グループ</p>
+      {Object.entries(pool.groups).map(([group, members]) => (
+        <ListElement key={group}>
+          <div className="flex flex-row justify-between items-center">
+            <p className="pl-2 text-sm cursor-pointer" onClick={() => editGroup(group)}>{group} ({members.length}人)</p>
+            <RemoveButton text="削除" type="button" className="text-xs px-4 py-1 rounded-md" onClick={() => deleteGroup(group)}/>
+          </div>
+        </ListElement>
+      ))}
+      <div className="pt-2 flex flex-col space-y-1">
+        <input type="text" value={groupName} onChange={(e) => setGroupName(e.target.value)} placeholder="グループ名"
+        className="bg-gray-50 border border-gray-300 rounded-lg px-2 py-1 text-sm"/>
+        {pool.members.map((name) => (
+          <label key={name} className="flex flex-row space-x-2 items-center text-sm">
+            <input type="checkbox" checked={groupMembers.includes(name)} onChange={(e) => toggleMember(name, e.target.checked)}/>
+            <span>{name}</span>
+          </label>
+        ))}
+        <TextButton text="グループを保存" type="button" className="text-xs px-4 py-1 rounded-md"
+        onClick={saveGroup} disabled={groupName === ""}/>
+      </div>
+    </div>
+  );
+};
+
+//メンバーごとのテストの状態======================================================
+interface OverviewProps {
+  name: string;
+}
+const Overview: FC<OverviewProps> = ({name}) => {
+  const [overview, setOverview] = useState<ParticipantOverview|undefined>(undefined);
+
+  useEffect(() => {
+    tauriGetParticipantOverview(name).then((_overview) => setOverview(_overview)).catch((err) => alert(errorMessage(err)));
+  }, [name]);
+
+  if (overview === undefined) return null;
+  return (
+    <div className="flex flex-col space-y-1">
+      <p className="text-lg font-bold">{overview.name}</p>
+      {overview.groups.length == 0 ? (null) : (
+        <p className="text-sm text-gray-500">グループ: {overview.groups.join(", ")}</p>
+      )}
+      {Object.entries(overview.tests).map(([testName, status]) => (
+        <ListElement key={testName}>
+          <div className="flex flex-row justify-between items-center text-sm">
+            <p className="pl-2">{testName}</p>
+            <p className="text-gray-500">{poolTestStatusToString[status]}</p>
+          </div>
+        </ListElement>
+      ))}
+    </div>
+  );
+};
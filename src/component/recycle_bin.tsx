@@ -0,0 +1,80 @@
+import { useState, useEffect, FC } from "react";
+import { confirm } from '@tauri-apps/api/dialog';
+
+import "../App.css";
+import { TrashEntry, tauriGetTrash, tauriRestoreTrash, tauriPurgeTrash, tauriEmptyTrash,
+         testTypeToString, errorMessage } from '../tauri_commands.ts';
+import { TextButton, RemoveButton } from "./button.tsx";
+import { ListElement } from "./list.tsx";
+
+/*=======================================================================
+ごみ箱の管理パネル
+削除したテストとトライアルの結果を新しい順に表示し，元に戻すか完全に削除する
+*/
+interface RecycleBinPanelProps {
+  onRestore: () => void; // テストの一覧を更新する
+}
+export const RecycleBinPanel: FC<RecycleBinPanelProps> = ({onRestore}) => {
+  const [entries, setEntries] = useState<TrashEntry[]>([]);
+
+  const updateEntries = () => {
+    tauriGetTrash().then((_entries) => setEntries(_entries)).catch((err) => alert(errorMessage(err)));
+  };
+  useEffect(() => {
+    updateEntries();
+  }, []);
+
+  const restore = (entry: TrashEntry) => {
+    tauriRestoreTrash(entry.id).then(() => {
+      updateEntries();
+      onRestore();
+    }).catch((err) => alert(errorMessage(err)));
+  };
+
+  const purge = async (entry: TrashEntry) => {
+    const isOk = await confirm("完全に削除すると元に戻せません．よろしいですか？");
+    if (!isOk) return;
+    tauriPurgeTrash(entry.id).then(() => updateEntries()).catch((err) => alert(errorMessage(err)));
+  };
+
+  const emptyTrash = async () => {
+    const isOk = await confirm("ごみ箱の中身を全て完全に削除します．よろしいですか？");
+    if (!isOk) return;
+    tauriEmptyTrash().then(() => updateEntries()).catch((err) => {
+      alert(errorMessage(err));
+      updateEntries();
+    });
+  };
+
+  // jsx---------------------------------------------------------------
+  return (
+    <div className="flex flex-col">
+      <div className="flex flex-row justify-between items-center mb-4 pb-1 border-b-2">
+        <p className="text-xl font-bold">ごみ箱</p>
+        <RemoveButton text="ごみ箱を空にする" type="button" className="py-1 px-2 text-sm"
+                      disabled={entries.length == 0} onClick={emptyTrash}/>
+      </div>
+      {entries.length == 0 ? (<p className="text-gray-500">ごみ箱は空です</p>) : (null)}
+      <div className="flex flex-col space-y-2">
+        {entries.map((entry) => (
+          <ListElement key={entry.id}>
+            <div className="flex flex-row justify-between items-center w-full">
+              <div className="flex flex-col">
+                {entry.kind == "test" ? (
+                  <p>テスト: {entry.test_name} ({testTypeToString(entry.test_type)})</p>
+                ) : (
+                  <p>結果: {entry.test_name} / {entry.examinee}{entry.session !== null && entry.session.index > 1 ? ` (${entry.session.index}回目)` : ""}</p>
+                )}
+                <p className="text-sm text-gray-500">削除日時: {new Date(entry.deleted_at).toLocaleString()}</p>
+              </div>
+              <div className="flex flex-row space-x-2">
+                <TextButton text="元に戻す" type="button" className="py-1 px-2 text-sm" onClick={() => restore(entry)}/>
+                <RemoveButton text="完全に削除" type="button" className="py-1 px-2 text-sm" onClick={() => purge(entry)}/>
+              </div>
+            </div>
+          </ListElement>
+        ))}
+      </div>
+    </div>
+  );
+};
@@ -3,8 +3,9 @@ import { useNavigate } from 'react-router-dom';
 import { Radio, RadioGroup } from '@headlessui/react'
 
 import "../App.css";
-import { tauriGetSettings, tauriDeleteTest, tauriEditTest, 
-  tauriDeleteTrial, tauriStartPreview, tauriClosePreview } from '../tauri_commands.ts';
+import { tauriGetSettings, tauriDeleteTest, tauriEditTest, tauriRenameTest, 
+  tauriDeleteTrial, tauriStartPreview, tauriClosePreview, SessionRecord, errorMessage,
+  testDisplayName, participantDisplayName } from '../tauri_commands.ts';
 import { AppContext, SettingContext, SettingProvider, TrialProvider } from "./context.tsx";
 import { TextButton, RemoveButton} from "./button.tsx";
 import { ListElement } from "./list.tsx";
@@ -107,9 +108,25 @@ const EditPanel = () => {
   const settingContext = useContext(SettingContext);
   if (settingContext === undefined) return null;
   const info = settingContext.info;
+  const [newName, setNewName] = useState<string>(testDisplayName(info));
+
+  // テスト名の変更(データのディレクトリも移動する)---------------------------------
+  const renameButtonHandler = async ()=> {
+    const isOk = await confirm(`テスト名を${newName}に変更してもよろしいですか？`);
+    if (isOk == false) return null;
+
+    tauriRenameTest(info.name, newName).then(() => {
+      return tauriGetSettings()
+    }).then((managers) => {
+      appContext.setManagers(managers);
+    }).catch((err) => {
+      alert(errorMessage(err));
+      console.error(err);
+    });
+  }
 
   const removeButtonHandler = async ()=> {
-    const isOk = await confirm("テストを削除してもよろしいですか？");
+    const isOk = await confirm("テストを削除してもよろしいですか？(ごみ箱から元に戻せます)");
     if (isOk == false) return null;
 
     tauriDeleteTest(info.name).then(() => {
@@ -117,12 +134,18 @@ const EditPanel = () => {
     }).then((managers) => {                               //取得した情報からホームを更新
       appContext.setManagers(managers);
     }).catch((err) => {
-      alert(err);
+      alert(errorMessage(err));
       console.error(err);
     });
   }
   return (
     <div className="flex flex-col space-y-12">
+      <div className="flex flex-row space-x-2 items-center">
+        <input type="text" value={newName} onChange={(e) => setNewName(e.target.value)}
+        className="w-9/12 bg-gray-50 border border-gray-300 rounded-lg px-2 py-1"/>
+        <TextButton text='名前を変更' type='button' className="w-3/12 py-1 px-4 text-sm" onClick={renameButtonHandler}
+        disabled={newName === "" || newName === testDisplayName(info)}/>
+      </div>
       <SetupForm testType={info.test_type} edit defaultInfo={info} id="edit"/>
       <div className="flex flex-row space-x-4 justify-around border-t-4 pt-8">
         <TextButton type="submit" text='保存' form="edit" className="w-1/3 py-2 px-4 font-bold"/>
@@ -155,7 +178,7 @@ const ParticipantsPanel= () => {
     }).then((managers) => {                               //取得した情報からホームを更新
         appContext.setManagers(managers);
     }).catch((err) => {
-        alert(err);
+        alert(errorMessage(err));
         console.error(err);
     });
   }
@@ -164,7 +187,8 @@ const ParticipantsPanel= () => {
     <div>
       {(Object.entries(info.participants) as [string, "Done"|"Yet"][]).map(([name, status]) => (
         <div key={name} className="">
-          <Participant participantName={name} participantStatus={status}/>
+          <Participant participantName={name} participantStatus={status}
+                       sessions={info.sessions?.[name] ?? []} sessionsPerParticipant={info.sessions_per_participant ?? 1}/>
         </div>
       ))}
       
@@ -183,8 +207,10 @@ const ParticipantsPanel= () => {
 interface ParticipantProps{
   participantName: string;
   participantStatus: "Done" | "Yet";
+  sessions: SessionRecord[];        // 受験済みのセッション
+  sessionsPerParticipant: number;   // 参加者ごとの受験回数
 }
-const Participant: FC<ParticipantProps> =({participantName, participantStatus})=> {
+const Participant: FC<ParticipantProps> =({participantName, participantStatus, sessions, sessionsPerParticipant})=> {
   const navigate = useNavigate();
 
   const appContext = useContext(AppContext);
@@ -193,6 +219,7 @@ const Participant: FC<ParticipantProps> =({participantName, participantStatus})=
   const settingContext = useContext(SettingContext);
   if (settingContext === undefined) return null;
   const info = settingContext.info;
+  const displayName = participantDisplayName(info, participantName);
 
   //テストを開始---------------------------------------------------
   const openTest = ()=> {
@@ -201,7 +228,7 @@ const Participant: FC<ParticipantProps> =({participantName, participantStatus})=
 
 //参加者を削除---------------------------------------------------
   const deleteParticipant= async () => {
-    const isOk = await confirm(`${participantName}を削除します．よろしいですか？`)
+    const isOk = await confirm(`${displayName}を削除します．よろしいですか？`)
     if (isOk == false) return;
 
     let setupInfo = getDefaultSetupValue(info.test_type, info);
@@ -213,14 +240,15 @@ const Participant: FC<ParticipantProps> =({participantName, participantStatus})=
     }).then((managers) => {                               //取得した情報からホームを更新
         appContext.setManagers(managers);
     }).catch((err) => {
-        alert(err);
+        alert(errorMessage(err));
         console.error(err);
     });
   }
 
   //テストの結果を削除------------------------------------------------------
   const delteTrial = async ()=> {
-    const isOk = await confirm(`${participantName}のテストの結果を削除します．よろしいですか？`)
+    const target = sessionsPerParticipant > 1 ? `${sessions[sessions.length - 1]?.index}回目の` : "";
+    const isOk = await confirm(`${displayName}の${target}テストの結果を削除します(ごみ箱から元に戻せます)．よろしいですか？`)
     if (isOk == false) return;
 
     tauriDeleteTrial(info.name, participantName).then(() => {       //作成したら情報を取得
@@ -228,7 +256,7 @@ const Participant: FC<ParticipantProps> =({participantName, participantStatus})=
     }).then((managers) => {                               //取得した情報からホームを更新
       appContext.setManagers(managers);
     }).catch((err) => {
-      alert(err);
+      alert(errorMessage(err));
       console.error(err);
     });
   }
@@ -238,14 +266,17 @@ const Participant: FC<ParticipantProps> =({participantName, participantStatus})=
     let statusLabel: ReactNode;
     let actionButton: ReactNode;
 
+    // 複数回受験するテストでは受験済みの回数を表示する
+    const count = sessionsPerParticipant > 1 ? ` ${sessions.length}/${sessionsPerParticipant}回` : "";
+
     switch(participantStatus){
       case "Yet": {
-        statusLabel = <div className="rounded-lg p-2 text-xs text-white bg-[#FF7777] flex items-center justify-center">未受験</div>
+        statusLabel = <div className="rounded-lg p-2 text-xs text-white bg-[#FF7777] flex items-center justify-center">未受験{count}</div>
         actionButton = <TextButton text="テストを受ける" className="text-sm p-2 w-full" onClick={openTest}/>
         break;
       }
       case "Done": {
-        statusLabel = <div className="rounded-lg p-2 text-xs text-white bg-[#399918] flex items-center justify-center">受験済</div>
+        statusLabel = <div className="rounded-lg p-2 text-xs text-white bg-[#399918] flex items-center justify-center">受験済{count}</div>
         actionButton = <RemoveButton text="結果を削除" className="w-full text-sm p-2" onClick={delteTrial}/>
         break;
       }
@@ -259,7 +290,10 @@ const Participant: FC<ParticipantProps> =({participantName, participantStatus})=
         <div className="w-32">
           {actionButton}
         </div>
-        <RemoveButton text="削除" className="text-sm p-2" disabled={participantStatus == "Done" ? true : false} onClick={deleteParticipant}/>
+        {participantStatus == "Yet" && sessions.length > 0 ? (
+          <RemoveButton text="最新の結果を削除" className="text-sm p-2" onClick={delteTrial}/>
+        ) : (null)}
+        <RemoveButton text="削除" className="text-sm p-2" disabled={sessions.length > 0} onClick={deleteParticipant}/>
       </div>
     )
   }
@@ -269,7 +303,7 @@ const Participant: FC<ParticipantProps> =({participantName, participantStatus})=
     <div className="py-2 flex flex-row items-center border-b">
       <div className="w-2/5">
       <ListElement>
-        {participantName}
+        {displayName}
       </ListElement>
       </div>
       <ParticipantAction/>
@@ -1,11 +1,12 @@
 import { useContext, useState, FC, useEffect } from "react";
 import { useForm, SubmitHandler, FormProvider, useFormContext } from "react-hook-form";
-import { open } from '@tauri-apps/api/dialog';
 import { basename } from '@tauri-apps/api/path';
 import { overrideTailwindClasses } from "tailwind-override";
 
 import "../App.css";
-import { tauriGetSettings, tauriTestType, tauriAddTest, tauriEditTest } from '../tauri_commands.ts';
+import { confirm } from '@tauri-apps/api/dialog';
+import { DisplayText, tauriGetSettings, tauriPickDirectory, tauriTestType, tauriAddTest, tauriEditTest, tauriForkTest,
+         ParticipantPool, tauriGetParticipantPool, errorMessage, isApplicationError } from '../tauri_commands.ts';
 import { AppContext } from "./context.tsx";
 import { TextButton, RemoveButton } from "./button.tsx";
 import { ListElement } from "./list.tsx";
@@ -19,9 +20,34 @@ interface SetupInfoBase {
     author: string;
     description: string;
     participants: string[];
+    participant_groups: string[];
+    sessions_per_participant: number;
     categories: [string, string][];
     time_limit: number;
+    trial_rule: TrialRule;
+    loudness_normalization: LoudnessNormalization | null;
+    transcode_unplayable: boolean;
+    pre_test: PreTestSetting;
+    post_test: {[key: string]: any}[];
+    display_text: DisplayText;
   }
+// バックエンドで強制するトライアルのルール(制限時間は秒．nullなら制限なし)
+interface TrialRule {
+  min_full_listens: number;
+  item_time_limit: number | null;
+  session_time_limit: number | null;
+}
+// カテゴリのコピー時のラウドネス正規化(nullなら正規化しない)
+interface LoudnessNormalization {
+  target_lufs: number;
+  true_peak_ceiling_dbtp: number;
+}
+// 受験前アンケートと同意の設定
+interface PreTestSetting {
+  require_consent: boolean;
+  consent_text: string;
+  questions: {[key: string]: any}[];
+}
 // MOS
 interface MosSetupInfo extends SetupInfoBase {
   num_repeat: number;
@@ -30,29 +56,63 @@ interface ThurstoneSetupInfo extends SetupInfoBase {
 }
 export type SetupInfo = MosSetupInfo | ThurstoneSetupInfo;
 
+// 空でもよい項目
+const optionalProperties: string[] = ["loudness_normalization", "participant_groups", "post_test"];
+
+// 標準の属性に関する質問(idはバックエンドで受験者の記録の各項目に格納される)
+const standardQuestions = [
+  {id: "age_range", label: "年齢", kind: {Choice: ["19歳以下", "20代", "30代", "40代", "50代", "60歳以上"]}, required: true},
+  {id: "native_language", label: "母語", kind: "Text", required: true},
+  {id: "hearing_impairment", label: "聴覚に障害がありますか", kind: "YesNo", required: true},
+  {id: "headphone_model", label: "使用しているヘッドホンの型番", kind: "Text", required: false},
+];
+// 標準の受験後アンケート
+const standardPostTestQuestions = [
+  {id: "fatigue", label: "疲労を感じましたか", kind: {Likert: {points: 5, min_label: "全く感じない", max_label: "とても感じた"}}, required: true},
+  {id: "difficulty", label: "評価は難しかったですか", kind: {Likert: {points: 5, min_label: "簡単", max_label: "難しい"}}, required: true},
+  {id: "playback_device", label: "再生機器", kind: {Choice: ["ヘッドホン", "イヤホン", "スピーカー", "その他"]}, required: true},
+  {id: "free_comment", label: "その他のコメント", kind: "Text", required: false},
+];
+
 // 各インターフェースのデフォルト値を設定=======================================================
 export const getDefaultSetupValue= (testType: tauriTestType, info?: {[key: string]: any}) => {
 
-  let defaultValues: SetupInfo = {name: "", author: "", description: "", participants: [], categories: [], time_limit: 5};
+  let defaultValues: SetupInfo = {name: "", author: "", description: "", participants: [], participant_groups: [], sessions_per_participant: 1, categories: [], time_limit: 5,
+    trial_rule: {min_full_listens: 0, item_time_limit: null, session_time_limit: null},
+    loudness_normalization: null, transcode_unplayable: false,
+    pre_test: {require_consent: false, consent_text: "", questions: []}, post_test: [],
+    display_text: {instruction: "", score_labels: [], start_button: "テストを開始", submit_button: "送信"}};
 
   if (info !== undefined) {
     defaultValues.name = info.name;
     defaultValues.author = info.author;
     defaultValues.description = info.description;
     defaultValues.participants = Object.keys(info.participants);
+    if (info.participant_groups !== undefined) defaultValues.participant_groups = info.participant_groups;
+    if (info.sessions_per_participant !== undefined) defaultValues.sessions_per_participant = info.sessions_per_participant;
     defaultValues.categories = info.categories["names"].map((name: string, index: number) => 
       [name, info.categories["original_paths"][index]]
     );
     defaultValues.time_limit = info.time_limit;
+    if (info.trial_rule !== undefined) defaultValues.trial_rule = info.trial_rule;
+    if (info.loudness_normalization !== undefined) defaultValues.loudness_normalization = info.loudness_normalization;
+    if (info.transcode_unplayable !== undefined) defaultValues.transcode_unplayable = info.transcode_unplayable;
+    if (info.pre_test !== undefined) defaultValues.pre_test = info.pre_test;
+    if (info.post_test !== undefined) defaultValues.post_test = info.post_test;
+    if (info.display_text !== undefined) defaultValues.display_text = info.display_text;
   }
   
   switch (testType) {
     case "Mos": {
       const defaultNumRepeatValue = 2;
       defaultValues = {...defaultValues, num_repeat: (info === undefined ? defaultNumRepeatValue : info.num_repeat)};
+      if (defaultValues.display_text.score_labels.length == 0)
+        defaultValues.display_text.score_labels = ["非常に悪い", "悪い", "普通", "良い", "非常に良い"];
       break;
     }
     case "Thurstone": {
+      if (defaultValues.display_text.score_labels.length == 0)
+        defaultValues.display_text.score_labels = ["A", "B"];
       break;
     }
   }
@@ -85,14 +145,33 @@ export const SetupForm: FC<SetupFormComponentProps> = ({testType, edit, defaultI
 
     //フォームのデータに空の要素が含まれているかどうか-------------------------------------
     const hasEmptyProperty= (data: SetupInfo): boolean => {
-      for (let v of Object.values(data)){
+      for (let [k, v] of Object.entries(data)){
+        if (optionalProperties.includes(k)) continue
+        // 参加者はグループを指定していれば空でもよい
+        if (k === "participants" && data.participant_groups.length > 0) continue
         if (v === undefined || v === null) return true
         if (typeof v === 'string') if (v == "") return true
-        if (typeof v === 'object') if (v.length == 0) return true
+        if (Array.isArray(v)) if (v.length == 0) return true
       }
       return false
     }
   
+    //結果のあるテストへの構造的な変更は，確認して新しいバージョンとして複製する--------------------------
+    const editOrFork = async (data: SetupInfo, setupInfoJSON: string): Promise<void> => {
+      try {
+        await tauriEditTest(defaultValues.name, setupInfoJSON);
+      } catch (err) {
+        const isStructural = isApplicationError(err) && err.code === "STRUCTURAL_EDIT";
+        if (isStructural === false) throw err;
+        let version = 2;
+        while (appContext.managers?.[`${defaultValues.name}_v${version}`] !== undefined) version++;
+        const newName = `${defaultValues.name}_v${version}`;
+        const isOk = await confirm(`このテストには結果があるため変更できません．${newName}として複製しますか？`);
+        if (isOk == false) return;
+        await tauriForkTest(defaultValues.name, JSON.stringify({...data, name: newName}));
+      }
+    };
+
     //テスト作成ボタンのサブミットハンドラ--------------------------------------------------      
     const submitHandler: SubmitHandler<SetupInfo>= (data: SetupInfo) => {
       
@@ -105,14 +184,14 @@ export const SetupForm: FC<SetupFormComponentProps> = ({testType, edit, defaultI
       let setupInfoJSON = JSON.stringify(data);
       console.log("send setup form:", data);
       //テストをバックエンドで作成-----------------------------------
-      const result = edit? tauriEditTest(defaultValues.name, setupInfoJSON) : tauriAddTest(testType, setupInfoJSON);
+      const result = edit? editOrFork(data, setupInfoJSON) : tauriAddTest(testType, setupInfoJSON);
 
       result.then(() => {       //作成したら情報を取得
         return tauriGetSettings()
       }).then((managers) => {                               //取得した情報からホームを更新
         appContext.setManagers(managers);
       }).catch((err) => {
-        alert(err);
+        alert(errorMessage(err));
         console.error(err);
       });
     };
@@ -142,8 +221,16 @@ export const SetupForm: FC<SetupFormComponentProps> = ({testType, edit, defaultI
               <p className={labelStyle}>説明</p>
               <textarea className={overrideTailwindClasses(`${inputStyle} w-full`)} rows={6} {...register("description")}/>
             </div>
-            <CategoryInput edit={edit}/>
+            <CategoryInput/>
             <ParticipantsInput edit={edit}/>
+            <div>
+              <p className={labelStyle}>参加者ごとの受験回数</p>
+              <div className="flex flex-row space-x-2 place-items-center">
+                <input type="number" min={1} className={overrideTailwindClasses(`${inputStyle} w-1/3`)}
+                {...register("sessions_per_participant", {valueAsNumber: true})}/>
+                <p>回</p>
+              </div>
+            </div>
             <div>
               <p className={labelStyle}>制限時間</p>
               <div className="flex flex-row space-x-2 place-items-center">
@@ -152,6 +239,10 @@ export const SetupForm: FC<SetupFormComponentProps> = ({testType, edit, defaultI
                 <p>秒</p>
               </div>
             </div>
+            <TrialRuleInput/>
+            <LoudnessInput edit={edit}/>
+            <PreTestInput/>
+            <DisplayTextInput/>
             <TestSpecificInput testType={testType}/>
         </form>
       </FormProvider>
@@ -159,10 +250,8 @@ export const SetupForm: FC<SetupFormComponentProps> = ({testType, edit, defaultI
 }
 
 // カテゴリの選択================================================================================
-interface CategoryInputProps {
-  edit: boolean
-}
-const CategoryInput: FC<CategoryInputProps> = ({edit})=> {
+// 編集時も変更できるが，結果のあるテストでは新しいバージョンとして複製される
+const CategoryInput = ()=> {
   const [categories, setCategories] = useState<[string, string][]>([]);
   const {setValue, getValues} = useFormContext();
 
@@ -191,8 +280,8 @@ const CategoryInput: FC<CategoryInputProps> = ({edit})=> {
   }
   //ファイルダイアログを開く----------------------------------------------------
   const openDialoge= async() => {
-    const path = await open({directory: true})
-    if (typeof(path) !== 'string' ) return;
+    const path = await tauriPickDirectory().catch((err) => { alert(errorMessage(err)); return null; });
+    if (path === null) return;
     if (path === '') return;
     if (categories.some(([_name, _path]) => _path.includes(path) )){
       alert('カテゴリが重複しています');
@@ -213,9 +302,7 @@ const CategoryInput: FC<CategoryInputProps> = ({edit})=> {
     <div>
       <div className="flex flex-row space-x-2 place-items-center">
         <p className={labelStyle}>カテゴリ</p>
-        {edit ? (null) : 
         <TextButton text='選択' type='button' className="text-xs px-4 py-1 rounded-md" onClick={openDialoge}/>
-        }
       </div>
       <div className="pt-4 flex flex-col space-y-1">
         {Object.entries(categories).map(([i, category]) => (
@@ -224,9 +311,9 @@ const CategoryInput: FC<CategoryInputProps> = ({edit})=> {
               <p className="text-xs">{category[1]}</p>
               <div className="flex flex-row space-x-2 justify-between place-items-center">
                 <input type="text" defaultValue={category[0]} className={overrideTailwindClasses(`${inputStyle} w-10/12 text-sm`)} 
-                onChange={(e) => changeCategoryName(Number(i), e.target.value)} disabled={edit}/>
+                onChange={(e) => changeCategoryName(Number(i), e.target.value)}/>
                 <RemoveButton text='削除' className='w-2/12 text-xs px-4 py-1 rounded-md' type='button' 
-                onClick={() => {removeCategory(Number(i))}} disabled={edit}/>
+                onClick={() => {removeCategory(Number(i))}}/>
               </div>
             </div>
           </ListElement>))}    
@@ -242,11 +329,13 @@ interface ParticipantsInputProps {
 const ParticipantsInput: FC<ParticipantsInputProps> =({edit})=> {
   const [participants, setParticipants] = useState<string[]>([]);
   const [currentParticipant, setCurrentParticipant] = useState<string>("");
-  const { setValue, getValues } = useFormContext();
+  const [pool, setPool] = useState<ParticipantPool>({members: [], groups: {}});
+  const { setValue, getValues, register } = useFormContext();
 
   useEffect(() => {
     const defaultParticipants = getValues("participants");
     updateParticipants(defaultParticipants);
+    tauriGetParticipantPool().then((_pool) => setPool(_pool)).catch((err) => console.error(err));
   }, []);
 
   const updateParticipants=(newParticipants: string[]) => {
@@ -300,15 +389,172 @@ const ParticipantsInput: FC<ParticipantsInputProps> =({edit})=> {
       {edit ? (null) :
         <div className="mt-2 flex flex-row space-x-2 justify-between">
           <input type='text' value={currentParticipant} onChange={(e) => setCurrentParticipant(e.target.value)} 
-           className={overrideTailwindClasses(`${inputStyle} w-10/12 text-sm`)}/>
+           list="pool-members" className={overrideTailwindClasses(`${inputStyle} w-10/12 text-sm`)}/>
+          <datalist id="pool-members">
+            {pool.members.map((name) => <option key={name} value={name}/>)}
+          </datalist>
           <TextButton text="追加" type='button' onClick={()=>addParticipant()} className="w-2/12 text-xs px-4 py-1 rounded-md" disabled={currentParticipant == ""}/>
         </div>
       }
+
+      {/* 参加者プールのグループ(メンバーは保存時に参加者に追加される) */}
+      {Object.keys(pool.groups).length == 0 ? (null) : (
+        <div className="mt-2 flex flex-row flex-wrap gap-x-4">
+          {Object.keys(pool.groups).map((group) => (
+            <label key={group} className="flex flex-row space-x-1 items-center text-sm">
+              <input type='checkbox' value={group} {...register("participant_groups")}/>
+              <span>{group}</span>
+            </label>
+          ))}
+        </div>
+      )}
     </div>
   )
 }
 
 
+// トライアルのルールの設定(空欄の制限時間は制限なし)==========================================
+const TrialRuleInput=()=>{
+  const { register } = useFormContext();
+  return (
+    <div className="flex flex-col space-y-2">
+      <div>
+        <p className={labelStyle}>回答前に最後まで再生する回数</p>
+        <div className="flex flex-row space-x-2 place-items-center">
+          <input type='number' className={overrideTailwindClasses(`${inputStyle} w-4/12`)}
+          {...register("trial_rule.min_full_listens", {valueAsNumber: true})}/>
+          <p>回</p>
+        </div>
+      </div>
+      <div>
+        <p className={labelStyle}>1問あたりの最大時間</p>
+        <div className="flex flex-row space-x-2 place-items-center">
+          <input type='number' className={overrideTailwindClasses(`${inputStyle} w-4/12`)}
+          {...register("trial_rule.item_time_limit", {setValueAs: (v) => v === "" || v === null ? null : Number(v)})}/>
+          <p>秒</p>
+        </div>
+      </div>
+      <div>
+        <p className={labelStyle}>テスト全体の最大時間</p>
+        <div className="flex flex-row space-x-2 place-items-center">
+          <input type='number' className={overrideTailwindClasses(`${inputStyle} w-4/12`)}
+          {...register("trial_rule.session_time_limit", {setValueAs: (v) => v === "" || v === null ? null : Number(v)})}/>
+          <p>秒</p>
+        </div>
+      </div>
+    </div>
+  )
+}
+
+// ラウドネス正規化の設定(編集時は変更不可)===================================================
+interface LoudnessInputProps {
+  edit: boolean;
+}
+const LoudnessInput: FC<LoudnessInputProps> = ({edit}) => {
+  const { register, setValue, watch } = useFormContext();
+  const option = watch("loudness_normalization");
+
+  const toggle = (enabled: boolean) => {
+    setValue("loudness_normalization", enabled ? {target_lufs: -23, true_peak_ceiling_dbtp: -1} : null);
+  }
+
+  return (
+    <div>
+      <div className="flex flex-row space-x-2 place-items-center">
+        <input type='checkbox' checked={option !== null} onChange={(e) => toggle(e.target.checked)} disabled={edit}/>
+        <p className={labelStyle}>ラウドネスを正規化してコピー</p>
+      </div>
+      {option === null ? (null) : (
+        <div className="pt-2 flex flex-row space-x-2 place-items-center">
+          <input type='number' step='0.1' className={overrideTailwindClasses(`${inputStyle} w-3/12`)} disabled={edit}
+          {...register("loudness_normalization.target_lufs", {valueAsNumber: true})}/>
+          <p>LUFS</p>
+          <input type='number' step='0.1' className={overrideTailwindClasses(`${inputStyle} w-3/12`)} disabled={edit}
+          {...register("loudness_normalization.true_peak_ceiling_dbtp", {valueAsNumber: true})}/>
+          <p>dBTP</p>
+        </div>
+      )}
+      <div className="pt-2 flex flex-row space-x-2 place-items-center">
+        <input type='checkbox' disabled={edit} {...register("transcode_unplayable")}/>
+        <p className={labelStyle}>再生できない形式(AIFF, M4Aなど)をwavに変換してコピー</p>
+      </div>
+    </div>
+  )
+}
+
+// 受験前・受験後アンケートと同意の設定===================================================
+const PreTestInput=()=>{
+  const { register, setValue, watch } = useFormContext();
+  const questions = watch("pre_test.questions");
+  const requireConsent = watch("pre_test.require_consent");
+  const postTest = watch("post_test");
+
+  const toggleQuestions = (enabled: boolean) => {
+    setValue("pre_test.questions", enabled ? standardQuestions : []);
+  }
+
+  const togglePostTest = (enabled: boolean) => {
+    setValue("post_test", enabled ? standardPostTestQuestions : []);
+  }
+
+  return (
+    <div>
+      <div className="flex flex-row space-x-2 place-items-center">
+        <input type='checkbox' checked={questions.length > 0} onChange={(e) => toggleQuestions(e.target.checked)}/>
+        <p className={labelStyle}>受験前に属性(年齢，母語，聴覚，ヘッドホン)を尋ねる</p>
+      </div>
+      <div className="pt-2 flex flex-row space-x-2 place-items-center">
+        <input type='checkbox' {...register("pre_test.require_consent")}/>
+        <p className={labelStyle}>受験前に同意を求める</p>
+      </div>
+      {requireConsent ? (
+        <div className="pt-2">
+          <p className={labelStyle}>同意書</p>
+          <textarea className={overrideTailwindClasses(`${inputStyle} w-full`)} rows={4} {...register("pre_test.consent_text")}/>
+        </div>
+      ) : (null)}
+      <div className="pt-2 flex flex-row space-x-2 place-items-center">
+        <input type='checkbox' checked={postTest.length > 0} onChange={(e) => togglePostTest(e.target.checked)}/>
+        <p className={labelStyle}>受験後にアンケート(疲労，難しさ，再生機器)をおこなう</p>
+      </div>
+    </div>
+  )
+}
+
+// 受験者に表示する文言の設定=============================================================
+const DisplayTextInput=()=>{
+  const { register, getValues } = useFormContext();
+  const scoreLabels: string[] = getValues("display_text.score_labels");
+
+  return (
+    <div className="flex flex-col space-y-2">
+      <div>
+        <p className={labelStyle}>回答中に表示する説明</p>
+        <textarea className={overrideTailwindClasses(`${inputStyle} w-full`)} rows={3} {...register("display_text.instruction")}/>
+      </div>
+      <div>
+        <p className={labelStyle}>評価のラベル</p>
+        <div className="flex flex-row space-x-2">
+          {scoreLabels.map((_, i) => (
+            <input key={i} type='text' className={overrideTailwindClasses(`${inputStyle} w-full text-sm`)}
+            {...register(`display_text.score_labels.${i}`)}/>
+          ))}
+        </div>
+      </div>
+      <div className="flex flex-row space-x-4">
+        <div className="w-1/2">
+          <p className={labelStyle}>開始ボタン</p>
+          <input type='text' className={overrideTailwindClasses(`${inputStyle} w-full`)} {...register("display_text.start_button")}/>
+        </div>
+        <div className="w-1/2">
+          <p className={labelStyle}>アンケートの送信ボタン</p>
+          <input type='text' className={overrideTailwindClasses(`${inputStyle} w-full`)} {...register("display_text.submit_button")}/>
+        </div>
+      </div>
+    </div>
+  )
+}
+
 //テストタイプに応じた設定========================================================
 interface TestSpecificInputProps {
   testType: tauriTestType;
@@ -1,14 +1,15 @@
-import { useContext, useState, ReactNode, ChangeEvent, FC } from 'react';
+import { useContext, useState, useEffect, ReactNode, ChangeEvent, FC } from 'react';
 import { useParams, useNavigate } from 'react-router-dom';
 import { IoIosArrowRoundBack } from "react-icons/io";
-import { invoke } from "@tauri-apps/api/tauri";
 
 import "../App.css";
 import { TrialContext, TrialProvider, TrialStatus } from "./context.tsx";
 import { TextButton } from "./button.tsx";
 import { Answer } from './answer/answer.tsx';
-import { testTypeToString, tauriStartTest } from '../tauri_commands.ts';
+import { testTypeToString, tauriStartTest, tauriRegisterParticipant, tauriSubmitPostTest, tauriOpenKiosk,
+         listenTrialEvent, errorMessage, participantDisplayName, testDisplayName } from '../tauri_commands.ts';
 import { MarkdownRenderer } from './markdown.tsx';
+import { ExperimenterConsole } from './kiosk.tsx';
 
 
 /*==============================================================
@@ -74,8 +75,8 @@ const TestAbstract=() =>{
 	// jsx---------------------------------------------------------------
 	return (
 		<div className="overflow-auto flex flex-col">
-			{trialContext.status!=TrialStatus.Doing ? (<BackToHomeButton/>) : (null)}
-			<p className="text-xl text-left font-medium text-black">{trialContext.testName}</p>
+			{trialContext.status==TrialStatus.Ready || trialContext.status==TrialStatus.Finished ? (<BackToHomeButton/>) : (null)}
+			<p className="text-xl text-left font-medium text-black">{testDisplayName(trialContext.info)}</p>
 			<p className="text-left text-gray-400 pb-5 border-b-2">{testTypeToString(trialContext.info.test_type)}</p>
 			<div className="pt-5 prose">
 				<MarkdownRenderer>{trialContext.info.description}</MarkdownRenderer>
@@ -103,25 +104,49 @@ const BackToHomeButton=()=>{
 テストが始まっていない		-> 受験者の選択画面を表示
 テスト中							-> 回答欄を表示
 テストが終了					-> 協力ありがとうございましたの画面
+設問が時間切れで進んだら回答欄を作り直し，時間切れでトライアルが終了したら終了の画面にする
 */
 const AnswerComponent=()=>{
 	const trialContext = useContext(TrialContext);
 	if (trialContext === undefined){
 		return null;
 	}
+	const [generation, setGeneration] = useState<number>(0);
+
+	useEffect(() => {
+		if (trialContext.status != TrialStatus.Doing) return;
+		const unlisten = listenTrialEvent((event) => {
+			switch (event.kind) {
+			case "timed_out":
+				setGeneration((prev) => prev + 1);
+				break;
+			case "finished":
+				trialContext.setStatus(TrialStatus.Finished);
+				break;
+			}
+		});
+		return () => { unlisten.then((f) => f()); };
+	}, [trialContext.status]);
 
 	const elem = () => {
 		switch (trialContext.status) {
 		case TrialStatus.Ready:
 			return <ReadyTrial/>
 		case TrialStatus.Doing:
-			return <Answer/>
-		case TrialStatus.Finished:
 			return (
-				<div className="text-center text-bold text-lg">
-					ご協力ありがとうございました。
+				<div className="flex flex-col">
+					{trialContext.info.display_text?.instruction ? (
+						<div className="pb-6 prose">
+							<MarkdownRenderer>{trialContext.info.display_text.instruction}</MarkdownRenderer>
+						</div>
+					) : (null)}
+					<Answer key={generation}/>
 				</div>
 			)
+		case TrialStatus.Monitoring:
+			return <ExperimenterConsole/>
+		case TrialStatus.Finished:
+			return <FinishedTrial/>
 		} 
 	}
 
@@ -143,12 +168,18 @@ const ReadyTrial=()=>{
 
 	const defaultExaminee = trialContext.examineeName !== undefined ? trialContext.examineeName : "undefined";
 	const [selectedExaminee, setSelectedExaminee] = useState<string>(defaultExaminee);
+	const [answers, setAnswers] = useState<{[key: string]: string}>({});
+	const [consent, setConsent] = useState<boolean>(false);
+
+	// 受験前アンケートが設定されているか(以前のテストには設定がない)
+	const preTest = trialContext.info.pre_test;
+	const hasPreTest = preTest !== undefined && (preTest.require_consent || preTest.questions.length > 0);
 
 	// 受験者を選択するセレクタのオプションとなるReactNodeのリストを生成---------------
 	const getParticipantOption = ()=>{
 		let l: ReactNode[] = [<option key="" value="undefined">受験者を選択</option>]
 		for (let [name, status] of Object.entries(trialContext.info.participants)) {
-			if (status!='Done') l.push(<option key={name} value={name}>{name}</option>);
+			if (status!='Done') l.push(<option key={name} value={name}>{participantDisplayName(trialContext.info, name)}</option>);
 		}
 		return l
 	};
@@ -157,16 +188,21 @@ const ReadyTrial=()=>{
 	    setSelectedExaminee(event.target.value);
 	};
 
-	// テストを開始する----------------------------------------------------
-	const startTrial= async ()=>{
+	// テストを開始する(kioskなら受験者用のキオスクで回答してもらう)-----------------------
+	const startTrial= async (kiosk: boolean)=>{
 	    if (selectedExaminee !== "undefined") {
-			await invoke("start_test", {test_name: trialContext.testName, examinee: selectedExaminee })
-			.catch(err => console.error("Error invoking start_test:", err));
-			tauriStartTest(trialContext.testName, selectedExaminee).then(() => {
+			// 受験前アンケートの回答を送ってからテストを開始
+			const register = hasPreTest ?
+				tauriRegisterParticipant(trialContext.testName, selectedExaminee, answers, consent) : Promise.resolve();
+			register.then(() => {
+				return tauriStartTest(trialContext.testName, selectedExaminee);
+			}).then(() => {
+				return kiosk ? tauriOpenKiosk() : Promise.resolve();
+			}).then(() => {
 				trialContext.setExamineeName(selectedExaminee);
-				trialContext.setStatus(TrialStatus.Doing);
+				trialContext.setStatus(kiosk ? TrialStatus.Monitoring : TrialStatus.Doing);
 			}).catch((e) => {
-				alert(e);
+				alert(errorMessage(e));
 			});
 	    } else {
 	      alert("受験者を選択してください");
@@ -175,13 +211,152 @@ const ReadyTrial=()=>{
 
 	// jsx---------------------------------------------------------------
 	return (
-		<div className="flex flex-row space-x-8 justify-center">
-			<select name="examinee" id="examinee-select"
-			onChange={handleSelectChange} value={selectedExaminee}
-			className="px-3 border-2 rounded-lg">
-			  {getParticipantOption()}
+		<div className="flex flex-col space-y-6">
+			{hasPreTest ? (
+				<PreTestForm setting={preTest} answers={answers} setAnswers={setAnswers}
+				consent={consent} setConsent={setConsent}/>
+			) : (null)}
+			<div className="flex flex-row space-x-8 justify-center">
+				<select name="examinee" id="examinee-select"
+				onChange={handleSelectChange} value={selectedExaminee}
+				className="px-3 border-2 rounded-lg">
+				  {getParticipantOption()}
+				</select>
+				<TextButton text={trialContext.info.display_text?.start_button ?? "テストを開始"}  className="py-2 px-4 font-bold" onClick={() => startTrial(false)}/>
+				<TextButton text="キオスクで開始" className="py-2 px-4" onClick={() => startTrial(true)}/>
+			</div>
+		</div>
+	);
+};
+
+/*=======================================================================
+テスト終了後の画面
+受験後アンケートが設定されていれば回答してもらい，その後お礼を表示
+*/
+const FinishedTrial=()=>{
+	const trialContext = useContext(TrialContext);
+	if (trialContext === undefined) return null;
+
+	const postTest: {[key: string]: any}[] = trialContext.info.post_test ?? [];
+	const [answers, setAnswers] = useState<{[key: string]: string}>({});
+	const [submitted, setSubmitted] = useState<boolean>(postTest.length == 0);
+
+	const submit = () => {
+		if (trialContext.examineeName === undefined) return;
+		tauriSubmitPostTest(trialContext.testName, trialContext.examineeName, answers).then(() => {
+			setSubmitted(true);
+		}).catch((e) => {
+			alert(errorMessage(e));
+		});
+	};
+
+	// jsx---------------------------------------------------------------
+	if (submitted) {
+		return (
+			<div className="text-center text-bold text-lg">
+				ご協力ありがとうございました。
+			</div>
+		);
+	}
+	return (
+		<div className="flex flex-col space-y-6">
+			<p className="text-lg">最後にアンケートへの回答をお願いします。</p>
+			<QuestionList questions={postTest} answers={answers} setAnswers={setAnswers}/>
+			<div className="flex flex-row justify-center">
+				<TextButton text={trialContext.info.display_text?.submit_button ?? "送信"} className="py-2 px-4 font-bold" onClick={submit}/>
+			</div>
+		</div>
+	);
+};
+
+/*=======================================================================
+受験前アンケートのコンポーネント
+質問の一覧と，同意のチェックボックスを表示
+*/
+interface PreTestFormProps {
+	setting: {[key: string]: any};
+	answers: {[key: string]: string};
+	setAnswers: (answers: {[key: string]: string}) => void;
+	consent: boolean;
+	setConsent: (consent: boolean) => void;
+}
+const PreTestForm: FC<PreTestFormProps> = ({setting, answers, setAnswers, consent, setConsent}) => {
+	// jsx---------------------------------------------------------------
+	return (
+		<div className="flex flex-col space-y-3">
+			<QuestionList questions={setting.questions} answers={answers} setAnswers={setAnswers}/>
+			{setting.require_consent ? (
+				<div className="flex flex-col space-y-2">
+					<div className="prose text-sm">
+						<MarkdownRenderer>{setting.consent_text}</MarkdownRenderer>
+					</div>
+					<label className="flex flex-row space-x-2 items-center">
+						<input type="checkbox" checked={consent} onChange={(e) => setConsent(e.target.checked)}/>
+						<span>上記に同意します</span>
+					</label>
+				</div>
+			) : (null)}
+		</div>
+	);
+};
+
+/*=======================================================================
+アンケートの質問の一覧
+質問の形式(Text, Choice, YesNo, Likert)に応じた入力欄を表示
+*/
+interface QuestionListProps {
+	questions: {[key: string]: any}[];
+	answers: {[key: string]: string};
+	setAnswers: (answers: {[key: string]: string}) => void;
+}
+const QuestionList: FC<QuestionListProps> = ({questions, answers, setAnswers}) => {
+	const setAnswer = (id: string, value: string) => {
+		setAnswers({...answers, [id]: value});
+	};
+
+	const getInput = (question: {[key: string]: any}) => {
+		const value = answers[question.id] ?? "";
+		if (question.kind === "Text") {
+			return <input type="text" value={value} className="px-2 py-1 border-2 rounded-lg"
+				onChange={(e) => setAnswer(question.id, e.target.value)}/>
+		}
+		if (question.kind.Likert !== undefined) {
+			const {points, min_label, max_label} = question.kind.Likert;
+			return (
+				<div className="flex flex-row space-x-4 items-center text-sm">
+					<span>{min_label}</span>
+					{[...Array(points)].map((_, i) => (
+						<label key={i} className="flex flex-col items-center">
+							<input type="radio" name={question.id} checked={value === String(i+1)}
+								onChange={() => setAnswer(question.id, String(i+1))}/>
+							<span>{i+1}</span>
+						</label>
+					))}
+					<span>{max_label}</span>
+				</div>
+			);
+		}
+		const options: [string, string][] = question.kind === "YesNo" ?
+			[["yes", "はい"], ["no", "いいえ"]] :
+			question.kind.Choice.map((o: string) => [o, o]);
+		return (
+			<select value={value} className="px-3 border-2 rounded-lg"
+				onChange={(e) => setAnswer(question.id, e.target.value)}>
+				<option value="">選択してください</option>
+				{options.map(([v, label]) => <option key={v} value={v}>{label}</option>)}
 			</select>
-			<TextButton text="テストを開始"  className="py-2 px-4 font-bold" onClick={startTrial}/>
+		);
+	};
+
+	// jsx---------------------------------------------------------------
+	return (
+		<div className="flex flex-col space-y-3">
+			{questions.map((question) => (
+				<div key={question.id} className="flex flex-col">
+					<p className="text-sm">{question.label}{question.required ? " *" : ""}</p>
+					{getInput(question)}
+				</div>
+			))}
 		</div>
 	);
-};
\ No newline at end of file
+};
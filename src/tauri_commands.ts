@@ -1,7 +1,25 @@
-import { invoke } from "@tauri-apps/api/tauri";
+import { invoke, convertFileSrc } from "@tauri-apps/api/tauri";
+import { listen, UnlistenFn } from "@tauri-apps/api/event";
 
 export type tauriTestType = "Mos" | "Thurstone";
 
+//バックエンドから返るエラー(codeでエラーの種類を判別する．paramsはメッセージに埋め込まれた値)
+export interface ApplicationError {
+  code: string;
+  params: string[];
+  message: string;
+  message_ja: string;
+}
+
+export const isApplicationError = (err: unknown): err is ApplicationError => {
+  return typeof err === "object" && err !== null && "code" in err && "message_ja" in err;
+};
+
+//画面に表示するエラーメッセージ
+export const errorMessage = (err: unknown): string => {
+  return isApplicationError(err) ? err.message_ja : String(err);
+};
+
 export const testTypeToString = (testType: tauriTestType): string => {
   switch (testType) {
     case "Mos":
@@ -11,6 +29,15 @@ export const testTypeToString = (testType: tauriTestType): string => {
   }
 };
 
+//入力された表示名(記録する前のテストや参加者プールから加えた参加者はファイル名に使う名前)--------
+export const testDisplayName = (info: { [key: string]: any }): string => {
+  return info.display_name ?? info.name;
+};
+
+export const participantDisplayName = (info: { [key: string]: any }, participant: string): string => {
+  return info.participant_records?.[participant]?.display_name ?? participant;
+};
+
 //テストマネージャをバックエンドから取得する関数================================
 export const tauriGetSettings = (): Promise<{ [key: string]: any }> => {
   return invoke<string[]>("get_settings")
@@ -53,6 +80,31 @@ export const tauriEditTest = async (
     });
 };
 
+export const tauriRenameTest = async (
+  testName: string,
+  newName: string
+): Promise<void> => {
+  return invoke("rename_test", { test_name: testName, new_name: newName })
+    .then(() => {})
+    .catch((err) => {
+      console.error(err);
+      throw err;
+    });
+};
+
+//結果のあるテストを新しいバージョンとして複製する(jsonStringの名前は新しいテスト名)
+export const tauriForkTest = async (
+  testName: string,
+  jsonString: string
+): Promise<void> => {
+  return invoke("fork_test", { test_name: testName, json_string: jsonString })
+    .then(() => {})
+    .catch((err) => {
+      console.error(err);
+      throw err;
+    });
+};
+
 export const tauriDeleteTest = async (testName: string): Promise<void> => {
   return invoke("delete_test", { test_name: testName })
     .then(() => {})
@@ -62,6 +114,42 @@ export const tauriDeleteTest = async (testName: string): Promise<void> => {
     });
 };
 
+export const tauriRegisterParticipant = async (
+  testName: string,
+  examineeName: string,
+  answers: { [key: string]: string },
+  consent: boolean
+): Promise<void> => {
+  return invoke("register_participant", {
+    test_name: testName,
+    examinee: examineeName,
+    answers: answers,
+    consent: consent,
+  })
+    .then(() => {})
+    .catch((err) => {
+      console.error(err);
+      throw err;
+    });
+};
+
+export const tauriSubmitPostTest = async (
+  testName: string,
+  examineeName: string,
+  answers: { [key: string]: string }
+): Promise<void> => {
+  return invoke("submit_post_test", {
+    test_name: testName,
+    examinee: examineeName,
+    answers: answers,
+  })
+    .then(() => {})
+    .catch((err) => {
+      console.error(err);
+      throw err;
+    });
+};
+
 export const tauriStartTest = async (
   testName: string,
   examineeName: string
@@ -74,10 +162,11 @@ export const tauriStartTest = async (
     });
 };
 
+//現在の設問の音声のトークンを取得する(ファイルパスは返ってこない)==========================
 export const tauriGetAudio = (): Promise<string[]> => {
   return invoke<string[]>("get_audio")
-    .then((paths) => {
-      return paths;
+    .then((tokens) => {
+      return tokens;
     })
     .catch((err) => {
       console.error(err);
@@ -85,8 +174,57 @@ export const tauriGetAudio = (): Promise<string[]> => {
     });
 };
 
-export const tauriSetScore = (score: string[]): Promise<string> => {
-  return invoke<string>("set_score", { score: score })
+//音声のトークンから再生用のURLを生成する(stimulusプロトコル)
+export const stimulusSrc = (token: string): string => {
+  return convertFileSrc(token, "stimulus");
+};
+
+//トークンには拡張子がないので，Howlerに渡す形式を固定する
+//(Web Audioでデコードするので実際の形式はファイルの中身から判別される)
+export const STIMULUS_FORMAT = ["wav"];
+
+//音声が最後まで再生されたことをバックエンドに伝える(最後まで再生された回数はバックエンドで数える)
+export const tauriReportPlaybackEnd = async (token: string): Promise<void> => {
+  return invoke("report_playback_end", { token: token })
+    .then(() => {})
+    .catch((err) => {
+      console.error(err);
+      throw err;
+    });
+};
+
+//刺激ごとの再生記録----------------------------------------------------------
+export interface PlaybackRecord {
+  play_count: number;
+  full_play_count: number;
+  played_to_end: boolean;
+}
+
+//回答の内容(typeで種類を指定する．設問の種類と合わない回答はエラーになる)
+export type Answer =
+  | { type: "rating"; value: number }
+  | { type: "choice"; option: string }
+  | { type: "multi_rating"; values: number[] }
+  | { type: "ranking"; order: string[] }
+  | { type: "text"; text: string };
+
+// tokensは回答した設問でtauriGetAudioから受け取ったトークン(時間切れで進んだ後の回答は記録されない)
+export const tauriSetScore = (
+  answer: Answer,
+  tokens: string[],
+  presentedAt: Date,
+  playback: PlaybackRecord[],
+  comment?: string
+): Promise<string> => {
+  const input = {
+    answer: answer,
+    tokens: tokens,
+    presented_at: presentedAt.toISOString(),
+    answered_at: new Date().toISOString(),
+    playback: playback,
+    comment: comment === undefined || comment === "" ? null : comment,
+  };
+  return invoke<string>("set_score", { score: input })
     .then((status) => {
       return status;
     })
@@ -96,6 +234,217 @@ export const tauriSetScore = (score: string[]): Promise<string> => {
     });
 };
 
+//トライアルの進み具合(phase / item_typeは全ての設問に回答済みならnull)=========================
+export type ItemPhase = "warm_up" | "scored";
+export type ItemType =
+  | { type: "rating"; min: number; max: number }
+  | { type: "choice"; options: string[] }
+  | { type: "multi_rating"; count: number; min: number; max: number }
+  | { type: "ranking"; options: string[] }
+  | { type: "text" };
+
+export interface TrialProgress {
+  index: number;
+  total: number;
+  phase: ItemPhase | null;
+  item_type: ItemType | null;
+  remaining_in_block: number;
+}
+
+export const tauriGetProgress = (): Promise<TrialProgress> => {
+  return invoke<TrialProgress>("get_progress").catch((err) => {
+    console.error(err);
+    throw err;
+  });
+};
+
+//実験者のコンソールからのトライアルの操作==================================================
+export const tauriPauseTrial = async (): Promise<void> => {
+  return invoke("pause_trial")
+    .then(() => {})
+    .catch((err) => {
+      console.error(err);
+      throw err;
+    });
+};
+
+export const tauriResumeTrial = async (): Promise<void> => {
+  return invoke("resume_trial")
+    .then(() => {})
+    .catch((err) => {
+      console.error(err);
+      throw err;
+    });
+};
+
+export const tauriSkipItem = (): Promise<string> => {
+  return invoke<string>("skip_item").catch((err) => {
+    console.error(err);
+    throw err;
+  });
+};
+
+export const tauriAbortTrial = async (): Promise<void> => {
+  return invoke("abort_trial")
+    .then(() => {})
+    .catch((err) => {
+      console.error(err);
+      throw err;
+    });
+};
+
+//受験者用のキオスク===========================================================
+//キオスクに表示する実施中のテストの情報(カテゴリや参加者は含まない)
+export interface TrialView {
+  name: string;
+  display_name: string | null;
+  test_type: tauriTestType;
+  description: string;
+  time_limit: number;
+  display_text: DisplayText;
+}
+
+export const tauriGetTrialView = (): Promise<TrialView> => {
+  return invoke<TrialView>("get_trial_view").catch((err) => {
+    console.error(err);
+    throw err;
+  });
+};
+
+export const tauriOpenKiosk = async (): Promise<void> => {
+  return invoke("open_kiosk")
+    .then(() => {})
+    .catch((err) => {
+      console.error(err);
+      throw err;
+    });
+};
+
+// カテゴリのフォルダを選ぶ(キャンセルした場合はnull)
+export const tauriPickDirectory = async (): Promise<string | null> => {
+  return invoke<string | null>("pick_directory")
+    .catch((err) => {
+      console.error(err);
+      throw err;
+    });
+};
+
+export const tauriCloseKiosk = async (): Promise<void> => {
+  return invoke("close_kiosk")
+    .then(() => {})
+    .catch((err) => {
+      console.error(err);
+      throw err;
+    });
+};
+
+// 参加者の1回分の受験(セッション)の記録(以前の結果は時刻が不明)
+export interface SessionRecord {
+  index: number;
+  started_at: string | null;
+  finished_at: string | null;
+}
+
+// ごみ箱(削除したテストとトライアルの結果)
+export type TrashEntry = {
+  id: string;
+  deleted_at: string;
+} & (
+  | { kind: "test"; test_name: string; test_type: tauriTestType }
+  | { kind: "trial"; test_name: string; examinee: string; session: SessionRecord | null }
+);
+
+export const tauriGetTrash = (): Promise<TrashEntry[]> => {
+  return invoke<TrashEntry[]>("get_trash").catch((err) => {
+    console.error(err);
+    throw err;
+  });
+};
+
+export const tauriRestoreTrash = async (id: string): Promise<void> => {
+  return invoke("restore_trash", { id: id })
+    .then(() => {})
+    .catch((err) => {
+      console.error(err);
+      throw err;
+    });
+};
+
+export const tauriPurgeTrash = async (id: string): Promise<void> => {
+  return invoke("purge_trash", { id: id })
+    .then(() => {})
+    .catch((err) => {
+      console.error(err);
+      throw err;
+    });
+};
+
+export const tauriEmptyTrash = async (): Promise<void> => {
+  return invoke("empty_trash")
+    .then(() => {})
+    .catch((err) => {
+      console.error(err);
+      throw err;
+    });
+};
+
+// 管理者のPIN(enabled: PINが設定されているか，unlocked: 管理操作ができるか)
+export interface AdminStatus {
+  enabled: boolean;
+  unlocked: boolean;
+  unlocked_until: string | null;
+  locked_out_until: string | null; // PINの入力に続けて失敗した場合，入力を受け付けない期限
+}
+
+export const tauriGetAdminStatus = (): Promise<AdminStatus> => {
+  return invoke<AdminStatus>("get_admin_status").catch((err) => {
+    console.error(err);
+    throw err;
+  });
+};
+
+export const tauriUnlockAdmin = async (pin: string): Promise<void> => {
+  return invoke("unlock_admin", { pin: pin })
+    .then(() => {})
+    .catch((err) => {
+      console.error(err);
+      throw err;
+    });
+};
+
+export const tauriLockAdmin = async (): Promise<void> => {
+  return invoke("lock_admin")
+    .then(() => {})
+    .catch((err) => {
+      console.error(err);
+      throw err;
+    });
+};
+
+// pinがnullの場合はPINを削除する
+export const tauriSetAdminPin = async (pin: string | null): Promise<void> => {
+  return invoke("set_admin_pin", { pin: pin })
+    .then(() => {})
+    .catch((err) => {
+      console.error(err);
+      throw err;
+    });
+};
+
+// 集計のための結果(管理者のPINでロックされている場合はエラー)
+// key: 結果ファイルの名前，scores: 評価結果の配列(テストの種類ごとに形式が異なる)
+export interface TrialResult {
+  key: string;
+  scores: unknown[];
+}
+
+export const tauriGetResults = (testName: string): Promise<TrialResult[]> => {
+  return invoke<TrialResult[]>("get_results", { test_name: testName }).catch((err) => {
+    console.error(err);
+    throw err;
+  });
+};
+
 export const tauriCloseTest = async (examineeName: string): Promise<void> => {
   return invoke("close_test", { examinee: examineeName })
     .then(() => {})
@@ -134,3 +483,188 @@ export const tauriClosePreview = async (): Promise<void> => {
       throw err;
     });
 };
+
+//参加者プール================================================================
+export interface ParticipantPool {
+  members: string[];
+  groups: { [group: string]: string[] };
+}
+export type PoolTestStatus = "Pending" | "InProgress" | "Taken";
+export interface ParticipantOverview {
+  name: string;
+  groups: string[];
+  tests: { [testName: string]: PoolTestStatus };
+}
+
+export const tauriGetParticipantPool = (): Promise<ParticipantPool> => {
+  return invoke<ParticipantPool>("get_participant_pool").catch((err) => {
+    console.error(err);
+    throw err;
+  });
+};
+
+export const tauriAddPoolMember = async (name: string): Promise<void> => {
+  return invoke("add_pool_member", { name: name })
+    .then(() => {})
+    .catch((err) => {
+      console.error(err);
+      throw err;
+    });
+};
+
+export const tauriRemovePoolMember = async (name: string): Promise<void> => {
+  return invoke("remove_pool_member", { name: name })
+    .then(() => {})
+    .catch((err) => {
+      console.error(err);
+      throw err;
+    });
+};
+
+export const tauriSetPoolGroup = async (
+  group: string,
+  members: string[]
+): Promise<void> => {
+  return invoke("set_pool_group", { group: group, members: members })
+    .then(() => {})
+    .catch((err) => {
+      console.error(err);
+      throw err;
+    });
+};
+
+export const tauriDeletePoolGroup = async (group: string): Promise<void> => {
+  return invoke("delete_pool_group", { group: group })
+    .then(() => {})
+    .catch((err) => {
+      console.error(err);
+      throw err;
+    });
+};
+
+export const tauriGetParticipantOverview = (
+  name: string
+): Promise<ParticipantOverview> => {
+  return invoke<ParticipantOverview>("get_participant_overview", { name: name }).catch(
+    (err) => {
+      console.error(err);
+      throw err;
+    }
+  );
+};
+
+// 読み込みに失敗したテスト===========================================================
+export interface BrokenTest {
+  name: string;
+  test_type: tauriTestType;
+  reason: string;
+  has_backup: boolean;
+}
+
+export const tauriGetBrokenTests = (): Promise<BrokenTest[]> => {
+  return invoke<BrokenTest[]>("get_broken_tests").catch((err) => {
+    console.error(err);
+    throw err;
+  });
+};
+
+export const tauriReloadBrokenTest = async (testName: string): Promise<void> => {
+  return invoke("reload_broken_test", { test_name: testName })
+    .then(() => {})
+    .catch((err) => {
+      console.error(err);
+      throw err;
+    });
+};
+
+export const tauriRemoveBrokenTest = async (testName: string): Promise<void> => {
+  return invoke("remove_broken_test", { test_name: testName })
+    .then(() => {})
+    .catch((err) => {
+      console.error(err);
+      throw err;
+    });
+};
+
+export const tauriRestoreTestBackup = async (testName: string): Promise<void> => {
+  return invoke("restore_test_backup", { test_name: testName })
+    .then(() => {})
+    .catch((err) => {
+      console.error(err);
+      throw err;
+    });
+};
+
+// テストの種類の説明(method_settingは方式固有の設定項目と既定値)================================
+// 受験者に表示する文言(score_labelsはMOSでは1から5，サーストン法ではA, Bの順)
+export interface DisplayText {
+  instruction: string;
+  score_labels: string[];
+  start_button: string;
+  submit_button: string;
+}
+
+export interface TestTypeDescription {
+  id: tauriTestType;
+  display_name: string;
+  num_score_labels: number;
+  default_display_text: DisplayText;
+  method_setting: { [key: string]: any };
+}
+
+export const tauriGetTestTypes = (): Promise<TestTypeDescription[]> => {
+  return invoke<TestTypeDescription[]>("get_test_types").catch((err) => {
+    console.error(err);
+    throw err;
+  });
+};
+
+// バックエンドから送られるイベント(実験者のウィンドウに届く)==================================
+export type TestEvent =
+  | { kind: "added"; test_name: string }
+  | { kind: "edited"; test_name: string }
+  | { kind: "deleted"; test_name: string }
+  | { kind: "renamed"; test_name: string; new_name: string };
+
+export type TrialEvent =
+  | { kind: "started"; test_name: string; examinee: string }
+  | { kind: "progressed"; test_name: string; examinee: string; progress: TrialProgress }
+  | { kind: "finished"; test_name: string; examinee: string }
+  | { kind: "paused"; test_name: string; examinee: string }
+  | { kind: "resumed"; test_name: string; examinee: string }
+  | { kind: "skipped"; test_name: string; examinee: string; progress: TrialProgress }
+  | { kind: "timed_out"; test_name: string; examinee: string; progress: TrialProgress }
+  | { kind: "aborted"; test_name: string; examinee: string };
+
+// キオスクに届くトライアルのイベント(受験者名とテスト名は含まない)
+export type KioskTrialEvent =
+  | { kind: "started" }
+  | { kind: "progressed"; progress: TrialProgress }
+  | { kind: "finished" }
+  | { kind: "paused" }
+  | { kind: "resumed" }
+  | { kind: "skipped"; progress: TrialProgress }
+  | { kind: "timed_out"; progress: TrialProgress }
+  | { kind: "aborted" };
+
+// 結果のエクスポートはまだないので，jobはコピーと検証のみ
+export type JobEvent = {
+  job: "copying" | "validation";
+  test_name: string | null;
+} & ({ status: "running" } | { status: "succeeded" } | { status: "failed"; error: string });
+
+export const listenTestEvent = (handler: (event: TestEvent) => void): Promise<UnlistenFn> => {
+  return listen<TestEvent>("test-changed", (event) => handler(event.payload));
+};
+
+export const listenTrialEvent = (handler: (event: TrialEvent) => void): Promise<UnlistenFn> => {
+  return listen<TrialEvent>("trial-changed", (event) => handler(event.payload));
+};
+
+export const listenKioskTrialEvent = (handler: (event: KioskTrialEvent) => void): Promise<UnlistenFn> => {
+  return listen<KioskTrialEvent>("trial-changed", (event) => handler(event.payload));
+};
+
+export const listenJobEvent = (handler: (event: JobEvent) => void): Promise<UnlistenFn> => {
+  return listen<JobEvent>("job-status", (event) => handler(event.payload));
+};